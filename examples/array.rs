@@ -11,7 +11,7 @@ fn main() {
 
 fn test_array_apply_inplace() {
     let mut array = Array::Int8(ArrayData::from_vec(vec![1,2,3]));
-    array.apply_inplace(|x: &mut i8| *x = *x*2).unwrap();
+    array.apply_inplace(|x: &mut i8| *x *= 2).unwrap();
     println!("{:?}", array);
     fn test_fn(x: &mut i8) {
         *x = x.pow(2);
@@ -0,0 +1,136 @@
+//! `#[derive(DataFrameRow)]` for llamas2.
+//!
+//! Generates `IntoDataFrame` and `FromDataFrame` for a struct with named
+//! fields, building one column per field without going through serde.
+//! Each field's Array variant is picked at compile time by its
+//! `llamas2::row::Column` impl, so an unsupported field type is a
+//! compile error:
+//!
+//! ```compile_fail
+//! #[macro_use]
+//! extern crate llamas2_derive;
+//! extern crate llamas2;
+//!
+//! #[derive(DataFrameRow)]
+//! struct Point {
+//!     coords: Vec<f64>,
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! A column can be given a different name than its field with
+//! `#[llamas(rename = "...")]`.
+
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+extern crate syn;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Fields, LitStr};
+
+#[proc_macro_derive(DataFrameRow, attributes(llamas))]
+pub fn derive_dataframe_row(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    expand(&input).unwrap_or_else(compile_error).into()
+}
+
+// Like `syn::Error::to_compile_error`, but without the `::core` path,
+// which 2015 edition crates can't name.
+fn compile_error(err: syn::Error) -> TokenStream2 {
+    err.into_iter().map(|err| {
+        let message = err.to_string();
+        quote_spanned!(err.span()=> compile_error!(#message);)
+    }).collect()
+}
+
+struct Field<'a> {
+    ident: &'a syn::Ident,
+    ty: &'a syn::Type,
+    column: String,
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream2, syn::Error> {
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => return Err(syn::Error::new(Span::call_site(), "DataFrameRow needs a struct with named fields")),
+        },
+        _ => return Err(syn::Error::new(Span::call_site(), "DataFrameRow can only be derived for structs")),
+    };
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new(input.generics.span(), "DataFrameRow can't be derived for generic structs"));
+    }
+
+    let mut columns: Vec<Field> = vec![];
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named field");
+        let column = column_name(field)?.unwrap_or_else(|| ident.to_string());
+        if columns.iter().any(|c| c.column == column) {
+            return Err(syn::Error::new(field.span(), format!("duplicate column name {}", column)));
+        }
+        columns.push(Field { ident, ty: &field.ty, column });
+    }
+
+    let name = &input.ident;
+    let idents: Vec<_> = columns.iter().map(|f| f.ident).collect();
+    let names: Vec<_> = columns.iter().map(|f| &f.column).collect();
+    // spanned to the field type, so a missing Column impl points at it
+    let into_arrays = columns.iter().map(|f| {
+        let (ident, ty) = (f.ident, f.ty);
+        quote_spanned!(ty.span()=> <#ty as ::llamas2::row::Column>::into_array(#ident))
+    });
+    let from_arrays = columns.iter().map(|f| {
+        let (ident, ty, column) = (f.ident, f.ty, &f.column);
+        quote_spanned!(ty.span()=>
+            let mut #ident = ::llamas2::row::column::<#ty>(__df, #column, __len)?.into_iter();
+        )
+    });
+
+    // locals are prefixed so they can't clash with field names
+    Ok(quote! {
+        impl ::llamas2::row::IntoDataFrame for #name {
+            fn into_dataframe(__rows: ::std::vec::Vec<Self>) -> ::llamas2::dataframe::DataFrame {
+                #(let mut #idents = ::std::vec::Vec::with_capacity(__rows.len());)*
+                for __row in __rows {
+                    #(#idents.push(__row.#idents);)*
+                }
+                let mut __df = ::llamas2::dataframe::DataFrame::new();
+                #(__df.add_col(::std::string::String::from(#names), #into_arrays);)*
+                __df
+            }
+        }
+
+        impl ::llamas2::row::FromDataFrame for #name {
+            fn from_dataframe(__df: &::llamas2::dataframe::DataFrame)
+                -> ::std::result::Result<::std::vec::Vec<Self>, ::llamas2::row::Error>
+            {
+                let __len = __df.n_rows();
+                #(#from_arrays)*
+                ::std::result::Result::Ok((0..__len).map(|_| #name {
+                    #(#idents: #idents.next().expect("column checked to be len rows"),)*
+                }).collect())
+            }
+        }
+    })
+}
+
+// The value of `#[llamas(rename = "...")]`, if the field has one.
+fn column_name(field: &syn::Field) -> Result<Option<String>, syn::Error> {
+    let mut rename = None;
+    for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("llamas")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                let name: LitStr = meta.value()?.parse()?;
+                rename = Some(name.value());
+                Ok(())
+            } else {
+                Err(meta.error("unknown llamas attribute, expected rename"))
+            }
+        })?;
+    }
+    Ok(rename)
+}
@@ -0,0 +1,71 @@
+extern crate chrono;
+extern crate llamas2;
+#[macro_use]
+extern crate llamas2_derive;
+
+use chrono::NaiveDate;
+use llamas2::dataframe::{Array, ArrayData, DataFrame};
+
+#[derive(Debug, Clone, PartialEq, DataFrameRow)]
+struct Trade {
+    id: u32,
+    len: i64,
+    #[llamas(rename = "ticker")]
+    symbol: String,
+    price: f64,
+    day: NaiveDate,
+    note: Option<String>,
+    settled: bool,
+}
+
+fn trades() -> Vec<Trade> {
+    vec![
+        Trade {
+            id: 1,
+            len: 4,
+            symbol: "ABC".to_owned(),
+            price: 10.5,
+            day: NaiveDate::from_ymd_opt(2018, 5, 1).unwrap(),
+            note: None,
+            settled: true,
+        },
+        Trade {
+            id: 2,
+            len: 7,
+            symbol: "XYZ".to_owned(),
+            price: 3.25,
+            day: NaiveDate::from_ymd_opt(2018, 5, 2).unwrap(),
+            note: Some("late".to_owned()),
+            settled: false,
+        },
+    ]
+}
+
+#[test]
+fn test_round_trip() {
+    let df = DataFrame::from_rows(trades());
+    assert_eq!(df.n_rows(), 2);
+    assert_eq!(df.get_col("id").unwrap().dtype(), "UInt32");
+    assert_eq!(df.get_col("ticker").unwrap().dtype(), "Str");
+    assert_eq!(df.get_col("day").unwrap().dtype(), "Date");
+    assert!(df.get_col("symbol").is_none());
+    assert!(df.get_col("note").unwrap().is_null(0));
+
+    assert_eq!(df.to_rows::<Trade>().unwrap(), trades());
+}
+
+#[test]
+fn test_from_dataframe_errors() {
+    let mut df = DataFrame::from_rows(trades());
+    df.add_col("price".to_owned(), Array::Float64(ArrayData::from_options(vec![Some(1.0), None])));
+    let err = df.to_rows::<Trade>().unwrap_err().to_string();
+    assert!(err.contains("column price"), "{}", err);
+
+    df.add_col("price".to_owned(), Array::Int64(ArrayData::from_vec(vec![1, 2])));
+    assert!(df.to_rows::<Trade>().is_err());
+
+    let mut df = DataFrame::new();
+    df.add_col("id".to_owned(), Array::UInt32(ArrayData::from_vec(vec![1])));
+    let err = df.to_rows::<Trade>().unwrap_err().to_string();
+    assert_eq!(err, "column len not found");
+}
@@ -0,0 +1,131 @@
+/// Packed bits, one per row. Used by `ArrayData` to mark which rows
+/// are valid (set) and which are null (unset).
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Bitmap {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl Bitmap {
+    pub fn new() -> Self {
+        Bitmap {
+            words: vec![],
+            len: 0,
+        }
+    }
+
+    /// `len` bits, all set to `bit`.
+    pub fn with_value(len: usize, bit: bool) -> Self {
+        let fill = if bit { !0 } else { 0 };
+        let mut bitmap = Bitmap {
+            words: vec![fill; len.div_ceil(64)],
+            len,
+        };
+        bitmap.clear_unused();
+        bitmap
+    }
+
+    // Bits past `len` in the last word are kept unset, so whole words
+    // can be compared and counted.
+    fn clear_unused(&mut self) {
+        let used = self.len % 64;
+        if used != 0 {
+            if let Some(last) = self.words.last_mut() {
+                *last &= (1 << used) - 1;
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Panics if `index` is out of bounds.
+    pub fn get(&self, index: usize) -> bool {
+        assert!(index < self.len, "bit {} out of bounds for bitmap of len {}", index, self.len);
+        self.words[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    /// Panics if `index` is out of bounds.
+    pub fn set(&mut self, index: usize, bit: bool) {
+        assert!(index < self.len, "bit {} out of bounds for bitmap of len {}", index, self.len);
+        if bit {
+            self.words[index / 64] |= 1 << (index % 64);
+        } else {
+            self.words[index / 64] &= !(1 << (index % 64));
+        }
+    }
+
+    pub fn push(&mut self, bit: bool) {
+        if self.len.is_multiple_of(64) {
+            self.words.push(0);
+        }
+        self.len += 1;
+        let index = self.len - 1;
+        self.set(index, bit);
+    }
+
+    /// Number of set bits in `offset..offset + len`.
+    pub fn count_ones(&self, offset: usize, len: usize) -> usize {
+        if offset == 0 && len == self.len {
+            return self.words.iter().map(|w| w.count_ones() as usize).sum();
+        }
+        (offset..offset + len).filter(|&i| self.get(i)).count()
+    }
+
+    /// Copy of the bits in `offset..offset + len`.
+    pub fn slice_copy(&self, offset: usize, len: usize) -> Self {
+        if offset == 0 && len == self.len {
+            return self.clone();
+        }
+        (offset..offset + len).map(|i| self.get(i)).collect()
+    }
+
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = bool> + 'a {
+        (0..self.len).map(move |i| self.get(i))
+    }
+
+    /// The packed words, least significant bit first. Bits past `len`
+    /// are always unset.
+    pub fn as_words(&self) -> &[u64] {
+        &self.words
+    }
+}
+
+impl ::std::iter::FromIterator<bool> for Bitmap {
+    fn from_iter<I: IntoIterator<Item = bool>>(iter: I) -> Self {
+        let mut bitmap = Bitmap::new();
+        for bit in iter {
+            bitmap.push(bit);
+        }
+        bitmap
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bitmap() {
+        let mut bitmap = Bitmap::with_value(70, true);
+        assert_eq!(bitmap.count_ones(0, 70), 70);
+        bitmap.set(3, false);
+        bitmap.set(65, false);
+        assert!(!bitmap.get(3) && bitmap.get(4) && !bitmap.get(65));
+        assert_eq!(bitmap.count_ones(0, 70), 68);
+        assert_eq!(bitmap.count_ones(2, 3), 2);
+
+        bitmap.push(false);
+        assert_eq!(bitmap.len(), 71);
+        assert!(!bitmap.get(70));
+
+        let sliced = bitmap.slice_copy(64, 3);
+        assert_eq!(sliced.iter().collect::<Vec<_>>(), vec![true, false, true]);
+        assert_eq!(sliced, vec![true, false, true].into_iter().collect());
+    }
+}
@@ -0,0 +1,340 @@
+use failure::Error;
+use indexmap::IndexSet;
+use std::sync::Arc;
+
+use dataframe::ArrayData;
+
+/// Dictionary encoded strings, for low-cardinality `Str` columns.
+///
+/// Each row is a u32 code into `categories`, which is shared (behind
+/// an `Arc`) between clones and slices of the array. The order of
+/// `categories` is the category order: sorting a Categorical sorts
+/// by code, not by the string value.
+#[derive(Debug, Clone)]
+pub struct CategoricalData {
+    codes: ArrayData<u32>,
+    categories: Arc<IndexSet<String>>,
+}
+
+impl CategoricalData {
+    /// Errors if any code points past the end of `categories`.
+    pub fn new(codes: ArrayData<u32>, categories: IndexSet<String>) -> Result<Self, Error> {
+        if let Some(code) = codes.as_slice().iter().find(|&&code| code as usize >= categories.len()) {
+            return Err(format_err!("code {} out of range for {} categories", code, categories.len()));
+        }
+        Ok(CategoricalData {
+            codes,
+            categories: Arc::new(categories),
+        })
+    }
+
+    /// Encodes strings, with categories in order of first appearance.
+    pub fn encode(values: &[String]) -> Self {
+        let mut categories = IndexSet::new();
+        let codes = values.iter()
+            .map(|v| categories.insert_full(v.clone()).0 as u32)
+            .collect();
+
+        CategoricalData {
+            codes: ArrayData::from_vec(codes),
+            categories: Arc::new(categories),
+        }
+    }
+
+    /// Encodes a `Str` array, with categories in order of first
+    /// appearance. Nulls stay null and don't add a category.
+    pub fn encode_array(values: &ArrayData<String>) -> Self {
+        let mut categories = IndexSet::new();
+        let codes = values.iter()
+            .map(|v| v.map(|v| categories.insert_full(v.clone()).0 as u32))
+            .collect();
+
+        CategoricalData {
+            codes: ArrayData::from_options(codes),
+            categories: Arc::new(categories),
+        }
+    }
+
+    /// Encodes strings using an explicit category order. Errors if a
+    /// value isn't one of `categories`.
+    pub fn encode_with_categories(values: &[String], categories: Vec<String>) -> Result<Self, Error> {
+        let categories: IndexSet<String> = categories.into_iter().collect();
+        let codes = values.iter()
+            .map(|v| {
+                categories.get_full(v)
+                    .map(|(code, _)| code as u32)
+                    .ok_or_else(|| format_err!("value {} not found in categories", v))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(CategoricalData {
+            codes: ArrayData::from_vec(codes),
+            categories: Arc::new(categories),
+        })
+    }
+
+    pub fn codes(&self) -> &ArrayData<u32> {
+        &self.codes
+    }
+
+    pub fn categories(&self) -> &IndexSet<String> {
+        &self.categories
+    }
+
+    /// Whether both arrays use the same dictionary (same categories in
+    /// the same order), in which case they can be compared by code alone.
+    pub fn shares_categories(&self, other: &CategoricalData) -> bool {
+        Arc::ptr_eq(&self.categories, &other.categories)
+            || self.categories.iter().eq(other.categories.iter())
+    }
+
+    /// Same values, re-encoded to a new category order. Errors if a
+    /// value in use isn't one of `categories`.
+    pub fn set_categories(&self, categories: Vec<String>) -> Result<Self, Error> {
+        let categories: IndexSet<String> = categories.into_iter().collect();
+        let remap = self.categories.iter()
+            .map(|c| categories.get_full(c).map(|(code, _)| code as u32))
+            .collect::<Vec<_>>();
+        let codes = self.codes.iter()
+            .map(|code| match code {
+                Some(&code) => {
+                    remap[code as usize]
+                        .map(Some)
+                        .ok_or_else(|| format_err!("value {} not found in categories", self.categories[code as usize]))
+                },
+                None => Ok(None),
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(CategoricalData {
+            codes: ArrayData::from_options(codes),
+            categories: Arc::new(categories),
+        })
+    }
+
+    pub fn decode(&self) -> ArrayData<String> {
+        // null rows may have a placeholder code with no category
+        self.codes.map_values(|&code| {
+            self.categories.get_index(code as usize).cloned().unwrap_or_default()
+        })
+    }
+
+    /// Only runs `f` once per category, then merges any categories
+    /// that map to the same string.
+    pub fn apply<F>(&self, f: F) -> Self
+        where F: Fn(&String) -> String + Sync + Send
+    {
+        let mut categories = IndexSet::new();
+        let remap: Vec<u32> = self.categories.iter()
+            .map(|c| categories.insert_full(f(c)).0 as u32)
+            .collect();
+
+        CategoricalData {
+            codes: self.codes.apply(|&code| remap.get(code as usize).cloned().unwrap_or(0)),
+            categories: Arc::new(categories),
+        }
+    }
+
+    pub fn apply_inplace<F>(&mut self, f: F)
+        where F: Fn(&mut String) + Sync + Send
+    {
+        *self = self.apply(|c| {
+            let mut c = c.clone();
+            f(&mut c);
+            c
+        });
+    }
+
+    // Inner option signifies null
+    pub fn get(&self, index: usize) -> Option<Option<&String>> {
+        self.codes.get(index).map(|code| code.map(|&code| &self.categories[code as usize]))
+    }
+
+    /// Adds `item` to the categories if it isn't already there.
+    pub fn push(&mut self, item: String) {
+        let code = match self.categories.get_full(&item) {
+            Some((code, _)) => code,
+            None => Arc::make_mut(&mut self.categories).insert_full(item).0,
+        };
+        self.codes.push(code as u32);
+    }
+
+    pub fn push_null(&mut self) {
+        self.codes.push_null();
+    }
+
+    /// Adds `value` to the categories if it isn't already there.
+    pub fn fill_null(&self, value: String) -> Self {
+        let mut categories = self.categories.clone();
+        let code = match categories.get_full(&value) {
+            Some((code, _)) => code,
+            None => Arc::make_mut(&mut categories).insert_full(value).0,
+        };
+        CategoricalData {
+            codes: self.codes.fill_null(code as u32),
+            categories,
+        }
+    }
+
+    pub fn multiply_row(&self, multiple: usize) -> Self {
+        CategoricalData {
+            codes: self.codes.multiply_row(multiple),
+            categories: self.categories.clone(),
+        }
+    }
+
+    pub fn slice(&self, offset: usize, len: usize) -> Result<Self, Error> {
+        Ok(CategoricalData {
+            codes: self.codes.slice(offset, len)?,
+            categories: self.categories.clone(),
+        })
+    }
+
+    pub(crate) fn take(&self, indices: &[usize]) -> Self {
+        CategoricalData {
+            codes: self.codes.take(indices),
+            categories: self.categories.clone(),
+        }
+    }
+
+    pub(crate) fn take_opt(&self, indices: &[Option<usize>]) -> Self {
+        CategoricalData {
+            codes: self.codes.take_opt(indices),
+            categories: self.categories.clone(),
+        }
+    }
+
+    /// Rows of all `parts`, one after the other. Codes are copied as is
+    /// when the parts share a dictionary, otherwise remapped to a
+    /// merged one (categories in order of first appearance).
+    pub fn concat(parts: &[&CategoricalData]) -> Result<Self, Error> {
+        let first = match parts.first() {
+            Some(first) => *first,
+            None => return Err(format_err!("can't concat zero arrays")),
+        };
+        if parts.iter().all(|part| part.shares_categories(first)) {
+            let codes: Vec<_> = parts.iter().map(|part| &part.codes).collect();
+            return Ok(CategoricalData {
+                codes: ArrayData::concat(&codes),
+                categories: first.categories.clone(),
+            });
+        }
+
+        let mut categories = IndexSet::new();
+        let codes = parts.iter()
+            .map(|part| {
+                let remap: Vec<u32> = part.categories.iter()
+                    .map(|c| categories.insert_full(c.clone()).0 as u32)
+                    .collect();
+                part.codes.apply(|&code| remap.get(code as usize).cloned().unwrap_or(0))
+            })
+            .collect::<Vec<_>>();
+
+        Ok(CategoricalData {
+            codes: ArrayData::concat(&codes.iter().collect::<Vec<_>>()),
+            categories: Arc::new(categories),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.codes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.codes.is_empty()
+    }
+}
+
+/// Compares codes directly when both sides share a dictionary,
+/// otherwise falls back to comparing the strings.
+impl PartialEq for CategoricalData {
+    fn eq(&self, other: &CategoricalData) -> bool {
+        if self.len() != other.len() {
+            return false;
+        }
+        if self.shares_categories(other) {
+            return self.codes.iter().eq(other.codes.iter());
+        }
+        (0..self.len()).all(|i| self.get(i) == other.get(i))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn strings(xs: &[&str]) -> Vec<String> {
+        xs.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let values = strings(&["NY", "CA", "NY", "TX", "CA"]);
+        let cat = CategoricalData::encode(&values);
+        assert_eq!(cat.codes().as_slice(), &[0, 1, 0, 2, 1]);
+        assert_eq!(cat.categories().iter().collect::<Vec<_>>(), vec!["NY", "CA", "TX"]);
+        assert_eq!(cat.decode().as_slice(), values.as_slice());
+    }
+
+    #[test]
+    fn test_encode_with_categories() {
+        let values = strings(&["med", "low", "high"]);
+        let cat = CategoricalData::encode_with_categories(&values, strings(&["low", "med", "high"])).unwrap();
+        assert_eq!(cat.codes().as_slice(), &[1, 0, 2]);
+
+        assert!(CategoricalData::encode_with_categories(&values, strings(&["low", "med"])).is_err());
+        assert!(cat.set_categories(strings(&["low", "high"])).is_err());
+
+        let reordered = cat.set_categories(strings(&["high", "med", "low"])).unwrap();
+        assert_eq!(reordered.codes().as_slice(), &[1, 2, 0]);
+        assert_eq!(reordered.decode().as_slice(), values.as_slice());
+        assert!(reordered == cat);
+    }
+
+    #[test]
+    fn test_apply_merges_categories() {
+        let cat = CategoricalData::encode(&strings(&["ny", "NY", "ca"]));
+        let upper = cat.apply(|s| s.to_uppercase());
+        assert_eq!(upper.categories().len(), 2);
+        assert_eq!(upper.codes().as_slice(), &[0, 0, 1]);
+        assert_eq!(upper.decode().as_slice(), strings(&["NY", "NY", "CA"]).as_slice());
+    }
+
+    #[test]
+    fn test_push_and_slice() {
+        let mut cat = CategoricalData::encode(&strings(&["a", "b"]));
+        cat.push("b".to_owned());
+        cat.push("c".to_owned());
+        assert_eq!(cat.codes().as_slice(), &[0, 1, 1, 2]);
+
+        let view = cat.slice(1, 3).unwrap();
+        assert_eq!(view.get(2), Some(Some(&"c".to_owned())));
+        assert!(view.shares_categories(&cat));
+        assert!(cat.slice(2, 3).is_err());
+    }
+
+    #[test]
+    fn test_nulls() {
+        let mut values = ArrayData::from_vec(strings(&["a", "b"]));
+        values.push_null();
+        let mut cat = CategoricalData::encode_array(&values);
+        assert_eq!(cat.categories().len(), 2);
+        assert_eq!(cat.get(2), Some(None));
+        cat.push_null();
+        assert_eq!(cat.decode().iter().collect::<Vec<_>>(), vec![Some(&"a".to_owned()), Some(&"b".to_owned()), None, None]);
+        assert_eq!(cat.set_categories(strings(&["b", "a"])).unwrap().get(3), Some(None));
+    }
+
+    #[test]
+    fn test_concat() {
+        let a = CategoricalData::encode(&strings(&["x", "y"]));
+        let b = CategoricalData::encode(&strings(&["z", "x"]));
+        let both = CategoricalData::concat(&[&a, &b, &a.slice(1, 1).unwrap()]).unwrap();
+        assert_eq!(both.categories().iter().collect::<Vec<_>>(), vec!["x", "y", "z"]);
+        assert_eq!(both.decode().as_slice(), strings(&["x", "y", "z", "x", "y"]).as_slice());
+
+        let same = CategoricalData::concat(&[&a, &a]).unwrap();
+        assert!(same.shares_categories(&a));
+        assert_eq!(same.codes().as_slice(), &[0, 1, 0, 1]);
+    }
+}
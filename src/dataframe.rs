@@ -1,6 +1,15 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime};
 use failure::Error;
-use indexmap::IndexMap;
+use indexmap::{IndexMap, IndexSet};
 use rayon::prelude::*;
+use std::cmp::Ordering;
+use std::fmt;
+use std::sync::Arc;
+
+use bitmap::Bitmap;
+use categorical::CategoricalData;
+use decimal::{self, DecimalData};
+use temporal::DatetimeData;
 
 // TODO remove Array, just use DataFrame, Column, and Array (and in future Buffer
 // can replace Vec for lowest level)
@@ -26,6 +35,79 @@ impl DataFrame {
     pub fn get_col<'a>(&'a self, col_name: & str) -> Option<&'a Array> {
         self.columns.get(col_name)
     }
+
+    /// Number of rows, going by the first column (0 with no columns).
+    pub fn n_rows(&self) -> usize {
+        self.columns.values().next().map_or(0, |array| array.len())
+    }
+
+    /// Zero-copy view of `len` rows starting at `offset`, across
+    /// every column. Errors (naming the column) if any column is too
+    /// short for the requested rows.
+    pub fn slice(&self, offset: usize, len: usize) -> Result<Self, Error> {
+        let mut df = DataFrame::new();
+        for (name, array) in &self.columns {
+            let view = array.slice(offset, len)
+                .map_err(|err| format_err!("column {}: {}", name, err))?;
+            df.add_col(name.clone(), view);
+        }
+        Ok(df)
+    }
+
+    /// New DataFrame with rows ordered by the values in `col_name`.
+    /// The sort is stable; Categorical columns sort by category order.
+    pub fn sort_by(&self, col_name: &str) -> Result<Self, Error> {
+        let indices = self.get_col(col_name)
+            .ok_or_else(|| format_err!("column {} not found", col_name))?
+            .sort_indices();
+
+        for (name, array) in &self.columns {
+            if array.len() != indices.len() {
+                return Err(format_err!(
+                    "column {} has len {}, sort column {} has len {}",
+                    name, array.len(), col_name, indices.len()
+                ));
+            }
+        }
+        Ok(self.take(&indices))
+    }
+
+    /// Rows where `mask` is true. Null mask values count as false.
+    /// Errors unless `mask` is Bool and as long as every column.
+    pub fn filter(&self, mask: &Array) -> Result<Self, Error> {
+        let mask = match *mask {
+            Array::Bool(ref array_data) => array_data,
+            _ => return Err(format_err!("filter mask must be Bool, got {}", mask.dtype())),
+        };
+        for (name, array) in &self.columns {
+            if array.len() != mask.len() {
+                return Err(format_err!("column {} has len {}, mask has len {}", name, array.len(), mask.len()));
+            }
+        }
+        let indices: Vec<usize> = mask.iter()
+            .enumerate()
+            .filter(|&(_, keep)| keep == Some(&true))
+            .map(|(i, _)| i)
+            .collect();
+        Ok(self.take(&indices))
+    }
+
+    // New DataFrame made of the rows at `indices`, in that order.
+    //
+    // Panics if an index is out of bounds for any column.
+    pub(crate) fn take(&self, indices: &[usize]) -> Self {
+        let mut df = DataFrame::new();
+        for (name, array) in &self.columns {
+            df.add_col(name.clone(), array.take(indices));
+        }
+        df
+    }
+}
+
+impl Default for DataFrame {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 // Why am I using a macro? Because I want the type of the value col
@@ -88,19 +170,23 @@ macro_rules! melt {
         // Now the value_vars col names get put into a col
         // Since we previously asserted that they exist, can just make a vec
         // that repeats in the iterator here
-        let mut value_vars = vec![];
-        $(
-            value_vars.push($value_var);
-        )+
-
-        let mut var_col = Array::new("Str").expect("couldn't create col");
-        // TODO create extend so don't have to use push
-        for _ in 0..df_len {
-            for v in &value_vars {
-                var_col.push(v.to_string());
-            }
-        }
-        df.add_col($var_name.to_string(), var_col);
+        //
+        // The var col is Categorical, with value_vars as the categories
+        // (in the order given), so each row is only a code. A value_var
+        // given twice is one category, so codes go by first position.
+        let value_vars: Vec<String> = vec![$($value_var.to_string()),+];
+        let var_codes: Vec<u32> = value_vars.iter()
+            .map(|name| value_vars.iter().position(|v| v == name).unwrap() as u32)
+            .collect();
+
+        let codes = (0..df_len)
+            .flat_map(|_| var_codes.iter().cloned())
+            .collect::<Vec<u32>>();
+        let var_col = $crate::categorical::CategoricalData::new(
+            $crate::dataframe::ArrayData::from_vec(codes),
+            value_vars.into_iter().collect(),
+        ).expect("codes are in range of value_vars");
+        df.add_col($var_name.to_string(), Array::Categorical(var_col));
 
         // now the values from the value_vars columns
         let mut value_col = Array::new($value_type).expect("couldn't create col");
@@ -111,10 +197,14 @@ macro_rules! melt {
                 let v: Result<Option<Option<&$value_var_type>>, Error> = col.get(i);
                 let v = v
                     .expect("Wrong type")
-                    .expect(format!("Could not find index {} in col", i).as_str())
-                    .unwrap() // this second unwrap is because no nulls impl yet
-                    .clone();
-                value_col.push(v as $value_primitive_type);
+                    .expect(format!("Could not find index {} in col", i).as_str());
+                match v {
+                    Some(v) => {
+                        value_col.push(v.clone() as $value_primitive_type)
+                            .expect("value_type does not match value_primitive_type");
+                    },
+                    None => value_col.push_null(),
+                }
             )+
         }
 
@@ -153,23 +243,41 @@ pub enum Array {
     UInt64(ArrayData<u64>),
     Float32(ArrayData<f32>),
     Float64(ArrayData<f64>),
+    Bool(ArrayData<bool>),
     Str(ArrayData<String>),
+    Categorical(CategoricalData),
+    Date(ArrayData<NaiveDate>),
+    Datetime(DatetimeData),
+    Duration(ArrayData<Duration>),
+    Decimal(DecimalData),
 }
 
 impl Array {
     pub fn new(dtype: &str) -> Result<Self, Error> {
         match dtype {
-            "Int8" => Ok(Array::Int8(ArrayData(vec![]))),
-            "Int16" => Ok(Array::Int16(ArrayData(vec![]))),
-            "Int32" => Ok(Array::Int32(ArrayData(vec![]))),
-            "Int64" => Ok(Array::Int64(ArrayData(vec![]))),
-            "UInt8" => Ok(Array::UInt8(ArrayData(vec![]))),
-            "UInt16" => Ok(Array::UInt16(ArrayData(vec![]))),
-            "UInt32" => Ok(Array::UInt32(ArrayData(vec![]))),
-            "UInt64" => Ok(Array::UInt64(ArrayData(vec![]))),
-            "Float32" => Ok(Array::Float32(ArrayData(vec![]))),
-            "Float64" => Ok(Array::Float64(ArrayData(vec![]))),
-            "Str" => Ok(Array::Str(ArrayData(vec![]))),
+            "Int8" => Ok(Array::Int8(ArrayData::from_vec(vec![]))),
+            "Int16" => Ok(Array::Int16(ArrayData::from_vec(vec![]))),
+            "Int32" => Ok(Array::Int32(ArrayData::from_vec(vec![]))),
+            "Int64" => Ok(Array::Int64(ArrayData::from_vec(vec![]))),
+            "UInt8" => Ok(Array::UInt8(ArrayData::from_vec(vec![]))),
+            "UInt16" => Ok(Array::UInt16(ArrayData::from_vec(vec![]))),
+            "UInt32" => Ok(Array::UInt32(ArrayData::from_vec(vec![]))),
+            "UInt64" => Ok(Array::UInt64(ArrayData::from_vec(vec![]))),
+            "Float32" => Ok(Array::Float32(ArrayData::from_vec(vec![]))),
+            "Float64" => Ok(Array::Float64(ArrayData::from_vec(vec![]))),
+            "Bool" => Ok(Array::Bool(ArrayData::from_vec(vec![]))),
+            "Str" => Ok(Array::Str(ArrayData::from_vec(vec![]))),
+            "Categorical" => Ok(Array::Categorical(
+                CategoricalData::new(ArrayData::from_vec(vec![]), IndexSet::new())?
+            )),
+            "Date" => Ok(Array::Date(ArrayData::from_vec(vec![]))),
+            "Datetime" => Ok(Array::Datetime(DatetimeData::new(ArrayData::from_vec(vec![]), None))),
+            "Duration" => Ok(Array::Duration(ArrayData::from_vec(vec![]))),
+            _ if dtype.starts_with("Decimal(") => {
+                let (precision, scale) = decimal::parse_decimal_dtype(dtype)
+                    .ok_or_else(|| format_err!("dtype {} not found, expected Decimal(precision,scale)", dtype))?;
+                Ok(Array::Decimal(DecimalData::new(ArrayData::from_vec(vec![]), precision, scale)?))
+            },
             _ => Err(format_err!("dtype {} not found", dtype)),
         }
 
@@ -187,7 +295,13 @@ impl Array {
             Array::UInt64(_) => "UInt64".to_owned(),
             Array::Float32(_) => "Float32".to_owned(),
             Array::Float64(_) => "Float64".to_owned(),
+            Array::Bool(_) => "Bool".to_owned(),
             Array::Str(_) => "Str".to_owned(),
+            Array::Categorical(_) => "Categorical".to_owned(),
+            Array::Date(_) => "Date".to_owned(),
+            Array::Datetime(_) => "Datetime".to_owned(),
+            Array::Duration(_) => "Duration".to_owned(),
+            Array::Decimal(ref dec_data) => format!("Decimal({},{})", dec_data.precision(), dec_data.scale()),
         }
     }
 
@@ -203,7 +317,12 @@ impl Array {
             Array::UInt64(_) => "u64".to_owned(),
             Array::Float32(_) => "f32".to_owned(),
             Array::Float64(_) => "f64".to_owned(),
-            Array::Str(_) => "String".to_owned(),
+            Array::Bool(_) => "bool".to_owned(),
+            Array::Str(_) | Array::Categorical(_) => "String".to_owned(),
+            Array::Date(_) => "NaiveDate".to_owned(),
+            Array::Datetime(_) => "NaiveDateTime".to_owned(),
+            Array::Duration(_) => "Duration".to_owned(),
+            Array::Decimal(_) => "i128".to_owned(),
         }
     }
 
@@ -220,7 +339,13 @@ impl Array {
             UInt64(ref array_data) => UInt64(array_data.multiply_row(multiple)),
             Float32(ref array_data) => Float32(array_data.multiply_row(multiple)),
             Float64(ref array_data) => Float64(array_data.multiply_row(multiple)),
+            Bool(ref array_data) => Bool(array_data.multiply_row(multiple)),
             Str(ref array_data) => Str(array_data.multiply_row(multiple)),
+            Categorical(ref cat_data) => Categorical(cat_data.multiply_row(multiple)),
+            Date(ref array_data) => Date(array_data.multiply_row(multiple)),
+            Datetime(ref dt_data) => Datetime(dt_data.multiply_row(multiple)),
+            Duration(ref array_data) => Duration(array_data.multiply_row(multiple)),
+            Decimal(ref dec_data) => Decimal(dec_data.multiply_row(multiple)),
         }
     }
 
@@ -237,11 +362,297 @@ impl Array {
             UInt64(ref array_data) => array_data.len(),
             Float32(ref array_data) => array_data.len(),
             Float64(ref array_data) => array_data.len(),
+            Bool(ref array_data) => array_data.len(),
             Str(ref array_data) => array_data.len(),
+            Categorical(ref cat_data) => cat_data.len(),
+            Date(ref array_data) => array_data.len(),
+            Datetime(ref dt_data) => dt_data.len(),
+            Duration(ref array_data) => array_data.len(),
+            Decimal(ref dec_data) => dec_data.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Panics if `index` is out of bounds.
+    pub fn is_null(&self, index: usize) -> bool {
+        use self::Array::*;
+        match *self {
+            Int8(ref array_data) => array_data.is_null(index),
+            Int16(ref array_data) => array_data.is_null(index),
+            Int32(ref array_data) => array_data.is_null(index),
+            Int64(ref array_data) => array_data.is_null(index),
+            UInt8(ref array_data) => array_data.is_null(index),
+            UInt16(ref array_data) => array_data.is_null(index),
+            UInt32(ref array_data) => array_data.is_null(index),
+            UInt64(ref array_data) => array_data.is_null(index),
+            Float32(ref array_data) => array_data.is_null(index),
+            Float64(ref array_data) => array_data.is_null(index),
+            Bool(ref array_data) => array_data.is_null(index),
+            Str(ref array_data) => array_data.is_null(index),
+            Categorical(ref cat_data) => cat_data.codes().is_null(index),
+            Date(ref array_data) => array_data.is_null(index),
+            Datetime(ref dt_data) => dt_data.values().is_null(index),
+            Duration(ref array_data) => array_data.is_null(index),
+            Decimal(ref dec_data) => dec_data.values().is_null(index),
+        }
+    }
+
+    pub fn null_count(&self) -> usize {
+        use self::Array::*;
+        match *self {
+            Int8(ref array_data) => array_data.null_count(),
+            Int16(ref array_data) => array_data.null_count(),
+            Int32(ref array_data) => array_data.null_count(),
+            Int64(ref array_data) => array_data.null_count(),
+            UInt8(ref array_data) => array_data.null_count(),
+            UInt16(ref array_data) => array_data.null_count(),
+            UInt32(ref array_data) => array_data.null_count(),
+            UInt64(ref array_data) => array_data.null_count(),
+            Float32(ref array_data) => array_data.null_count(),
+            Float64(ref array_data) => array_data.null_count(),
+            Bool(ref array_data) => array_data.null_count(),
+            Str(ref array_data) => array_data.null_count(),
+            Categorical(ref cat_data) => cat_data.codes().null_count(),
+            Date(ref array_data) => array_data.null_count(),
+            Datetime(ref dt_data) => dt_data.values().null_count(),
+            Duration(ref array_data) => array_data.null_count(),
+            Decimal(ref dec_data) => dec_data.values().null_count(),
+        }
+    }
+
+    pub fn push_null(&mut self) {
+        use self::Array::*;
+        match *self {
+            Int8(ref mut array_data) => array_data.push_null(),
+            Int16(ref mut array_data) => array_data.push_null(),
+            Int32(ref mut array_data) => array_data.push_null(),
+            Int64(ref mut array_data) => array_data.push_null(),
+            UInt8(ref mut array_data) => array_data.push_null(),
+            UInt16(ref mut array_data) => array_data.push_null(),
+            UInt32(ref mut array_data) => array_data.push_null(),
+            UInt64(ref mut array_data) => array_data.push_null(),
+            Float32(ref mut array_data) => array_data.push_null(),
+            Float64(ref mut array_data) => array_data.push_null(),
+            Bool(ref mut array_data) => array_data.push_null(),
+            Str(ref mut array_data) => array_data.push_null(),
+            Categorical(ref mut cat_data) => cat_data.push_null(),
+            Date(ref mut array_data) => array_data.push_null(),
+            Datetime(ref mut dt_data) => dt_data.push_null(),
+            Duration(ref mut array_data) => array_data.push_null(),
+            Decimal(ref mut dec_data) => dec_data.push_null(),
+        }
+    }
+
+    /// Rows of all `arrays`, one after the other. Errors unless all
+    /// arrays have the same dtype (including Decimal precision and
+    /// scale, and Datetime offset). Categoricals get a merged dictionary.
+    pub fn concat(arrays: &[&Array]) -> Result<Array, Error> {
+        let first = match arrays.first() {
+            Some(first) => *first,
+            None => return Err(format_err!("can't concat zero arrays")),
+        };
+
+        // Plain ArrayData concat can't fail; the wrapper types check
+        // that their precision, offset, etc. match.
+        macro_rules! concat_as {
+            ($p:path) => {{
+                let parts = concat_parts!($p);
+                $p(ArrayData::concat(&parts))
+            }};
+            ($p:path, $data:ident) => {{
+                let parts = concat_parts!($p);
+                $p($data::concat(&parts)?)
+            }};
+        }
+        macro_rules! concat_parts {
+            ($p:path) => {
+                arrays.iter()
+                    .map(|array| match **array {
+                        $p(ref data) => Ok(data),
+                        _ => Err(format_err!("can't concat {} with {}", first.dtype(), array.dtype())),
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?
+            };
+        }
+
+        Ok(match *first {
+            Array::Int8(_) => concat_as!(Array::Int8),
+            Array::Int16(_) => concat_as!(Array::Int16),
+            Array::Int32(_) => concat_as!(Array::Int32),
+            Array::Int64(_) => concat_as!(Array::Int64),
+            Array::UInt8(_) => concat_as!(Array::UInt8),
+            Array::UInt16(_) => concat_as!(Array::UInt16),
+            Array::UInt32(_) => concat_as!(Array::UInt32),
+            Array::UInt64(_) => concat_as!(Array::UInt64),
+            Array::Float32(_) => concat_as!(Array::Float32),
+            Array::Float64(_) => concat_as!(Array::Float64),
+            Array::Bool(_) => concat_as!(Array::Bool),
+            Array::Str(_) => concat_as!(Array::Str),
+            Array::Categorical(_) => concat_as!(Array::Categorical, CategoricalData),
+            Array::Date(_) => concat_as!(Array::Date),
+            Array::Datetime(_) => concat_as!(Array::Datetime, DatetimeData),
+            Array::Duration(_) => concat_as!(Array::Duration),
+            Array::Decimal(_) => concat_as!(Array::Decimal, DecimalData),
+        })
+    }
+
+    /// Zero-copy view of `len` rows starting at `offset`. Errors if
+    /// the requested rows run past the end of the array.
+    pub fn slice(&self, offset: usize, len: usize) -> Result<Self, Error> {
+        use self::Array::*;
+        match *self {
+            Int8(ref array_data) => Ok(Int8(array_data.slice(offset, len)?)),
+            Int16(ref array_data) => Ok(Int16(array_data.slice(offset, len)?)),
+            Int32(ref array_data) => Ok(Int32(array_data.slice(offset, len)?)),
+            Int64(ref array_data) => Ok(Int64(array_data.slice(offset, len)?)),
+            UInt8(ref array_data) => Ok(UInt8(array_data.slice(offset, len)?)),
+            UInt16(ref array_data) => Ok(UInt16(array_data.slice(offset, len)?)),
+            UInt32(ref array_data) => Ok(UInt32(array_data.slice(offset, len)?)),
+            UInt64(ref array_data) => Ok(UInt64(array_data.slice(offset, len)?)),
+            Float32(ref array_data) => Ok(Float32(array_data.slice(offset, len)?)),
+            Float64(ref array_data) => Ok(Float64(array_data.slice(offset, len)?)),
+            Bool(ref array_data) => Ok(Bool(array_data.slice(offset, len)?)),
+            Str(ref array_data) => Ok(Str(array_data.slice(offset, len)?)),
+            Categorical(ref cat_data) => Ok(Categorical(cat_data.slice(offset, len)?)),
+            Date(ref array_data) => Ok(Date(array_data.slice(offset, len)?)),
+            Datetime(ref dt_data) => Ok(Datetime(dt_data.slice(offset, len)?)),
+            Duration(ref array_data) => Ok(Duration(array_data.slice(offset, len)?)),
+            Decimal(ref dec_data) => Ok(Decimal(dec_data.slice(offset, len)?)),
+        }
+    }
+
+    /// Dictionary-encodes a `Str` array. A Categorical array is
+    /// returned as is.
+    pub fn to_categorical(&self) -> Result<Self, Error> {
+        match *self {
+            Array::Str(ref array_data) => Ok(Array::Categorical(CategoricalData::encode_array(array_data))),
+            Array::Categorical(_) => Ok(self.clone()),
+            _ => Err(format_err!("can't convert {} to Categorical", self.dtype())),
+        }
+    }
+
+    /// Decodes a Categorical array back to `Str`. A `Str` array is
+    /// returned as is.
+    pub fn to_str_array(&self) -> Result<Self, Error> {
+        match *self {
+            Array::Categorical(ref cat_data) => Ok(Array::Str(cat_data.decode())),
+            Array::Str(_) => Ok(self.clone()),
+            _ => Err(format_err!("can't convert {} to Str", self.dtype())),
+        }
+    }
+
+    // All the values of a Str or Categorical array, for parsing into
+    // `dtype`. Nulls are None.
+    pub(crate) fn string_values(&self, dtype: &str) -> Result<Vec<Option<&String>>, Error> {
+        match *self {
+            Array::Str(_) | Array::Categorical(_) => Ok(DataTypeIterator::<String>::values(self)?.collect()),
+            _ => Err(format_err!("can't parse {} as {}, array must be Str", self.dtype(), dtype)),
+        }
+    }
+
+    /// Indices that would sort the array (stable). Nulls sort last,
+    /// after NaN for floats. Categorical sorts by category order.
+    pub fn sort_indices(&self) -> Vec<usize> {
+        use self::Array::*;
+        match *self {
+            Int8(ref array_data) => argsort_by(array_data, Ord::cmp),
+            Int16(ref array_data) => argsort_by(array_data, Ord::cmp),
+            Int32(ref array_data) => argsort_by(array_data, Ord::cmp),
+            Int64(ref array_data) => argsort_by(array_data, Ord::cmp),
+            UInt8(ref array_data) => argsort_by(array_data, Ord::cmp),
+            UInt16(ref array_data) => argsort_by(array_data, Ord::cmp),
+            UInt32(ref array_data) => argsort_by(array_data, Ord::cmp),
+            UInt64(ref array_data) => argsort_by(array_data, Ord::cmp),
+            Float32(ref array_data) => argsort_by(array_data, cmp_float),
+            Float64(ref array_data) => argsort_by(array_data, cmp_float),
+            Bool(ref array_data) => argsort_by(array_data, Ord::cmp),
+            Str(ref array_data) => argsort_by(array_data, Ord::cmp),
+            Categorical(ref cat_data) => argsort_by(cat_data.codes(), Ord::cmp),
+            Date(ref array_data) => argsort_by(array_data, Ord::cmp),
+            Datetime(ref dt_data) => argsort_by(dt_data.values(), Ord::cmp),
+            Duration(ref array_data) => argsort_by(array_data, Ord::cmp),
+            Decimal(ref dec_data) => argsort_by(dec_data.values(), Ord::cmp),
+        }
+    }
+
+    /// New array made of the rows at `indices`, in that order.
+    ///
+    /// Panics if an index is out of bounds.
+    pub(crate) fn take(&self, indices: &[usize]) -> Self {
+        use self::Array::*;
+        match *self {
+            Int8(ref array_data) => Int8(array_data.take(indices)),
+            Int16(ref array_data) => Int16(array_data.take(indices)),
+            Int32(ref array_data) => Int32(array_data.take(indices)),
+            Int64(ref array_data) => Int64(array_data.take(indices)),
+            UInt8(ref array_data) => UInt8(array_data.take(indices)),
+            UInt16(ref array_data) => UInt16(array_data.take(indices)),
+            UInt32(ref array_data) => UInt32(array_data.take(indices)),
+            UInt64(ref array_data) => UInt64(array_data.take(indices)),
+            Float32(ref array_data) => Float32(array_data.take(indices)),
+            Float64(ref array_data) => Float64(array_data.take(indices)),
+            Bool(ref array_data) => Bool(array_data.take(indices)),
+            Str(ref array_data) => Str(array_data.take(indices)),
+            Categorical(ref cat_data) => Categorical(cat_data.take(indices)),
+            Date(ref array_data) => Date(array_data.take(indices)),
+            Datetime(ref dt_data) => Datetime(dt_data.take(indices)),
+            Duration(ref array_data) => Duration(array_data.take(indices)),
+            Decimal(ref dec_data) => Decimal(dec_data.take(indices)),
+        }
+    }
+
+    /// Like `take`, but a `None` index gives a null row.
+    ///
+    /// Panics if an index is out of bounds.
+    pub(crate) fn take_opt(&self, indices: &[Option<usize>]) -> Self {
+        use self::Array::*;
+        match *self {
+            Int8(ref array_data) => Int8(array_data.take_opt(indices)),
+            Int16(ref array_data) => Int16(array_data.take_opt(indices)),
+            Int32(ref array_data) => Int32(array_data.take_opt(indices)),
+            Int64(ref array_data) => Int64(array_data.take_opt(indices)),
+            UInt8(ref array_data) => UInt8(array_data.take_opt(indices)),
+            UInt16(ref array_data) => UInt16(array_data.take_opt(indices)),
+            UInt32(ref array_data) => UInt32(array_data.take_opt(indices)),
+            UInt64(ref array_data) => UInt64(array_data.take_opt(indices)),
+            Float32(ref array_data) => Float32(array_data.take_opt(indices)),
+            Float64(ref array_data) => Float64(array_data.take_opt(indices)),
+            Bool(ref array_data) => Bool(array_data.take_opt(indices)),
+            Str(ref array_data) => Str(array_data.take_opt(indices)),
+            Categorical(ref cat_data) => Categorical(cat_data.take_opt(indices)),
+            Date(ref array_data) => Date(array_data.take_opt(indices)),
+            Datetime(ref dt_data) => Datetime(dt_data.take_opt(indices)),
+            Duration(ref array_data) => Duration(array_data.take_opt(indices)),
+            Decimal(ref dec_data) => Decimal(dec_data.take_opt(indices)),
         }
     }
 }
 
+fn argsort_by<T, F>(array_data: &ArrayData<T>, cmp: F) -> Vec<usize>
+    where T: Send + Sync + Clone,
+          F: Fn(&T, &T) -> Ordering
+{
+    let values = array_data.as_slice();
+    let mut indices: Vec<usize> = (0..values.len()).collect();
+    indices.sort_by(|&a, &b| {
+        match (array_data.is_null(a), array_data.is_null(b)) {
+            (false, false) => cmp(&values[a], &values[b]),
+            (a_null, b_null) => a_null.cmp(&b_null),
+        }
+    });
+    indices
+}
+
+// NaN sorts after every other value
+fn cmp_float<T: PartialOrd + Copy>(a: &T, b: &T) -> Ordering {
+    #[allow(clippy::eq_op)]
+    let is_nan = |x: T| x != x;
+    a.partial_cmp(b).unwrap_or_else(|| is_nan(*a).cmp(&is_nan(*b)))
+}
+
 // TODO add error type which will give better info
 // Also, the error should give a runtime error which points
 // at the offending code
@@ -299,56 +710,353 @@ impl_datatype_for_array!(u32, Array::UInt32);
 impl_datatype_for_array!(u64, Array::UInt64);
 impl_datatype_for_array!(f32, Array::Float32);
 impl_datatype_for_array!(f64, Array::Float64);
-impl_datatype_for_array!(String, Array::Str);
+impl_datatype_for_array!(bool, Array::Bool);
+impl_datatype_for_array!(NaiveDate, Array::Date);
+impl_datatype_for_array!(NaiveDateTime, Array::Datetime);
+impl_datatype_for_array!(Duration, Array::Duration);
+
+// Decimal values are unscaled i128s that have to fit in the array's
+// precision, so this one is written out to check every value it makes.
+impl DataType<i128> for Array {
+    fn apply_inplace<F>(&mut self, f: F) -> Result<(), Error>
+        where F: Fn(&mut i128) + Sync + Send,
+    {
+        let applied = DataType::<i128>::apply(self, |&x| {
+            let mut x = x;
+            f(&mut x);
+            x
+        })?;
+        *self = applied;
+        Ok(())
+    }
 
+    fn apply<F>(&self, f: F) -> Result<Array, Error>
+        where F: Fn(&i128) -> i128 + Sync + Send,
+    {
+        match *self {
+            Array::Decimal(ref dec_data) => {
+                let values = dec_data.values().apply(f);
+                Ok(Array::Decimal(DecimalData::new(values, dec_data.precision(), dec_data.scale())?))
+            },
+            _ => Err(format_err!("Fn type mismatch, array is {}", self.dtype())),
+        }
+    }
 
-#[derive(Debug, Clone)]
-pub struct ArrayData<T>(Vec<T>);
+    fn get(&self, index: usize) -> Result<Option<Option<&i128>>, Error> {
+        match *self {
+            Array::Decimal(ref dec_data) => Ok(dec_data.get(index)),
+            _ => Err(format_err!("type mismatch, array is {}", self.dtype())),
+        }
+    }
+
+    fn push(&mut self, item: i128) -> Result<(), Error> {
+        match *self {
+            Array::Decimal(ref mut dec_data) => dec_data.push(item),
+            _ => Err(format_err!("type mismatch, array is {}", self.dtype())),
+        }
+    }
+}
+
+// Str and Categorical both hold strings, so this one is written out
+// instead of using the macro.
+impl DataType<String> for Array {
+    fn apply_inplace<F>(&mut self, f: F) -> Result<(), Error>
+        where F: Fn(&mut String) + Sync + Send,
+    {
+        match *self {
+            Array::Str(ref mut array_data) => array_data.apply_inplace(f),
+            Array::Categorical(ref mut cat_data) => cat_data.apply_inplace(f),
+            _ => return Err(format_err!("Fn type mismatch, array is {}", self.dtype())),
+        }
+        Ok(())
+    }
+
+    fn apply<F>(&self, f: F) -> Result<Array, Error>
+        where F: Fn(&String) -> String + Sync + Send,
+    {
+        match *self {
+            Array::Str(ref array_data) => Ok(Array::Str(array_data.apply(f))),
+            Array::Categorical(ref cat_data) => Ok(Array::Categorical(cat_data.apply(f))),
+            _ => Err(format_err!("Fn type mismatch, array is {}", self.dtype())),
+        }
+    }
+
+    fn get(&self, index: usize) -> Result<Option<Option<&String>>, Error> {
+        match *self {
+            Array::Str(ref array_data) => Ok(array_data.get(index)),
+            Array::Categorical(ref cat_data) => Ok(cat_data.get(index)),
+            _ => Err(format_err!("type mismatch, array is {}", self.dtype())),
+        }
+    }
+
+    fn push(&mut self, item: String) -> Result<(), Error> {
+        match *self {
+            Array::Str(ref mut array_data) => {
+                array_data.push(item);
+                Ok(())
+            },
+            Array::Categorical(ref mut cat_data) => {
+                cat_data.push(item);
+                Ok(())
+            },
+            _ => Err(format_err!("type mismatch, array is {}", self.dtype())),
+        }
+    }
+}
+
+
+/// Values are kept behind an `Arc` so that slicing an array (or a whole
+/// DataFrame) only copies the offset and length, not the data.
+///
+/// Mutating a view (`apply_inplace`, `push`) first copies the viewed
+/// rows out, so the parent array is never changed through a slice.
+///
+/// Nulls are tracked in an optional validity bitmap (indexed like
+/// `values`), which is `None` while the array has never held a null.
+/// A null row still has a placeholder in `values` (`T::default()` when
+/// pushed), so kernels over `as_slice` see it and should check
+/// `is_null` or go through `get`/`iter`.
+#[derive(Clone)]
+pub struct ArrayData<T> {
+    values: Arc<Vec<T>>,
+    validity: Option<Arc<Bitmap>>,
+    offset: usize,
+    len: usize,
+}
+
+impl<T: fmt::Debug + Send + Sync + Clone> fmt::Debug for ArrayData<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.validity.is_none() {
+            return f.debug_tuple("ArrayData").field(&self.as_slice()).finish();
+        }
+        f.debug_tuple("ArrayData").field(&self.iter().collect::<Vec<_>>()).finish()
+    }
+}
 
 impl<T: Send + Sync + Clone> ArrayData<T> {
     pub fn from_vec(xs: Vec<T>) -> Self {
-        ArrayData(xs)
+        let len = xs.len();
+        ArrayData {
+            values: Arc::new(xs),
+            validity: None,
+            offset: 0,
+            len,
+        }
+    }
+
+    /// `None` entries become nulls.
+    pub fn from_options(xs: Vec<Option<T>>) -> Self
+        where T: Default
+    {
+        let validity: Bitmap = xs.iter().map(|x| x.is_some()).collect();
+        let values = xs.into_iter().map(|x| x.unwrap_or_default()).collect();
+        ArrayData::with_validity(values, Some(validity))
+    }
+
+    /// Panics if `validity` isn't the same length as `values`.
+    pub fn with_validity(values: Vec<T>, validity: Option<Bitmap>) -> Self {
+        if let Some(ref validity) = validity {
+            assert_eq!(values.len(), validity.len(), "validity must have one bit per value");
+        }
+        let len = values.len();
+        ArrayData {
+            values: Arc::new(values),
+            // an all valid bitmap carries no information
+            validity: validity
+                .filter(|v| v.count_ones(0, len) != len)
+                .map(Arc::new),
+            offset: 0,
+            len,
+        }
+    }
+
+    /// The rows visible through this array (all of them, unless it's a
+    /// slice). Null rows hold a placeholder value.
+    pub fn as_slice(&self) -> &[T] {
+        &self.values[self.offset..self.offset + self.len]
     }
 
+    /// Validity of the visible rows, `None` if there are no nulls.
+    pub fn validity(&self) -> Option<Bitmap> {
+        match self.validity {
+            Some(ref validity) if self.null_count() > 0 => Some(validity.slice_copy(self.offset, self.len)),
+            _ => None,
+        }
+    }
+
+    /// Panics if `index` is out of bounds.
+    pub fn is_null(&self, index: usize) -> bool {
+        assert!(index < self.len, "index {} out of bounds for array of len {}", index, self.len);
+        match self.validity {
+            Some(ref validity) => !validity.get(self.offset + index),
+            None => false,
+        }
+    }
+
+    pub fn null_count(&self) -> usize {
+        match self.validity {
+            Some(ref validity) => self.len - validity.count_ones(self.offset, self.len),
+            None => 0,
+        }
+    }
+
+    /// Iterates over rows, with `None` for nulls.
+    pub fn iter<'a>(&'a self) -> ArrayIterator<'a, T> {
+        ArrayIterator::new(self)
+    }
+
+    /// Zero-copy view of `len` rows starting at `offset`.
+    pub fn slice(&self, offset: usize, len: usize) -> Result<Self, Error> {
+        match offset.checked_add(len) {
+            Some(end) if end <= self.len => {
+                Ok(ArrayData {
+                    values: self.values.clone(),
+                    validity: self.validity.clone(),
+                    offset: self.offset + offset,
+                    len,
+                })
+            },
+            Some(end) => Err(format_err!("slice {}..{} out of bounds for array of len {}", offset, end, self.len)),
+            None => Err(format_err!("slice of len {} at offset {} overflows", len, offset)),
+        }
+    }
+
+    // Gets a uniquely owned vec holding exactly the visible rows,
+    // copying only if the values are shared or this is a view.
+    fn make_mut(&mut self) -> &mut Vec<T> {
+        if self.offset != 0 || self.len != self.values.len() {
+            self.values = Arc::new(self.as_slice().to_vec());
+            self.validity = self.validity.take()
+                .map(|validity| Arc::new(validity.slice_copy(self.offset, self.len)));
+            self.offset = 0;
+        }
+        Arc::make_mut(&mut self.values)
+    }
+
+    /// `f` also runs on the placeholders of null rows, which stay null.
     pub fn apply_inplace<F>(&mut self, f: F)
         where F: Fn(&mut T) + Sync + Send
     {
-        self.0.par_iter_mut().for_each(f);
+        self.make_mut().par_iter_mut().for_each(f);
     }
 
+    /// `f` also runs on the placeholders of null rows, which stay null.
     pub fn apply<F>(&self, f: F) -> Self
         where F: Fn(&T) -> T + Sync + Send
     {
-        ArrayData(
-            self.0.par_iter()
+        self.map_values(f)
+    }
+
+    /// Like `apply`, but `f` can change the value type. Nulls stay null.
+    pub fn map_values<U, F>(&self, f: F) -> ArrayData<U>
+        where U: Send + Sync + Clone,
+              F: Fn(&T) -> U + Sync + Send
+    {
+        ArrayData::with_validity(
+            self.as_slice().par_iter()
                 .map(f)
-                .collect::<Vec<_>>()
+                .collect::<Vec<_>>(),
+            self.validity(),
         )
     }
 
     // Inner option signifies null
     pub fn get(&self, index: usize) -> Option<Option<&T>> {
-        // TODO for now it's wrapped in Some to signifiy
-        // that it's always non-null
-        self.0.get(index).map(|x| Some(x))
+        if index >= self.len {
+            return None;
+        }
+        if self.is_null(index) {
+            Some(None)
+        } else {
+            Some(Some(&self.values[self.offset + index]))
+        }
     }
 
     pub fn push(&mut self, item: T) {
-        self.0.push(item);
+        self.make_mut().push(item);
+        self.len += 1;
+        if let Some(ref mut validity) = self.validity {
+            Arc::make_mut(validity).push(true);
+        }
+    }
+
+    pub fn push_null(&mut self)
+        where T: Default
+    {
+        let len = self.len;
+        self.make_mut().push(T::default());
+        self.len += 1;
+        let validity = self.validity.get_or_insert_with(|| Arc::new(Bitmap::with_value(len, true)));
+        Arc::make_mut(validity).push(false);
+    }
+
+    /// Copy with every null replaced by `value`.
+    pub fn fill_null(&self, value: T) -> Self {
+        if self.validity.is_none() {
+            return self.clone();
+        }
+        let values = self.iter().map(|x| x.cloned().unwrap_or_else(|| value.clone())).collect();
+        ArrayData::from_vec(values)
     }
 
     pub fn multiply_row(&self, multiple: usize) -> Self {
-        let mut res = Vec::new();
-        for row in &self.0 {
+        let mut res = Vec::with_capacity(self.len * multiple);
+        for row in self.as_slice() {
             for _ in 0..multiple {
                 res.push(row.clone());
             }
         }
-        ArrayData(res)
+        let validity = self.validity().map(|validity| {
+            validity.iter()
+                .flat_map(|bit| ::std::iter::repeat_n(bit, multiple))
+                .collect()
+        });
+        ArrayData::with_validity(res, validity)
     }
 
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// New array made of the rows at `indices`, in that order.
+    ///
+    /// Panics if an index is out of bounds.
+    pub(crate) fn take(&self, indices: &[usize]) -> Self {
+        let values = self.as_slice();
+        let validity = self.validity.as_ref()
+            .map(|_| indices.iter().map(|&i| !self.is_null(i)).collect());
+        ArrayData::with_validity(indices.iter().map(|&i| values[i].clone()).collect(), validity)
+    }
+
+    /// Like `take`, but a `None` index gives a null row.
+    ///
+    /// Panics if an index is out of bounds.
+    pub(crate) fn take_opt(&self, indices: &[Option<usize>]) -> Self
+        where T: Default
+    {
+        let values = self.as_slice();
+        let validity = indices.iter().map(|i| i.is_some_and(|i| !self.is_null(i))).collect();
+        let values = indices.iter().map(|i| i.map_or_else(T::default, |i| values[i].clone())).collect();
+        ArrayData::with_validity(values, Some(validity))
+    }
+
+    /// Rows of all `arrays`, one after the other.
+    pub fn concat(arrays: &[&ArrayData<T>]) -> Self {
+        let len = arrays.iter().map(|a| a.len()).sum();
+        let mut values = Vec::with_capacity(len);
+        for array in arrays {
+            values.extend_from_slice(array.as_slice());
+        }
+        let validity = if arrays.iter().any(|a| a.null_count() > 0) {
+            Some(arrays.iter().flat_map(|a| (0..a.len()).map(move |i| !a.is_null(i))).collect())
+        } else {
+            None
+        };
+        ArrayData::with_validity(values, validity)
     }
 }
 
@@ -356,18 +1064,67 @@ impl<T: Send + Sync + Clone> ArrayData<T> {
 
 /// Iterator for column types.
 pub struct ArrayIterator<'a, T: 'a> {
-    values: &'a ArrayData<T>,
+    values: IterValues<'a, T>,
     index: usize,
+    end: usize,
+}
+
+// Dictionary encoded arrays iterate over codes, looking up each value.
+pub(crate) enum IterValues<'a, T: 'a> {
+    Plain(&'a ArrayData<T>),
+    Dictionary(&'a ArrayData<u32>, &'a IndexSet<T>),
 }
 
-impl<'a, T> ArrayIterator<'a, T>
+// Only references inside, so no `T: Copy` bound like derive would add.
+impl<'a, T> Clone for IterValues<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for IterValues<'a, T> {}
+
+impl<'a, T: Send + Sync + Clone> IterValues<'a, T> {
+    pub(crate) fn len(&self) -> usize {
+        match *self {
+            IterValues::Plain(values) => values.len(),
+            IterValues::Dictionary(codes, _) => codes.len(),
+        }
+    }
+
+    pub(crate) fn get(&self, index: usize) -> Option<Option<&'a T>> {
+        match *self {
+            IterValues::Plain(values) => values.get(index),
+            IterValues::Dictionary(codes, dictionary) => {
+                codes.get(index)
+                    .map(|code| code.map(|&code| &dictionary[code as usize]))
+            },
+        }
+    }
+}
+
+impl<'a, T: Send + Sync + Clone> ArrayIterator<'a, T>
 {
     pub fn new(values: &'a ArrayData<T>) -> Self {
+        ArrayIterator::window(IterValues::Plain(values), 0, values.len())
+    }
+
+    pub fn from_dictionary(codes: &'a ArrayData<u32>, dictionary: &'a IndexSet<T>) -> Self {
+        ArrayIterator::window(IterValues::Dictionary(codes, dictionary), 0, codes.len())
+    }
+
+    /// Rows `index..end` of `values`.
+    pub(crate) fn window(values: IterValues<'a, T>, index: usize, end: usize) -> Self {
         ArrayIterator {
-            values: values,
-            index: 0,
+            values,
+            index,
+            end,
         }
     }
+
+    pub(crate) fn into_values(self) -> IterValues<'a, T> {
+        self.values
+    }
 }
 
 impl<'a, T: 'a + Clone> Iterator for ArrayIterator<'a, T>
@@ -379,6 +1136,9 @@ impl<'a, T: 'a + Clone> Iterator for ArrayIterator<'a, T>
     // whether or not a value exists.
     // The inner Option is to signify a Null
     fn next(&mut self) -> Option<Option<&'a T>> {
+        if self.index >= self.end {
+            return None;
+        }
         let res = self.values.get(self.index);
         self.index += 1;
         res
@@ -413,7 +1173,39 @@ impl_datatype_iter_for_array!(u32, Array::UInt32);
 impl_datatype_iter_for_array!(u64, Array::UInt64);
 impl_datatype_iter_for_array!(f32, Array::Float32);
 impl_datatype_iter_for_array!(f64, Array::Float64);
-impl_datatype_iter_for_array!(String, Array::Str);
+impl_datatype_iter_for_array!(bool, Array::Bool);
+impl_datatype_iter_for_array!(NaiveDate, Array::Date);
+impl_datatype_iter_for_array!(Duration, Array::Duration);
+
+impl<'a> DataTypeIterator<'a, i128> for &'a Array {
+    fn values(self) -> Result<ArrayIterator<'a, i128>, Error> {
+        match self {
+            Array::Decimal(ref dec_data) => Ok(ArrayIterator::new(dec_data.values())),
+            _ => Err(format_err!("type mismatch, array is {}", self.dtype())),
+        }
+    }
+}
+
+impl<'a> DataTypeIterator<'a, NaiveDateTime> for &'a Array {
+    fn values(self) -> Result<ArrayIterator<'a, NaiveDateTime>, Error> {
+        match self {
+            Array::Datetime(ref dt_data) => Ok(ArrayIterator::new(dt_data.values())),
+            _ => Err(format_err!("type mismatch, array is {}", self.dtype())),
+        }
+    }
+}
+
+impl<'a> DataTypeIterator<'a, String> for &'a Array {
+    fn values(self) -> Result<ArrayIterator<'a, String>, Error> {
+        match self {
+            Array::Str(ref array_data) => Ok(ArrayIterator::new(array_data)),
+            Array::Categorical(ref cat_data) => {
+                Ok(ArrayIterator::from_dictionary(cat_data.codes(), cat_data.categories()))
+            },
+            _ => Err(format_err!("type mismatch, array is {}", self.dtype())),
+        }
+    }
+}
 
 #[cfg(test)]
 mod test {
@@ -423,8 +1215,8 @@ mod test {
     fn test_dataframe_basic() {
         let df = DataFrame {
             columns: indexmap!{
-                "id".to_owned() => Array::Int8(ArrayData(vec![1,2,3,4,5])),
-                "population".to_owned() => Array::Int8(ArrayData(vec![42,22,63,34,53])),
+                "id".to_owned() => Array::Int8(ArrayData::from_vec(vec![1,2,3,4,5])),
+                "population".to_owned() => Array::Int8(ArrayData::from_vec(vec![42,22,63,34,53])),
             }
         };
         println!("{:?}", df);
@@ -434,8 +1226,8 @@ mod test {
     fn test_dataframe_add_col() {
         let mut df = DataFrame {
             columns: indexmap!{
-                "id".to_owned() => Array::Int8(ArrayData(vec![1,2,3,4,5])),
-                "population".to_owned() => Array::Int8(ArrayData(vec![42,22,63,34,53])),
+                "id".to_owned() => Array::Int8(ArrayData::from_vec(vec![1,2,3,4,5])),
+                "population".to_owned() => Array::Int8(ArrayData::from_vec(vec![42,22,63,34,53])),
             }
         };
         println!("{:?}", df);
@@ -447,8 +1239,8 @@ mod test {
 
     #[test]
     fn test_array_apply_inplace() {
-        let mut array = Array::Int8(ArrayData(vec![1,2,3]));
-        array.apply_inplace(|x: &mut i8| *x = *x*2).unwrap();
+        let mut array = Array::Int8(ArrayData::from_vec(vec![1,2,3]));
+        array.apply_inplace(|x: &mut i8| *x *= 2).unwrap();
         println!("{:?}", array);
         fn test_fn(x: &mut i8) {
             *x = x.pow(2);
@@ -459,7 +1251,7 @@ mod test {
 
     #[test]
     fn test_array_apply() {
-        let array = Array::Int8(ArrayData(vec![1,2,3]));
+        let array = Array::Int8(ArrayData::from_vec(vec![1,2,3]));
         let array1 = array.apply(|&x: &i8| x*2).unwrap();
         println!("{:?}", array1);
         fn test_fn1(x: &i8) -> i8 {
@@ -469,7 +1261,7 @@ mod test {
         let array2 = array.apply(test_fn1).unwrap();
         println!("{:?}", array2);
 
-        let array = Array::UInt8(ArrayData(vec![1,2,3]));
+        let array = Array::UInt8(ArrayData::from_vec(vec![1,2,3]));
         let array1 = array.apply(|&x: &u8| x*2);
         println!("{:?}", array1);
         fn test_fn2(x: &u8) -> u8 {
@@ -485,7 +1277,7 @@ mod test {
     #[test]
     #[should_panic]
     fn test_array_apply_wrong_type_i8() {
-        let array = Array::Int8(ArrayData(vec![1,2,3]));
+        let array = Array::Int8(ArrayData::from_vec(vec![1,2,3]));
 
         // Test to see if this compiles
         fn test_fn_bad1(x: &u8) -> u8 {
@@ -497,7 +1289,7 @@ mod test {
     #[test]
     #[should_panic]
     fn test_array_apply_wrong_type_u8() {
-        let array = Array::UInt8(ArrayData(vec![1,2,3]));
+        let array = Array::UInt8(ArrayData::from_vec(vec![1,2,3]));
         // Test to see if this compiles
         fn test_fn_bad2(x: &i8) -> i8 {
             x.pow(2)
@@ -507,12 +1299,12 @@ mod test {
 
     #[test]
     fn test_melt_basic() {
-        let mut df = DataFrame {
+        let df = DataFrame {
             columns: indexmap!{
-                "id".to_owned() => Array::Int8(ArrayData(vec![1,2,3,4,5])),
-                "id2".to_owned() => Array::Int8(ArrayData(vec![6,7,8,9,15])),
-                "A".to_owned() => Array::Int8(ArrayData(vec![42,22,63,34,53])),
-                "B".to_owned() => Array::Int8(ArrayData(vec![41,21,61,31,51])),
+                "id".to_owned() => Array::Int8(ArrayData::from_vec(vec![1,2,3,4,5])),
+                "id2".to_owned() => Array::Int8(ArrayData::from_vec(vec![6,7,8,9,15])),
+                "A".to_owned() => Array::Int8(ArrayData::from_vec(vec![42,22,63,34,53])),
+                "B".to_owned() => Array::Int8(ArrayData::from_vec(vec![41,21,61,31,51])),
             }
         };
 
@@ -526,15 +1318,221 @@ mod test {
             value_name="value"
             );
         println!("{:?}", df);
-        panic!();
+        assert_eq!(df.columns.keys().collect::<Vec<_>>(), vec!["id", "id2", "var", "value"]);
+        match df.get_col("id") {
+            Some(Array::Int8(data)) => assert_eq!(data.as_slice(), &[1,1,2,2,3,3,4,4,5,5]),
+            other => panic!("unexpected id col {:?}", other),
+        }
+        match df.get_col("var") {
+            Some(Array::Categorical(data)) => {
+                assert_eq!(data.categories().iter().collect::<Vec<_>>(), vec!["A", "B"]);
+                assert_eq!(data.codes().as_slice(), &[0,1,0,1,0,1,0,1,0,1]);
+            },
+            other => panic!("unexpected var col {:?}", other),
+        }
+        match df.get_col("value") {
+            Some(Array::UInt8(data)) => assert_eq!(data.as_slice(), &[42,41,22,21,63,61,34,31,53,51]),
+            other => panic!("unexpected value col {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_melt_duplicate_value_vars() {
+        let df = DataFrame {
+            columns: indexmap!{
+                "id".to_owned() => Array::Int8(ArrayData::from_vec(vec![1,2])),
+                "A".to_owned() => Array::Int8(ArrayData::from_vec(vec![42,22])),
+                "B".to_owned() => Array::Int8(ArrayData::from_vec(vec![41,21])),
+            }
+        };
+
+        let df = melt!(
+            df=df,
+            id_vars=["id"],
+            value_vars=[("A", i8), ("B", i8), ("A", i8)],
+            value_primitive_type=u8,
+            value_type="UInt8",
+            var_name="var",
+            value_name="value"
+            );
+        match df.get_col("var") {
+            Some(Array::Categorical(data)) => {
+                assert_eq!(data.categories().iter().collect::<Vec<_>>(), vec!["A", "B"]);
+                assert_eq!(data.codes().as_slice(), &[0,1,0,0,1,0]);
+            },
+            other => panic!("unexpected var col {:?}", other),
+        }
+        match df.get_col("value") {
+            Some(Array::UInt8(data)) => assert_eq!(data.as_slice(), &[42,41,42,22,21,22]),
+            other => panic!("unexpected value col {:?}", other),
+        }
     }
 
     #[test]
     fn test_get() {
-        let array = Array::Int8(ArrayData(vec![1,2,3]));
+        let array = Array::Int8(ArrayData::from_vec(vec![1,2,3]));
         println!("{:?}", array);
         let x: Result<Option<Option<&i8>>, Error> = array.get(0);
         println!("{:?}", x);
-        panic!();
+        assert_eq!(x.unwrap(), Some(Some(&1)));
+        let x: Result<Option<Option<&i8>>, Error> = array.get(3);
+        assert_eq!(x.unwrap(), None);
+        let x: Result<Option<Option<&u8>>, Error> = array.get(0);
+        assert!(x.is_err());
+    }
+
+    #[test]
+    fn test_array_slice() {
+        let array = Array::Int8(ArrayData::from_vec(vec![1,2,3,4,5]));
+        let view = array.slice(1, 3).unwrap();
+        assert_eq!(view.len(), 3);
+        let x: Option<Option<&i8>> = view.get(0).unwrap();
+        assert_eq!(x, Some(Some(&2)));
+        let x: Option<Option<&i8>> = view.get(3).unwrap();
+        assert_eq!(x, None);
+
+        let values: Vec<_> = DataTypeIterator::<i8>::values(&view).unwrap().collect();
+        assert_eq!(values, vec![Some(&2), Some(&3), Some(&4)]);
+
+        // slice of a slice is relative to the view
+        let view2 = view.slice(1, 2).unwrap();
+        let values: Vec<_> = DataTypeIterator::<i8>::values(&view2).unwrap().collect();
+        assert_eq!(values, vec![Some(&3), Some(&4)]);
+
+        let doubled = view.apply(|&x: &i8| x * 2).unwrap();
+        let values: Vec<_> = DataTypeIterator::<i8>::values(&doubled).unwrap().collect();
+        assert_eq!(values, vec![Some(&4), Some(&6), Some(&8)]);
+
+        assert_eq!(array.slice(3, 3).unwrap_err().to_string(), "slice 3..6 out of bounds for array of len 5");
+        assert!(array.slice(5, 0).unwrap().is_empty());
+        assert!(array.slice(usize::MAX, 2).is_err());
+    }
+
+    #[test]
+    fn test_array_slice_mutation_does_not_touch_parent() {
+        let array = Array::Int8(ArrayData::from_vec(vec![1,2,3,4,5]));
+        let mut view = array.slice(1, 2).unwrap();
+        view.apply_inplace(|x: &mut i8| *x *= 10).unwrap();
+        view.push(9i8).unwrap();
+
+        match view {
+            Array::Int8(ref data) => assert_eq!(data.as_slice(), &[20, 30, 9]),
+            _ => unreachable!(),
+        }
+        match array {
+            Array::Int8(ref data) => assert_eq!(data.as_slice(), &[1, 2, 3, 4, 5]),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_categorical_array() {
+        let array = Array::Str(ArrayData::from_vec(
+            vec!["NY", "CA", "NY"].into_iter().map(|s| s.to_owned()).collect()
+        ));
+        let cat = array.to_categorical().unwrap();
+        assert_eq!(cat.dtype(), "Categorical");
+
+        let x: Option<Option<&String>> = cat.get(2).unwrap();
+        assert_eq!(x, Some(Some(&"NY".to_owned())));
+        let values: Vec<_> = DataTypeIterator::<String>::values(&cat).unwrap().collect();
+        assert_eq!(values, vec![Some(&"NY".to_owned()), Some(&"CA".to_owned()), Some(&"NY".to_owned())]);
+
+        let lower = cat.apply(|s: &String| s.to_lowercase()).unwrap();
+        assert_eq!(lower.dtype(), "Categorical");
+        let x: Option<Option<&String>> = lower.get(1).unwrap();
+        assert_eq!(x, Some(Some(&"ca".to_owned())));
+
+        match cat.to_str_array().unwrap() {
+            Array::Str(ref data) => assert_eq!(data.as_slice(), &["NY", "CA", "NY"]),
+            other => panic!("unexpected array {:?}", other),
+        }
+        assert!(Array::Int8(ArrayData::from_vec(vec![1])).to_categorical().is_err());
+    }
+
+    #[test]
+    fn test_sort_by() {
+        let df = DataFrame {
+            columns: indexmap!{
+                "size".to_owned() => Array::Str(ArrayData::from_vec(
+                    vec!["med", "low", "high", "low"].into_iter().map(|s| s.to_owned()).collect()
+                )),
+                "x".to_owned() => Array::Float64(ArrayData::from_vec(vec![2.0, f64::NAN, -1.0, 0.5])),
+            }
+        };
+
+        // Str sorts lexically
+        let sorted = df.sort_by("size").unwrap();
+        let sizes: Vec<_> = DataTypeIterator::<String>::values(sorted.get_col("size").unwrap())
+            .unwrap()
+            .map(|s| s.unwrap().clone())
+            .collect();
+        assert_eq!(sizes, vec!["high", "low", "low", "med"]);
+
+        // Categorical sorts by category order
+        let mut df = df;
+        let size = match df.get_col("size").unwrap().to_categorical().unwrap() {
+            Array::Categorical(cat) => cat.set_categories(
+                vec!["low", "med", "high"].into_iter().map(|s| s.to_owned()).collect()
+            ).unwrap(),
+            _ => unreachable!(),
+        };
+        df.add_col("size".to_owned(), Array::Categorical(size));
+        let sorted = df.sort_by("size").unwrap();
+        let sizes: Vec<_> = DataTypeIterator::<String>::values(sorted.get_col("size").unwrap())
+            .unwrap()
+            .map(|s| s.unwrap().clone())
+            .collect();
+        assert_eq!(sizes, vec!["low", "low", "med", "high"]);
+
+        // NaN sorts last
+        let sorted = df.sort_by("x").unwrap();
+        match sorted.get_col("x") {
+            Some(Array::Float64(data)) => {
+                assert_eq!(&data.as_slice()[..3], &[-1.0, 0.5, 2.0]);
+                assert!(data.as_slice()[3].is_nan());
+            },
+            other => panic!("unexpected col {:?}", other),
+        }
+
+        assert!(df.sort_by("missing").is_err());
+    }
+
+    #[test]
+    fn test_dataframe_slice() {
+        let df = DataFrame {
+            columns: indexmap!{
+                "id".to_owned() => Array::Int8(ArrayData::from_vec(vec![1,2,3,4,5])),
+                "name".to_owned() => Array::Str(ArrayData::from_vec(
+                    vec!["a", "b", "c", "d", "e"].into_iter().map(|s| s.to_owned()).collect()
+                )),
+            }
+        };
+        let view = df.slice(3, 2).unwrap();
+        let x: Option<Option<&String>> = view.get_col("name").unwrap().get(0).unwrap();
+        assert_eq!(x, Some(Some(&"d".to_owned())));
+        assert_eq!(view.get_col("id").unwrap().len(), 2);
+
+        let err = df.slice(4, 2).unwrap_err();
+        assert!(err.to_string().contains("id"));
+    }
+
+    #[test]
+    fn test_nulls_and_concat() {
+        let mut a = Array::Int32(ArrayData::from_vec(vec![3, 1]));
+        a.push_null();
+        assert!(a.is_null(2) && !a.is_null(0));
+        assert_eq!(a.null_count(), 1);
+        assert_eq!(a.sort_indices(), vec![1, 0, 2]);
+
+        let b = a.slice(1, 2).unwrap();
+        let both = Array::concat(&[&a, &b]).unwrap();
+        assert_eq!(both.len(), 5);
+        assert_eq!(both.null_count(), 2);
+        assert_eq!(DataTypeIterator::<i32>::values(&both).unwrap().map(|x| x.cloned()).collect::<Vec<_>>(),
+            vec![Some(3), Some(1), None, Some(1), None]);
+
+        let strs = Array::Str(ArrayData::from_vec(vec!["a".to_owned()]));
+        assert!(Array::concat(&[&a, &strs]).is_err());
     }
 }
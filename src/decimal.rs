@@ -0,0 +1,549 @@
+use failure::Error;
+use rayon::prelude::*;
+
+use dataframe::{Array, ArrayData};
+
+/// Largest precision that always fits in an i128.
+pub const MAX_PRECISION: u8 = 38;
+
+/// Fixed point decimals, e.g. for currency.
+///
+/// Each value is stored unscaled: with scale 2, 1234.50 is stored as
+/// 123450. `precision` is the total number of digits allowed, `scale`
+/// the number of those after the decimal point, as in SQL NUMERIC(p,s).
+#[derive(Debug, Clone)]
+pub struct DecimalData {
+    values: ArrayData<i128>,
+    precision: u8,
+    scale: u8,
+}
+
+/// How to drop digits when reducing the scale of a Decimal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RoundingMode {
+    /// Ties away from zero (1.25 -> 1.3, -1.25 -> -1.3)
+    HalfUp,
+    /// Ties to the even neighbour, "banker's rounding" (1.25 -> 1.2)
+    HalfEven,
+    /// Towards zero, i.e. truncate
+    Down,
+    /// Away from zero
+    Up,
+    /// Towards negative infinity
+    Floor,
+    /// Towards positive infinity
+    Ceiling,
+}
+
+fn pow10(exp: u8) -> i128 {
+    10i128.pow(u32::from(exp))
+}
+
+fn check_precision(precision: u8, scale: u8) -> Result<(), Error> {
+    if precision == 0 || precision > MAX_PRECISION {
+        return Err(format_err!("decimal precision must be 1 to {}, found {}", MAX_PRECISION, precision));
+    }
+    if scale > precision {
+        return Err(format_err!("decimal scale {} is larger than precision {}", scale, precision));
+    }
+    Ok(())
+}
+
+pub(crate) fn check_fits(value: i128, precision: u8) -> Result<i128, Error> {
+    if value.unsigned_abs() < pow10(precision) as u128 {
+        Ok(value)
+    } else {
+        Err(format_err!("value {} doesn't fit in precision {}", value, precision))
+    }
+}
+
+/// Parses a decimal string into its unscaled value at `scale`.
+///
+/// Accepts a leading sign or accounting-style parentheses for
+/// negatives (not both), a leading currency symbol and `,` thousands
+/// separators between groups of three digits, e.g. "$1,234.50", "-0.5",
+/// "(12.00)". Digits past `scale` must be zeros; use a larger scale and
+/// `round` to round instead.
+pub fn parse_decimal(s: &str, scale: u8) -> Result<i128, Error> {
+    let err = || format_err!("can't parse {:?} as decimal with scale {}", s, scale);
+
+    let mut rest = s.trim();
+    let mut negative = false;
+    if rest.starts_with('(') && rest.ends_with(')') {
+        negative = true;
+        rest = rest[1..rest.len() - 1].trim();
+    }
+    if rest.starts_with('-') || rest.starts_with('+') {
+        if negative {
+            return Err(err());
+        }
+        negative = rest.starts_with('-');
+        rest = &rest[1..];
+    }
+    let rest = rest.trim_start_matches(&['$', '€', '£', '¥'][..]);
+
+    let (int_part, frac_part) = match rest.find('.') {
+        Some(i) => (&rest[..i], &rest[i + 1..]),
+        None => (rest, ""),
+    };
+    let mut groups = int_part.split(',');
+    let first_len = groups.next().map_or(0, str::len);
+    if int_part.contains(',') && (first_len == 0 || first_len > 3 || groups.any(|group| group.len() != 3)) {
+        return Err(err());
+    }
+    let int_digits: String = int_part.chars().filter(|&c| c != ',').collect();
+    if int_digits.is_empty() && frac_part.is_empty() {
+        return Err(err());
+    }
+    if !int_digits.chars().chain(frac_part.chars()).all(|c| c.is_ascii_digit()) {
+        return Err(err());
+    }
+    let (frac_digits, extra) = frac_part.split_at(frac_part.len().min(scale as usize));
+    if extra.chars().any(|c| c != '0') {
+        return Err(format_err!("{:?} has more than {} decimal places", s, scale));
+    }
+
+    let mut value: i128 = 0;
+    let padding = (scale as usize - frac_digits.len()) as u8;
+    for digit in int_digits.chars().chain(frac_digits.chars()) {
+        value = value.checked_mul(10)
+            .and_then(|v| v.checked_add(i128::from(digit as u8 - b'0')))
+            .ok_or_else(err)?;
+    }
+    value = value.checked_mul(pow10(padding)).ok_or_else(err)?;
+
+    Ok(if negative { -value } else { value })
+}
+
+/// Formats an unscaled value at `scale`, e.g. 123450 at scale 2 is "1234.50".
+pub fn format_decimal(value: i128, scale: u8) -> String {
+    let sign = if value < 0 { "-" } else { "" };
+    let abs = value.unsigned_abs();
+    if scale == 0 {
+        return format!("{}{}", sign, abs);
+    }
+    let divisor = pow10(scale) as u128;
+    format!("{}{}.{:0width$}", sign, abs / divisor, abs % divisor, width = scale as usize)
+}
+
+fn round_unscaled(value: i128, drop_digits: u8, mode: RoundingMode) -> i128 {
+    let divisor = pow10(drop_digits);
+    let quotient = value / divisor;
+    let remainder = value % divisor;
+    if remainder == 0 {
+        return quotient;
+    }
+    let away = quotient + value.signum();
+    let twice_remainder = remainder.abs() * 2;
+    match mode {
+        RoundingMode::Down => quotient,
+        RoundingMode::Up => away,
+        RoundingMode::Floor => if value < 0 { away } else { quotient },
+        RoundingMode::Ceiling => if value > 0 { away } else { quotient },
+        RoundingMode::HalfUp => if twice_remainder >= divisor { away } else { quotient },
+        RoundingMode::HalfEven => {
+            if twice_remainder > divisor || (twice_remainder == divisor && quotient % 2 != 0) {
+                away
+            } else {
+                quotient
+            }
+        },
+    }
+}
+
+impl DecimalData {
+    /// Errors if precision/scale are out of range or a value has more
+    /// than `precision` digits.
+    pub fn new(values: ArrayData<i128>, precision: u8, scale: u8) -> Result<Self, Error> {
+        check_precision(precision, scale)?;
+        for &value in values.as_slice() {
+            check_fits(value, precision)?;
+        }
+        Ok(DecimalData {
+            values,
+            precision,
+            scale,
+        })
+    }
+
+    /// Unscaled values.
+    pub fn values(&self) -> &ArrayData<i128> {
+        &self.values
+    }
+
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    pub fn scale(&self) -> u8 {
+        self.scale
+    }
+
+    /// Each value formatted with exactly `scale` decimal places. Null
+    /// rows are formatted from their placeholder; check `values().is_null`.
+    pub fn to_strings(&self) -> Vec<String> {
+        self.values.as_slice().par_iter()
+            .map(|&v| format_decimal(v, self.scale))
+            .collect()
+    }
+
+    /// Rescales to `scale`, rounding with `mode` if digits are dropped.
+    /// The precision grows or shrinks with the scale, keeping room for
+    /// a carry from rounding, so this only errors past MAX_PRECISION.
+    pub fn round(&self, scale: u8, mode: RoundingMode) -> Result<Self, Error> {
+        let int_digits = self.precision - self.scale;
+        if scale >= self.scale {
+            let precision = int_digits.saturating_add(scale);
+            check_precision(precision, scale)?;
+            let factor = pow10(scale - self.scale);
+            let values = self.values.apply(|&v| v * factor);
+            return DecimalData::new(values, precision, scale);
+        }
+
+        let precision = (int_digits + scale + 1).min(MAX_PRECISION);
+        let drop_digits = self.scale - scale;
+        let values = self.values.apply(|&v| round_unscaled(v, drop_digits, mode));
+        DecimalData::new(values, precision, scale)
+    }
+
+    // Both sides brought to the larger scale, for add and sub
+    fn aligned(&self, other: &DecimalData) -> Result<(DecimalData, DecimalData), Error> {
+        if self.len() != other.len() {
+            return Err(format_err!("length mismatch: {} and {}", self.len(), other.len()));
+        }
+        let scale = self.scale.max(other.scale);
+        Ok((self.round(scale, RoundingMode::Down)?, other.round(scale, RoundingMode::Down)?))
+    }
+
+    fn combine<F>(&self, other: &DecimalData, precision: u8, scale: u8, op: F) -> Result<Self, Error>
+        where F: Fn(i128, i128) -> Option<i128> + Sync + Send
+    {
+        // null if either side is null
+        let values = (0..self.len()).into_par_iter()
+            .map(|i| match (self.values.get(i), other.values.get(i)) {
+                (Some(Some(&a)), Some(Some(&b))) => {
+                    op(a, b)
+                        .ok_or_else(|| format_err!("decimal overflow"))
+                        .and_then(|v| check_fits(v, precision))
+                        .map(Some)
+                },
+                _ => Ok(None),
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        DecimalData::new(ArrayData::from_options(values), precision, scale)
+    }
+
+    /// Exact row by row sum. The result has the larger of the two
+    /// scales and one more integer digit than the wider input.
+    pub fn add(&self, other: &DecimalData) -> Result<Self, Error> {
+        let (a, b) = self.aligned(other)?;
+        let precision = (a.precision.max(b.precision) + 1).min(MAX_PRECISION);
+        a.combine(&b, precision, a.scale, |x, y| x.checked_add(y))
+    }
+
+    /// Exact row by row difference, sized like `add`.
+    pub fn sub(&self, other: &DecimalData) -> Result<Self, Error> {
+        let (a, b) = self.aligned(other)?;
+        let precision = (a.precision.max(b.precision) + 1).min(MAX_PRECISION);
+        a.combine(&b, precision, a.scale, |x, y| x.checked_sub(y))
+    }
+
+    /// Exact row by row product. Scales and precisions add, with the
+    /// precision capped at MAX_PRECISION (erroring on overflow).
+    pub fn mul(&self, other: &DecimalData) -> Result<Self, Error> {
+        if self.len() != other.len() {
+            return Err(format_err!("length mismatch: {} and {}", self.len(), other.len()));
+        }
+        let scale = self.scale + other.scale;
+        if scale > MAX_PRECISION {
+            return Err(format_err!("decimal scale {} too large for product", scale));
+        }
+        let precision = (self.precision + other.precision).min(MAX_PRECISION);
+        self.combine(other, precision, scale, |x, y| x.checked_mul(y))
+    }
+
+    pub fn apply_inplace<F>(&mut self, f: F)
+        where F: Fn(&mut i128) + Sync + Send
+    {
+        self.values.apply_inplace(f);
+    }
+
+    /// `f` works on unscaled values; the result isn't checked
+    /// against the precision.
+    pub fn apply<F>(&self, f: F) -> Self
+        where F: Fn(&i128) -> i128 + Sync + Send
+    {
+        DecimalData {
+            values: self.values.apply(f),
+            precision: self.precision,
+            scale: self.scale,
+        }
+    }
+
+    // Inner option signifies null
+    pub fn get(&self, index: usize) -> Option<Option<&i128>> {
+        self.values.get(index)
+    }
+
+    /// `item` is unscaled. Errors if it doesn't fit in the precision.
+    pub fn push(&mut self, item: i128) -> Result<(), Error> {
+        self.values.push(check_fits(item, self.precision)?);
+        Ok(())
+    }
+
+    pub fn push_null(&mut self) {
+        self.values.push_null();
+    }
+
+    /// `value` is unscaled. Errors if it doesn't fit in the precision.
+    pub fn fill_null(&self, value: i128) -> Result<Self, Error> {
+        Ok(DecimalData {
+            values: self.values.fill_null(check_fits(value, self.precision)?),
+            precision: self.precision,
+            scale: self.scale,
+        })
+    }
+
+    /// Errors unless all parts have the same precision and scale.
+    pub fn concat(parts: &[&DecimalData]) -> Result<Self, Error> {
+        let first = match parts.first() {
+            Some(first) => *first,
+            None => return Err(format_err!("can't concat zero arrays")),
+        };
+        if let Some(part) = parts.iter().find(|p| (p.precision, p.scale) != (first.precision, first.scale)) {
+            return Err(format_err!(
+                "can't concat Decimal({},{}) with Decimal({},{})",
+                first.precision, first.scale, part.precision, part.scale
+            ));
+        }
+        let values: Vec<_> = parts.iter().map(|part| &part.values).collect();
+        Ok(DecimalData {
+            values: ArrayData::concat(&values),
+            precision: first.precision,
+            scale: first.scale,
+        })
+    }
+
+    pub fn multiply_row(&self, multiple: usize) -> Self {
+        DecimalData {
+            values: self.values.multiply_row(multiple),
+            precision: self.precision,
+            scale: self.scale,
+        }
+    }
+
+    pub fn slice(&self, offset: usize, len: usize) -> Result<Self, Error> {
+        Ok(DecimalData {
+            values: self.values.slice(offset, len)?,
+            precision: self.precision,
+            scale: self.scale,
+        })
+    }
+
+    pub(crate) fn take(&self, indices: &[usize]) -> Self {
+        DecimalData {
+            values: self.values.take(indices),
+            precision: self.precision,
+            scale: self.scale,
+        }
+    }
+
+    pub(crate) fn take_opt(&self, indices: &[Option<usize>]) -> Self {
+        DecimalData {
+            values: self.values.take_opt(indices),
+            precision: self.precision,
+            scale: self.scale,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+/// Parses the `p` and `s` out of a "Decimal(p,s)" dtype.
+pub(crate) fn parse_decimal_dtype(dtype: &str) -> Option<(u8, u8)> {
+    let inner = dtype.trim().trim_start_matches("Decimal(").trim_end_matches(')');
+    let mut parts = inner.split(',').map(|s| s.trim().parse::<u8>());
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(p)), Some(Ok(s)), None) => Some((p, s)),
+        _ => None,
+    }
+}
+
+impl Array {
+    /// Parses a `Str` or Categorical array into Decimal(precision, scale).
+    /// See `parse_decimal` for the accepted formats.
+    pub fn parse_decimal(&self, precision: u8, scale: u8) -> Result<Array, Error> {
+        check_precision(precision, scale)?;
+        let strings = self.string_values("Decimal")?;
+        let values = strings.par_iter()
+            .enumerate()
+            .map(|(i, s)| match *s {
+                Some(s) => {
+                    parse_decimal(s, scale)
+                        .and_then(|v| check_fits(v, precision))
+                        .map(Some)
+                        .map_err(|err| format_err!("row {}: {}", i, err))
+                },
+                None => Ok(None),
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(Array::Decimal(DecimalData::new(ArrayData::from_options(values), precision, scale)?))
+    }
+
+    /// Rescales a Decimal array, see `DecimalData::round`.
+    pub fn round_decimal(&self, scale: u8, mode: RoundingMode) -> Result<Array, Error> {
+        match *self {
+            Array::Decimal(ref dec_data) => Ok(Array::Decimal(dec_data.round(scale, mode)?)),
+            _ => Err(format_err!("can't round {}, array must be Decimal", self.dtype())),
+        }
+    }
+
+    pub fn add_decimal(&self, other: &Array) -> Result<Array, Error> {
+        let (a, b) = self.decimal_pair(other)?;
+        Ok(Array::Decimal(a.add(b)?))
+    }
+
+    pub fn sub_decimal(&self, other: &Array) -> Result<Array, Error> {
+        let (a, b) = self.decimal_pair(other)?;
+        Ok(Array::Decimal(a.sub(b)?))
+    }
+
+    pub fn mul_decimal(&self, other: &Array) -> Result<Array, Error> {
+        let (a, b) = self.decimal_pair(other)?;
+        Ok(Array::Decimal(a.mul(b)?))
+    }
+
+    fn decimal_pair<'a>(&'a self, other: &'a Array) -> Result<(&'a DecimalData, &'a DecimalData), Error> {
+        match (self, other) {
+            (Array::Decimal(a), Array::Decimal(b)) => Ok((a, b)),
+            _ => Err(format_err!("expected two Decimal arrays, found {} and {}", self.dtype(), other.dtype())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dataframe::DataType;
+    use value::Value;
+
+    fn dec(xs: &[&str], precision: u8, scale: u8) -> DecimalData {
+        let values = xs.iter().map(|s| parse_decimal(s, scale).unwrap()).collect();
+        DecimalData::new(ArrayData::from_vec(values), precision, scale).unwrap()
+    }
+
+    #[test]
+    fn test_parse_decimal() {
+        assert_eq!(parse_decimal("$1,234.50", 2).unwrap(), 123450);
+        assert_eq!(parse_decimal("-$1,234.5", 2).unwrap(), -123450);
+        assert_eq!(parse_decimal("(12.00)", 2).unwrap(), -1200);
+        assert_eq!(parse_decimal(" +7 ", 2).unwrap(), 700);
+        assert_eq!(parse_decimal(".5", 1).unwrap(), 5);
+        assert_eq!(parse_decimal("1.2300", 2).unwrap(), 123);
+        assert!(parse_decimal("1.234", 2).is_err());
+        assert!(parse_decimal("abc", 2).is_err());
+        assert!(parse_decimal("", 2).is_err());
+        assert!(parse_decimal("1.2.3", 2).is_err());
+        assert!(parse_decimal("(-5)", 0).is_err());
+        assert!(parse_decimal("(+5)", 0).is_err());
+        assert_eq!(parse_decimal("12,345,678", 0).unwrap(), 12345678);
+        assert!(parse_decimal("1,2,3", 0).is_err());
+        assert!(parse_decimal("1234,567", 0).is_err());
+        assert!(parse_decimal(",123", 0).is_err());
+        assert!(parse_decimal("1,23.5", 1).is_err());
+    }
+
+    #[test]
+    fn test_check_fits() {
+        assert_eq!(check_fits(-999, 3).unwrap(), -999);
+        assert!(check_fits(1000, 3).is_err());
+        assert!(check_fits(i128::MIN, 38).is_err());
+        assert!(check_fits(i128::MAX, 38).is_err());
+    }
+
+    #[test]
+    fn test_push_checks_precision() {
+        let mut array = Array::new("Decimal(4,2)").unwrap();
+        DataType::<i128>::push(&mut array, 9999).unwrap();
+        assert!(DataType::<i128>::push(&mut array, 10i128.pow(30)).is_err());
+        assert_eq!(array.len(), 1);
+
+        assert!(DataType::<i128>::apply(&array, |x| x * 10).is_err());
+        assert!(DataType::<i128>::apply_inplace(&mut array, |x| *x *= 10).is_err());
+        assert_eq!(array.get_value(0), Some(Value::Decimal(9999, 2)));
+        DataType::<i128>::apply_inplace(&mut array, |x| *x = -*x).unwrap();
+        assert_eq!(array.get_value(0), Some(Value::Decimal(-9999, 2)));
+    }
+
+    #[test]
+    fn test_format_decimal() {
+        assert_eq!(format_decimal(123450, 2), "1234.50");
+        assert_eq!(format_decimal(-5, 2), "-0.05");
+        assert_eq!(format_decimal(42, 0), "42");
+    }
+
+    #[test]
+    fn test_arithmetic_is_exact() {
+        let a = dec(&["0.10", "1.00"], 5, 2);
+        let b = dec(&["0.20", "0.005"], 6, 3);
+
+        let sum = a.add(&b).unwrap();
+        assert_eq!((sum.precision(), sum.scale()), (7, 3));
+        assert_eq!(sum.to_strings(), vec!["0.300", "1.005"]);
+
+        let diff = a.sub(&b).unwrap();
+        assert_eq!(diff.to_strings(), vec!["-0.100", "0.995"]);
+
+        let product = a.mul(&b).unwrap();
+        assert_eq!((product.precision(), product.scale()), (11, 5));
+        assert_eq!(product.to_strings(), vec!["0.02000", "0.00500"]);
+
+        let big = dec(&["99999999999999999999999999999999999999"], 38, 0);
+        assert!(big.add(&big).is_err());
+        assert!(big.mul(&big).is_err());
+        assert!(a.add(&dec(&["1"], 1, 0)).is_err());
+    }
+
+    #[test]
+    fn test_round() {
+        let xs = dec(&["1.25", "1.35", "-1.25", "1.21", "-1.21", "9.99"], 3, 2);
+        let rounded = |mode| xs.round(1, mode).unwrap().to_strings();
+        assert_eq!(rounded(RoundingMode::HalfUp), vec!["1.3", "1.4", "-1.3", "1.2", "-1.2", "10.0"]);
+        assert_eq!(rounded(RoundingMode::HalfEven), vec!["1.2", "1.4", "-1.2", "1.2", "-1.2", "10.0"]);
+        assert_eq!(rounded(RoundingMode::Down), vec!["1.2", "1.3", "-1.2", "1.2", "-1.2", "9.9"]);
+        assert_eq!(rounded(RoundingMode::Up), vec!["1.3", "1.4", "-1.3", "1.3", "-1.3", "10.0"]);
+        assert_eq!(rounded(RoundingMode::Floor), vec!["1.2", "1.3", "-1.3", "1.2", "-1.3", "9.9"]);
+        assert_eq!(rounded(RoundingMode::Ceiling), vec!["1.3", "1.4", "-1.2", "1.3", "-1.2", "10.0"]);
+
+        let wider = xs.round(4, RoundingMode::HalfUp).unwrap();
+        assert_eq!((wider.precision(), wider.scale()), (5, 4));
+        assert_eq!(wider.to_strings()[0], "1.2500");
+    }
+
+    #[test]
+    fn test_array_parse_decimal() {
+        let strings = Array::Str(ArrayData::from_vec(vec!["$1,234.50".to_owned(), "(3.25)".to_owned()]));
+        let array = strings.parse_decimal(10, 2).unwrap();
+        assert_eq!(array.dtype(), "Decimal(10,2)");
+
+        let doubled = array.add_decimal(&array).unwrap();
+        match doubled {
+            Array::Decimal(ref data) => assert_eq!(data.to_strings(), vec!["2469.00", "-6.50"]),
+            ref other => panic!("expected Decimal, found {:?}", other),
+        }
+
+        let err = strings.parse_decimal(3, 2).unwrap_err();
+        assert!(err.to_string().contains("row 0"));
+        assert!(strings.parse_decimal(39, 2).is_err());
+        assert!(array.add_decimal(&strings).is_err());
+        assert_eq!(parse_decimal_dtype("Decimal(10, 2)"), Some((10, 2)));
+        assert_eq!(parse_decimal_dtype("Decimal(10)"), None);
+    }
+}
@@ -0,0 +1,269 @@
+use chrono::{Datelike, Timelike};
+use failure::Error;
+use indexmap::IndexMap;
+use rayon::prelude::*;
+
+use dataframe::{Array, ArrayData, DataFrame};
+
+/// One cell in a form that can be hashed and compared, whatever the
+/// dtype. Categorical cells are their codes, so keys from two
+/// Categorical arrays only match if the arrays share categories.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum KeyValue<'a> {
+    Null,
+    Int(i128),
+    // Bits of the f64, with -0.0 as 0.0 and a single NaN
+    Float(u64),
+    Str(&'a str),
+    Code(u32),
+}
+
+fn float_key(x: f64) -> KeyValue<'static> {
+    if x.is_nan() {
+        KeyValue::Float(f64::NAN.to_bits())
+    } else if x == 0.0 {
+        KeyValue::Float(0)
+    } else {
+        KeyValue::Float(x.to_bits())
+    }
+}
+
+fn keys_with<'a, T, F>(array_data: &'a ArrayData<T>, f: F) -> Vec<KeyValue<'a>>
+    where T: Send + Sync + Clone,
+          F: Fn(&'a T) -> KeyValue<'a>,
+{
+    array_data.iter().map(|x| x.map_or(KeyValue::Null, &f)).collect()
+}
+
+// The cells of an array as keys, one per row.
+pub(crate) fn key_values(array: &Array) -> Vec<KeyValue<'_>> {
+    use self::Array::*;
+    use self::KeyValue::{Code, Int};
+    match *array {
+        Int8(ref array_data) => keys_with(array_data, |&x| Int(x.into())),
+        Int16(ref array_data) => keys_with(array_data, |&x| Int(x.into())),
+        Int32(ref array_data) => keys_with(array_data, |&x| Int(x.into())),
+        Int64(ref array_data) => keys_with(array_data, |&x| Int(x.into())),
+        UInt8(ref array_data) => keys_with(array_data, |&x| Int(x.into())),
+        UInt16(ref array_data) => keys_with(array_data, |&x| Int(x.into())),
+        UInt32(ref array_data) => keys_with(array_data, |&x| Int(x.into())),
+        UInt64(ref array_data) => keys_with(array_data, |&x| Int(x.into())),
+        Float32(ref array_data) => keys_with(array_data, |&x| float_key(x.into())),
+        Float64(ref array_data) => keys_with(array_data, |&x| float_key(x)),
+        Bool(ref array_data) => keys_with(array_data, |&x| Int(x.into())),
+        Str(ref array_data) => keys_with(array_data, |x| KeyValue::Str(x)),
+        Categorical(ref cat_data) => keys_with(cat_data.codes(), |&x| Code(x)),
+        Date(ref array_data) => keys_with(array_data, |x| Int(x.num_days_from_ce().into())),
+        Datetime(ref dt_data) => keys_with(dt_data.values(), |x| {
+            Int(i128::from(x.and_utc().timestamp()) * 1_000_000_000 + i128::from(x.nanosecond()))
+        }),
+        Duration(ref array_data) => keys_with(array_data, |x| {
+            Int(i128::from(x.num_seconds()) * 1_000_000_000 + i128::from(x.subsec_nanos()))
+        }),
+        Decimal(ref dec_data) => keys_with(dec_data.values(), |&x| Int(x)),
+    }
+}
+
+// Row indices grouped by equal values across all `columns`, with
+// groups in order of first appearance. Row keys are built in parallel;
+// the columns must all have the same length.
+pub(crate) fn row_groups(columns: &[&Array]) -> Vec<Vec<usize>> {
+    let len = columns.first().map_or(0, |c| c.len());
+    let keys: Vec<Vec<KeyValue>> = columns.par_iter().map(|c| key_values(c)).collect();
+    let row_keys: Vec<Vec<KeyValue>> = (0..len).into_par_iter()
+        .map(|row| keys.iter().map(|k| k[row]).collect())
+        .collect();
+
+    let mut groups: IndexMap<Vec<KeyValue>, Vec<usize>> = IndexMap::new();
+    for (row, key) in row_keys.into_iter().enumerate() {
+        groups.entry(key).or_default().push(row);
+    }
+    groups.into_iter().map(|(_, rows)| rows).collect()
+}
+
+/// Rows of a DataFrame split into groups by the values of some key
+/// columns, made by `DataFrame::group_by`.
+#[derive(Debug, Clone)]
+pub struct GroupBy<'a> {
+    df: &'a DataFrame,
+    by: Vec<String>,
+    groups: Vec<Vec<usize>>,
+}
+
+impl DataFrame {
+    /// Groups rows with equal values in all of the `by` columns.
+    /// Groups are in order of first appearance, and nulls form a group
+    /// of their own. Errors if a column is missing or the key columns
+    /// have different lengths.
+    pub fn group_by(&self, by: &[&str]) -> Result<GroupBy<'_>, Error> {
+        if by.is_empty() {
+            return Err(format_err!("group_by needs at least one column"));
+        }
+        let columns = self.columns_of(by)?;
+        Ok(GroupBy {
+            df: self,
+            by: by.iter().map(|s| s.to_string()).collect(),
+            groups: row_groups(&columns),
+        })
+    }
+
+    // The named columns, checked to exist and have the same length.
+    // No names means every column.
+    pub(crate) fn columns_of(&self, names: &[&str]) -> Result<Vec<&Array>, Error> {
+        let all_names: Vec<&str>;
+        let names = if names.is_empty() {
+            all_names = self.columns.keys().map(|name| name.as_str()).collect();
+            &all_names
+        } else {
+            names
+        };
+        let columns = names.iter()
+            .map(|&name| self.get_col(name).ok_or_else(|| format_err!("column {} not found", name)))
+            .collect::<Result<Vec<_>, Error>>()?;
+        if let Some(i) = columns.iter().position(|c| c.len() != columns[0].len()) {
+            return Err(format_err!("column {} has len {}, column {} has len {}",
+                names[i], columns[i].len(), names[0], columns[0].len()));
+        }
+        Ok(columns)
+    }
+}
+
+impl<'a> GroupBy<'a> {
+    /// Row indices of each group, in order.
+    pub fn groups(&self) -> &[Vec<usize>] {
+        &self.groups
+    }
+
+    /// Number of groups.
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// The key columns, with one row per group.
+    pub fn keys(&self) -> DataFrame {
+        let first_rows: Vec<usize> = self.groups.iter().map(|rows| rows[0]).collect();
+        let mut df = DataFrame::new();
+        for name in &self.by {
+            df.add_col(name.clone(), self.df.columns[name].take(&first_rows));
+        }
+        df
+    }
+
+    /// Runs `f` on each group's rows of `col_name` (in parallel), and
+    /// puts the results back in the original row order. `f` must return
+    /// as many rows as it's given, all of the same dtype.
+    ///
+    /// ```
+    /// # extern crate llamas2;
+    /// # use llamas2::dataframe::{Array, ArrayData, DataFrame};
+    /// # use llamas2::value::Value;
+    /// # fn main() {
+    /// let mut df = DataFrame::new();
+    /// let states = vec!["NY".to_owned(), "CA".to_owned(), "NY".to_owned()];
+    /// df.add_col("state".to_owned(), Array::Str(ArrayData::from_vec(states)));
+    /// df.add_col("sales".to_owned(), Array::Int64(ArrayData::from_vec(vec![1, 10, 2])));
+    ///
+    /// let running = df.group_by(&["state"]).unwrap().transform("sales", |a| a.cumsum()).unwrap();
+    /// assert_eq!(running.get_value(2), Some(Value::Int64(3)));
+    /// # }
+    /// ```
+    pub fn transform<F>(&self, col_name: &str, f: F) -> Result<Array, Error>
+        where F: Fn(&Array) -> Result<Array, Error> + Sync + Send
+    {
+        let array = self.df.get_col(col_name)
+            .ok_or_else(|| format_err!("column {} not found", col_name))?;
+        let order: Vec<usize> = self.groups.concat();
+        if array.len() != order.len() {
+            return Err(format_err!(
+                "column {} has len {}, group keys have len {}",
+                col_name, array.len(), order.len()
+            ));
+        }
+
+        // Rows sorted by group, so each group is a zero-copy slice
+        let grouped = array.take(&order);
+        if self.groups.is_empty() {
+            return f(&grouped);
+        }
+        let offsets: Vec<usize> = self.groups.iter()
+            .scan(0, |offset, rows| {
+                let start = *offset;
+                *offset += rows.len();
+                Some(start)
+            })
+            .collect();
+        let results = self.groups.par_iter()
+            .zip(offsets.par_iter())
+            .map(|(rows, &offset)| {
+                let result = f(&grouped.slice(offset, rows.len())?)?;
+                if result.len() != rows.len() {
+                    return Err(format_err!(
+                        "transform returned {} rows for a group of {}",
+                        result.len(), rows.len()
+                    ));
+                }
+                Ok(result)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let combined = Array::concat(&results.iter().collect::<Vec<_>>())?;
+
+        let mut inverse = vec![0; order.len()];
+        for (position, &row) in order.iter().enumerate() {
+            inverse[row] = position;
+        }
+        Ok(combined.take(&inverse))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dataframe::DataTypeIterator;
+
+    fn sample() -> DataFrame {
+        let mut df = DataFrame::new();
+        let states = ["NY", "CA", "NY", "CA", "NY"].iter().map(|s| s.to_string()).collect();
+        df.add_col("state".to_owned(), Array::Str(ArrayData::from_vec(states)).to_categorical().unwrap());
+        df.add_col("sales".to_owned(), Array::Int64(ArrayData::from_vec(vec![1, 10, 2, 20, 3])));
+        df
+    }
+
+    #[test]
+    fn test_group_by() {
+        let df = sample();
+        let gb = df.group_by(&["state"]).unwrap();
+        assert_eq!(gb.groups(), &[vec![0, 2, 4], vec![1, 3]]);
+        let keys = gb.keys();
+        assert_eq!(DataTypeIterator::<String>::values(&keys.columns["state"]).unwrap().map(|x| x.cloned()).collect::<Vec<_>>(),
+            vec![Some("NY".to_owned()), Some("CA".to_owned())]);
+
+        assert!(df.group_by(&["missing"]).is_err());
+    }
+
+    #[test]
+    fn test_group_by_nulls_and_floats() {
+        let mut df = DataFrame::new();
+        df.add_col("x".to_owned(), Array::Float64(ArrayData::from_options(vec![Some(0.0), None, Some(-0.0), None])));
+        assert_eq!(df.group_by(&["x"]).unwrap().groups(), &[vec![0, 2], vec![1, 3]]);
+    }
+
+    #[test]
+    fn test_transform() {
+        let df = sample();
+        let gb = df.group_by(&["state"]).unwrap();
+
+        let running = gb.transform("sales", |a| a.cumsum()).unwrap();
+        assert_eq!(DataTypeIterator::<i64>::values(&running).unwrap().map(|x| x.cloned()).collect::<Vec<_>>(),
+            vec![Some(1), Some(10), Some(3), Some(30), Some(6)]);
+
+        let prev = gb.transform("sales", |a| Ok(a.shift(1))).unwrap();
+        assert_eq!(DataTypeIterator::<i64>::values(&prev).unwrap().map(|x| x.cloned()).collect::<Vec<_>>(),
+            vec![None, None, Some(1), Some(10), Some(2)]);
+
+        assert!(gb.transform("sales", |a| a.slice(0, 1)).is_err());
+    }
+}
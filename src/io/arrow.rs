@@ -0,0 +1,302 @@
+//! Conversion between DataFrames and Arrow record batches, shared by
+//! the Arrow IPC and Parquet readers and writers.
+//!
+//! Each Array maps to the matching Arrow type, with the validity bitmap
+//! as the Arrow null buffer:
+//!
+//! | llamas2         | Arrow                                  |
+//! |-----------------|----------------------------------------|
+//! | Int8..UInt64    | Int8..UInt64                           |
+//! | Float32/Float64 | Float32/Float64                        |
+//! | Bool            | Boolean                                |
+//! | Str             | Utf8 (LargeUtf8 also read)             |
+//! | Categorical     | Dictionary(UInt32, Utf8)               |
+//! | Date            | Date32 (Date64 also read)              |
+//! | Datetime        | Timestamp(Microsecond, offset if any)  |
+//! | Duration        | Duration(Microsecond)                  |
+//! | Decimal         | Decimal128(precision, scale)           |
+//!
+//! Timestamps and durations of any unit are read. Timezones must be
+//! fixed offsets like "+05:00" (or "UTC").
+
+use arrow_array::cast::AsArray;
+use arrow_array::types::{
+    ArrowPrimitiveType, Date32Type, Date64Type, Decimal128Type, DurationMicrosecondType,
+    DurationMillisecondType, DurationNanosecondType, DurationSecondType, Float32Type, Float64Type,
+    Int16Type, Int32Type, Int64Type, Int8Type, TimestampMicrosecondType, TimestampMillisecondType,
+    TimestampNanosecondType, TimestampSecondType, UInt16Type, UInt32Type, UInt64Type, UInt8Type,
+};
+use arrow_array::{
+    Array as ArrowArray, ArrayRef, BooleanArray, DictionaryArray, PrimitiveArray, RecordBatch,
+    RecordBatchOptions, StringArray,
+};
+use arrow_buffer::NullBuffer;
+use arrow_schema::{DataType as ArrowType, Field, Schema, SchemaRef, TimeUnit};
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate, NaiveDateTime};
+use failure::Error;
+use indexmap::IndexSet;
+use std::sync::Arc;
+
+use bitmap::Bitmap;
+use categorical::CategoricalData;
+use dataframe::{Array, ArrayData, DataFrame};
+use decimal::DecimalData;
+use temporal::DatetimeData;
+
+fn nulls<T: Send + Sync + Clone>(array_data: &ArrayData<T>) -> Option<NullBuffer> {
+    if array_data.null_count() == 0 {
+        return None;
+    }
+    let valid: Vec<bool> = (0..array_data.len()).map(|i| !array_data.is_null(i)).collect();
+    Some(NullBuffer::from(valid))
+}
+
+fn primitive<T: ArrowPrimitiveType>(array_data: &ArrayData<T::Native>) -> PrimitiveArray<T>
+    where T::Native: Send + Sync
+{
+    PrimitiveArray::new(array_data.as_slice().to_vec().into(), nulls(array_data))
+}
+
+// Converts the non-null values, with `T::default()` for nulls
+fn map_primitive<T, U, F>(array_data: &ArrayData<T>, f: F) -> Result<PrimitiveArray<U>, Error>
+    where T: Send + Sync + Clone,
+          U: ArrowPrimitiveType,
+          F: Fn(&T) -> Result<U::Native, Error>
+{
+    let values = array_data.as_slice().iter()
+        .enumerate()
+        .map(|(i, x)| if array_data.is_null(i) { Ok(U::Native::default()) } else { f(x) })
+        .collect::<Result<Vec<_>, Error>>()?;
+    Ok(PrimitiveArray::new(values.into(), nulls(array_data)))
+}
+
+fn epoch() -> NaiveDate {
+    NaiveDate::from_ymd_opt(1970, 1, 1).expect("valid date")
+}
+
+fn to_arrow(array: &Array) -> Result<ArrayRef, Error> {
+    Ok(match *array {
+        Array::Int8(ref array_data) => Arc::new(primitive::<Int8Type>(array_data)),
+        Array::Int16(ref array_data) => Arc::new(primitive::<Int16Type>(array_data)),
+        Array::Int32(ref array_data) => Arc::new(primitive::<Int32Type>(array_data)),
+        Array::Int64(ref array_data) => Arc::new(primitive::<Int64Type>(array_data)),
+        Array::UInt8(ref array_data) => Arc::new(primitive::<UInt8Type>(array_data)),
+        Array::UInt16(ref array_data) => Arc::new(primitive::<UInt16Type>(array_data)),
+        Array::UInt32(ref array_data) => Arc::new(primitive::<UInt32Type>(array_data)),
+        Array::UInt64(ref array_data) => Arc::new(primitive::<UInt64Type>(array_data)),
+        Array::Float32(ref array_data) => Arc::new(primitive::<Float32Type>(array_data)),
+        Array::Float64(ref array_data) => Arc::new(primitive::<Float64Type>(array_data)),
+        Array::Bool(ref array_data) => {
+            let values = array_data.as_slice().iter().cloned().collect();
+            Arc::new(BooleanArray::new(values, nulls(array_data)))
+        },
+        Array::Str(ref array_data) => {
+            let values: Vec<Option<&str>> = array_data.iter().map(|s| s.map(|s| s.as_str())).collect();
+            Arc::new(StringArray::from(values))
+        },
+        Array::Categorical(ref cat_data) => {
+            let keys = primitive::<UInt32Type>(cat_data.codes());
+            let values = StringArray::from_iter_values(cat_data.categories().iter());
+            Arc::new(DictionaryArray::try_new(keys, Arc::new(values))?)
+        },
+        Array::Date(ref array_data) => {
+            Arc::new(map_primitive::<_, Date32Type, _>(array_data, |date| {
+                Ok((*date - epoch()).num_days() as i32)
+            })?)
+        },
+        Array::Datetime(ref dt_data) => {
+            let values = map_primitive::<_, TimestampMicrosecondType, _>(dt_data.values(), |dt| {
+                Ok(dt.and_utc().timestamp_micros())
+            })?;
+            Arc::new(values.with_timezone_opt(dt_data.offset().map(|offset| offset.to_string())))
+        },
+        Array::Duration(ref array_data) => {
+            Arc::new(map_primitive::<_, DurationMicrosecondType, _>(array_data, |duration| {
+                duration.num_microseconds()
+                    .ok_or_else(|| format_err!("duration {} overflows microseconds", duration))
+            })?)
+        },
+        Array::Decimal(ref dec_data) => {
+            let values = primitive::<Decimal128Type>(dec_data.values())
+                .with_precision_and_scale(dec_data.precision(), dec_data.scale() as i8)?;
+            Arc::new(values)
+        },
+    })
+}
+
+fn validity(array: &dyn ArrowArray) -> Option<Bitmap> {
+    array.nulls().map(|nulls| nulls.iter().collect())
+}
+
+fn from_primitive<T: ArrowPrimitiveType>(array: &dyn ArrowArray) -> ArrayData<T::Native>
+    where T::Native: Send + Sync
+{
+    ArrayData::with_validity(array.as_primitive::<T>().values().to_vec(), validity(array))
+}
+
+// Converts the non-null values, with `U::default()` for nulls
+fn map_from_primitive<T, U, F>(array: &dyn ArrowArray, f: F) -> Result<ArrayData<U>, Error>
+    where T: ArrowPrimitiveType,
+          U: Send + Sync + Clone + Default,
+          F: Fn(T::Native) -> Option<U>
+{
+    let values = array.as_primitive::<T>().values().iter()
+        .enumerate()
+        .map(|(i, &x)| if array.is_null(i) {
+            Ok(U::default())
+        } else {
+            f(x).ok_or_else(|| format_err!("value {:?} out of range", x))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    Ok(ArrayData::with_validity(values, validity(array)))
+}
+
+fn timestamps<T>(array: &dyn ArrowArray, f: fn(i64) -> Option<DateTime<chrono::Utc>>)
+    -> Result<ArrayData<NaiveDateTime>, Error>
+    where T: ArrowPrimitiveType<Native = i64>
+{
+    map_from_primitive::<T, _, _>(array, |x| f(x).map(|dt| dt.naive_utc()))
+}
+
+fn durations<T>(array: &dyn ArrowArray, f: fn(i64) -> Option<Duration>) -> Result<ArrayData<Duration>, Error>
+    where T: ArrowPrimitiveType<Native = i64>
+{
+    map_from_primitive::<T, _, _>(array, f)
+}
+
+fn parse_offset(tz: &str) -> Result<FixedOffset, Error> {
+    match tz {
+        "UTC" | "Z" => Ok(FixedOffset::east_opt(0).expect("valid offset")),
+        _ => tz.parse().map_err(|_| format_err!("unsupported timezone {}, expected a fixed offset", tz)),
+    }
+}
+
+fn strings<'a, I: Iterator<Item = Option<&'a str>>>(values: I) -> ArrayData<String> {
+    ArrayData::from_options(values.map(|s| s.map(|s| s.to_owned())).collect())
+}
+
+fn from_dictionary(array: &dyn ArrowArray) -> Result<Array, Error> {
+    let dict = array.as_any_dictionary();
+    let values = match *dict.values().data_type() {
+        ArrowType::Utf8 => dict.values().as_string::<i32>(),
+        ref other => return Err(format_err!("unsupported dictionary values {}", other)),
+    };
+    let categories: IndexSet<String> = values.iter().flatten().map(|s| s.to_owned()).collect();
+    // keep the dictionary as is when it maps one to one
+    if values.null_count() == 0 && categories.len() == values.len() && !categories.is_empty() {
+        let codes = dict.normalized_keys().into_iter().map(|code| code as u32).collect();
+        let codes = ArrayData::with_validity(codes, validity(dict.keys()));
+        return Ok(Array::Categorical(CategoricalData::new(codes, categories)?));
+    }
+    // normalized_keys panics on an empty dictionary, where every key is null
+    let keys = if values.is_empty() { vec![0; array.len()] } else { dict.normalized_keys() };
+    let decoded = keys.into_iter()
+        .enumerate()
+        .map(|(i, key)| (dict.keys().is_valid(i) && values.is_valid(key)).then(|| values.value(key)));
+    Ok(Array::Categorical(CategoricalData::encode_array(&strings(decoded))))
+}
+
+fn from_arrow(array: &dyn ArrowArray) -> Result<Array, Error> {
+    Ok(match *array.data_type() {
+        ArrowType::Int8 => Array::Int8(from_primitive::<Int8Type>(array)),
+        ArrowType::Int16 => Array::Int16(from_primitive::<Int16Type>(array)),
+        ArrowType::Int32 => Array::Int32(from_primitive::<Int32Type>(array)),
+        ArrowType::Int64 => Array::Int64(from_primitive::<Int64Type>(array)),
+        ArrowType::UInt8 => Array::UInt8(from_primitive::<UInt8Type>(array)),
+        ArrowType::UInt16 => Array::UInt16(from_primitive::<UInt16Type>(array)),
+        ArrowType::UInt32 => Array::UInt32(from_primitive::<UInt32Type>(array)),
+        ArrowType::UInt64 => Array::UInt64(from_primitive::<UInt64Type>(array)),
+        ArrowType::Float32 => Array::Float32(from_primitive::<Float32Type>(array)),
+        ArrowType::Float64 => Array::Float64(from_primitive::<Float64Type>(array)),
+        ArrowType::Boolean => {
+            let values = array.as_boolean().values().iter().collect();
+            Array::Bool(ArrayData::with_validity(values, validity(array)))
+        },
+        ArrowType::Utf8 => Array::Str(strings(array.as_string::<i32>().iter())),
+        ArrowType::LargeUtf8 => Array::Str(strings(array.as_string::<i64>().iter())),
+        ArrowType::Dictionary(..) => from_dictionary(array)?,
+        ArrowType::Date32 => {
+            Array::Date(map_from_primitive::<Date32Type, _, _>(array, |days| {
+                epoch().checked_add_signed(Duration::days(days as i64))
+            })?)
+        },
+        ArrowType::Date64 => {
+            Array::Date(map_from_primitive::<Date64Type, _, _>(array, |ms| {
+                DateTime::from_timestamp_millis(ms).map(|dt| dt.date_naive())
+            })?)
+        },
+        ArrowType::Timestamp(ref unit, ref tz) => {
+            let values = match *unit {
+                TimeUnit::Second => timestamps::<TimestampSecondType>(array, |x| DateTime::from_timestamp(x, 0))?,
+                TimeUnit::Millisecond => timestamps::<TimestampMillisecondType>(array, DateTime::from_timestamp_millis)?,
+                TimeUnit::Microsecond => timestamps::<TimestampMicrosecondType>(array, DateTime::from_timestamp_micros)?,
+                TimeUnit::Nanosecond => timestamps::<TimestampNanosecondType>(array, |x| Some(DateTime::from_timestamp_nanos(x)))?,
+            };
+            let offset = match *tz {
+                Some(ref tz) => Some(parse_offset(tz)?),
+                None => None,
+            };
+            Array::Datetime(DatetimeData::new(values, offset))
+        },
+        ArrowType::Duration(ref unit) => {
+            Array::Duration(match *unit {
+                TimeUnit::Second => durations::<DurationSecondType>(array, Duration::try_seconds)?,
+                TimeUnit::Millisecond => durations::<DurationMillisecondType>(array, Duration::try_milliseconds)?,
+                TimeUnit::Microsecond => durations::<DurationMicrosecondType>(array, |x| Some(Duration::microseconds(x)))?,
+                TimeUnit::Nanosecond => durations::<DurationNanosecondType>(array, |x| Some(Duration::nanoseconds(x)))?,
+            })
+        },
+        ArrowType::Decimal128(precision, scale) => {
+            if scale < 0 {
+                return Err(format_err!("unsupported negative decimal scale {}", scale));
+            }
+            Array::Decimal(DecimalData::new(from_primitive::<Decimal128Type>(array), precision, scale as u8)?)
+        },
+        ref other => return Err(format_err!("unsupported arrow type {}", other)),
+    })
+}
+
+impl DataFrame {
+    /// All columns as one Arrow record batch. Errors if the columns
+    /// aren't all the same length.
+    pub fn to_record_batch(&self) -> Result<RecordBatch, Error> {
+        let columns = self.columns_of(&[])?.into_iter()
+            .map(to_arrow)
+            .collect::<Result<Vec<_>, Error>>()?;
+        let fields: Vec<Field> = self.columns.keys().zip(&columns)
+            .map(|(name, column)| Field::new(name.clone(), column.data_type().clone(), true))
+            .collect();
+        let options = RecordBatchOptions::new().with_row_count(Some(self.n_rows()));
+        Ok(RecordBatch::try_new_with_options(Arc::new(Schema::new(fields)), columns, &options)?)
+    }
+
+    /// Errors on Arrow types with no matching Array, or on a field name
+    /// used twice, which Arrow allows but a DataFrame doesn't.
+    pub fn from_record_batch(batch: &RecordBatch) -> Result<Self, Error> {
+        let mut df = DataFrame::new();
+        for (field, column) in batch.schema().fields().iter().zip(batch.columns()) {
+            if df.columns.contains_key(field.name()) {
+                return Err(format_err!("duplicate column {}", field.name()));
+            }
+            let array = from_arrow(column.as_ref())
+                .map_err(|err| format_err!("column {}: {}", field.name(), err))?;
+            df.add_col(field.name().clone(), array);
+        }
+        Ok(df)
+    }
+
+    /// Record batches sharing `schema`, concatenated. No batches gives
+    /// an empty DataFrame with the schema's columns.
+    pub fn from_record_batches(schema: SchemaRef, batches: &[RecordBatch]) -> Result<Self, Error> {
+        if batches.is_empty() {
+            return DataFrame::from_record_batch(&RecordBatch::new_empty(schema));
+        }
+        let dfs = batches.iter().map(DataFrame::from_record_batch).collect::<Result<Vec<_>, _>>()?;
+        let mut df = DataFrame::new();
+        for (i, field) in schema.fields().iter().enumerate() {
+            let parts: Vec<&Array> = dfs.iter().map(|df| &df.columns[i]).collect();
+            df.add_col(field.name().clone(), Array::concat(&parts)?);
+        }
+        Ok(df)
+    }
+}
@@ -0,0 +1,122 @@
+//! Stream compression for text formats. The codecs need the
+//! `compression` feature, on by default.
+
+#[cfg(feature = "compression")]
+use bzip2::read::MultiBzDecoder;
+#[cfg(feature = "compression")]
+use bzip2::write::BzEncoder;
+use failure::Error;
+#[cfg(feature = "compression")]
+use flate2::read::MultiGzDecoder;
+#[cfg(feature = "compression")]
+use flate2::write::GzEncoder;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+/// Compression codec of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+    Bzip2,
+}
+
+impl Compression {
+    /// Going by extension: `.gz`, `.zst` (or `.zstd`), `.bz2`, else
+    /// None.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("zst") | Some("zstd") => Compression::Zstd,
+            Some("bz2") => Compression::Bzip2,
+            _ => Compression::None,
+        }
+    }
+
+    /// Decompresses `rdr` as it's read.
+    pub(crate) fn decoder<'a, R: Read + 'a>(self, rdr: R) -> Result<Box<dyn Read + 'a>, Error> {
+        match self {
+            Compression::None => Ok(Box::new(rdr)),
+            #[cfg(feature = "compression")]
+            Compression::Gzip => Ok(Box::new(MultiGzDecoder::new(rdr))),
+            #[cfg(feature = "compression")]
+            Compression::Zstd => Ok(Box::new(zstd::Decoder::new(rdr)?)),
+            #[cfg(feature = "compression")]
+            Compression::Bzip2 => Ok(Box::new(MultiBzDecoder::new(rdr))),
+            #[cfg(not(feature = "compression"))]
+            _ => Err(format_err!("{:?} needs the compression feature", self)),
+        }
+    }
+
+    /// Compresses as it's written. Call `Encoder::finish` when done.
+    pub(crate) fn encoder<W: Write>(self, wtr: W) -> Result<Encoder<W>, Error> {
+        match self {
+            Compression::None => Ok(Encoder::Plain(wtr)),
+            #[cfg(feature = "compression")]
+            Compression::Gzip => Ok(Encoder::Gzip(GzEncoder::new(wtr, flate2::Compression::default()))),
+            #[cfg(feature = "compression")]
+            Compression::Zstd => Ok(Encoder::Zstd(zstd::Encoder::new(wtr, 0)?)),
+            #[cfg(feature = "compression")]
+            Compression::Bzip2 => Ok(Encoder::Bzip2(BzEncoder::new(wtr, bzip2::Compression::default()))),
+            #[cfg(not(feature = "compression"))]
+            _ => Err(format_err!("{:?} needs the compression feature", self)),
+        }
+    }
+}
+
+// Not all encoders finish their stream on drop, and those that do
+// ignore errors, so `finish` has to be called explicitly.
+pub(crate) enum Encoder<W: Write> {
+    Plain(W),
+    #[cfg(feature = "compression")]
+    Gzip(GzEncoder<W>),
+    #[cfg(feature = "compression")]
+    Zstd(zstd::Encoder<'static, W>),
+    #[cfg(feature = "compression")]
+    Bzip2(BzEncoder<W>),
+}
+
+impl<W: Write> Encoder<W> {
+    /// Writes the end of the compressed stream and flushes.
+    #[cfg_attr(not(feature = "compression"), allow(clippy::infallible_destructuring_match))]
+    pub(crate) fn finish(self) -> io::Result<W> {
+        let mut wtr = match self {
+            Encoder::Plain(wtr) => wtr,
+            #[cfg(feature = "compression")]
+            Encoder::Gzip(encoder) => encoder.finish()?,
+            #[cfg(feature = "compression")]
+            Encoder::Zstd(encoder) => encoder.finish()?,
+            #[cfg(feature = "compression")]
+            Encoder::Bzip2(encoder) => encoder.finish()?,
+        };
+        wtr.flush()?;
+        Ok(wtr)
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match *self {
+            Encoder::Plain(ref mut wtr) => wtr.write(buf),
+            #[cfg(feature = "compression")]
+            Encoder::Gzip(ref mut encoder) => encoder.write(buf),
+            #[cfg(feature = "compression")]
+            Encoder::Zstd(ref mut encoder) => encoder.write(buf),
+            #[cfg(feature = "compression")]
+            Encoder::Bzip2(ref mut encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match *self {
+            Encoder::Plain(ref mut wtr) => wtr.flush(),
+            #[cfg(feature = "compression")]
+            Encoder::Gzip(ref mut encoder) => encoder.flush(),
+            #[cfg(feature = "compression")]
+            Encoder::Zstd(ref mut encoder) => encoder.flush(),
+            #[cfg(feature = "compression")]
+            Encoder::Bzip2(ref mut encoder) => encoder.flush(),
+        }
+    }
+}
@@ -0,0 +1,1201 @@
+use csv::{ByteRecord, ReaderBuilder, WriterBuilder};
+use failure::Error;
+use rayon::prelude::*;
+use rayon::{current_num_threads, ThreadPoolBuilder};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, Cursor, Read, Write};
+use std::path::Path;
+use std::str;
+
+use dataframe::{Array, ArrayData, DataFrame};
+use io::compression::{Compression, Encoder};
+use io::encoding::{EncodeWriter, Encoding, EncodingErrors};
+use io::{array_to_strings, infer_array, parse_as, parses_as};
+
+/// Options for reading a csv into a DataFrame.
+///
+/// By default the first row is a header, column dtypes are inferred
+/// (see `infer_dtypes`), compression is picked from the file extension
+/// (see `compression`), the input is UTF-8 (see `encoding`), parsing is
+/// single threaded (see `n_threads`) and a bad row is an error (see
+/// `bad_rows`).
+#[derive(Debug, Clone)]
+pub struct CsvReader {
+    has_headers: bool,
+    delimiter: u8,
+    infer_dtypes: bool,
+    compression: Option<Compression>,
+    encoding: Option<Encoding>,
+    encoding_errors: EncodingErrors,
+    n_threads: usize,
+    bad_rows: BadRows,
+}
+
+impl CsvReader {
+    pub fn new() -> Self {
+        CsvReader {
+            has_headers: true,
+            delimiter: b',',
+            infer_dtypes: true,
+            compression: None,
+            encoding: None,
+            encoding_errors: EncodingErrors::Error,
+            n_threads: 1,
+            bad_rows: BadRows::Fail,
+        }
+    }
+
+    /// Without headers, columns are named by position: "0", "1", ...
+    pub fn has_headers(mut self, has_headers: bool) -> Self {
+        self.has_headers = has_headers;
+        self
+    }
+
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// When true, each column becomes the narrowest of Int64, Float64,
+    /// Date, Datetime or Str that all its values parse as. When false,
+    /// every column is Str.
+    pub fn infer_dtypes(mut self, infer_dtypes: bool) -> Self {
+        self.infer_dtypes = infer_dtypes;
+        self
+    }
+
+    /// Decompress the input with this codec. Unset, `read_path` goes by
+    /// the file extension (see `Compression::from_path`) and `read`
+    /// reads it as is. The input is decompressed as it's parsed.
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Decode the input from this encoding, after any decompression. A
+    /// byte order mark picks the encoding instead, and is dropped.
+    ///
+    /// Unset, the input is UTF-8 (with or without a byte order mark),
+    /// and a row with invalid UTF-8 is a bad row (see `bad_rows`).
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    /// What to do with bytes that aren't valid in the `encoding`, if set.
+    /// By default they're an error.
+    pub fn encoding_errors(mut self, encoding_errors: EncodingErrors) -> Self {
+        self.encoding_errors = encoding_errors;
+        self
+    }
+
+    /// Parse on this many threads, or one per core for 0.
+    ///
+    /// With more than one thread, `read` and `read_path` read all of the
+    /// (decompressed) input into memory, split it into pieces of whole
+    /// records, split fields in the pieces in parallel, then infer the
+    /// columns' dtypes in parallel. The DataFrame is the same as with
+    /// one thread. Batched reads are always single threaded.
+    pub fn n_threads(mut self, n_threads: usize) -> Self {
+        self.n_threads = n_threads;
+        self
+    }
+
+    /// What to do with bad rows. Use `read_with_report` to get the
+    /// count of rows rejected, and under `BadRows::Quarantine` the rows.
+    pub fn bad_rows(mut self, bad_rows: BadRows) -> Self {
+        self.bad_rows = bad_rows;
+        self
+    }
+
+    pub fn read_path<P: AsRef<Path>>(&self, path: P) -> Result<DataFrame, Error> {
+        self.read_path_with_report(path).map(|(df, _)| df)
+    }
+
+    /// Errors on rows with a different number of fields than the header,
+    /// or invalid UTF-8, unless `bad_rows` says otherwise.
+    pub fn read<R: Read>(&self, rdr: R) -> Result<DataFrame, Error> {
+        self.read_with_report(rdr).map(|(df, _)| df)
+    }
+
+    /// Like `read_path`, also counting the rows read and rejected.
+    pub fn read_path_with_report<P: AsRef<Path>>(&self, path: P) -> Result<(DataFrame, CsvReport), Error> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .map_err(|err| format_err!("couldn't open {}: {}", path.display(), err))?;
+        let compression = self.compression.unwrap_or_else(|| Compression::from_path(path));
+        self.read_decoded(self.decoder(file, compression)?)
+    }
+
+    /// Like `read`, also counting the rows read and rejected.
+    pub fn read_with_report<R: Read>(&self, rdr: R) -> Result<(DataFrame, CsvReport), Error> {
+        self.read_decoded(self.decoder(rdr, self.compression.unwrap_or(Compression::None))?)
+    }
+
+    fn decoder<'a, R: Read + 'a>(&self, rdr: R, compression: Compression) -> Result<Box<dyn Read + 'a>, Error> {
+        let rdr = compression.decoder(rdr)?;
+        match self.encoding {
+            Some(encoding) => encoding.decoder(rdr, self.encoding_errors),
+            None => Ok(rdr),
+        }
+    }
+
+    fn read_decoded<'a>(&self, mut rdr: Box<dyn Read + 'a>) -> Result<(DataFrame, CsvReport), Error> {
+        if self.n_threads == 1 {
+            return self.batches(rdr, usize::MAX)?.read_all();
+        }
+        let mut bytes = vec![];
+        rdr.read_to_end(&mut bytes)?;
+        let pool = ThreadPoolBuilder::new().num_threads(self.n_threads).build()?;
+        pool.install(|| self.read_parallel(&bytes))
+    }
+
+    fn read_parallel(&self, bytes: &[u8]) -> Result<(DataFrame, CsvReport), Error> {
+        let mut rejects = Rejects::new(self.bad_rows, self.delimiter);
+        let mut rdr = self.piece_reader(bytes);
+        let mut first = ByteRecord::new();
+        if !rdr.read_byte_record(&mut first)? {
+            return Ok((DataFrame::new(), rejects.report(0)));
+        }
+        // without headers, the first record sets the width
+        let (names, body, header_lines) = if self.has_headers {
+            let names = first.iter()
+                .map(|h| String::from_utf8(h.to_vec()).map_err(|err| format_err!("header: {}", err)))
+                .collect::<Result<Vec<_>, Error>>()?;
+            (names, &bytes[rdr.position().byte() as usize..], rdr.position().line() - 1)
+        } else {
+            ((0..first.len()).map(|i| i.to_string()).collect(), bytes, 0)
+        };
+
+        // a few pieces per thread, so that uneven pieces even out
+        let splits = split_records(body, self.delimiter, current_num_threads() * 4);
+        let pieces = splits.par_windows(2)
+            .map(|w| self.split_fields(&body[w[0].0..w[1].0], header_lines + w[0].1, names.len()))
+            .collect::<Vec<_>>();
+
+        let mut cols: Vec<Vec<String>> = names.iter().map(|_| vec![]).collect();
+        for piece in pieces {
+            let (piece_cols, piece_rejects) = piece?;
+            for (col, values) in cols.iter_mut().zip(piece_cols) {
+                col.extend(values);
+            }
+            rejects.extend(piece_rejects)?;
+        }
+        let n_rows = cols[0].len();
+        let arrays = cols.into_par_iter()
+            .map(|values| if self.infer_dtypes {
+                infer_array(values)
+            } else {
+                Array::Str(ArrayData::from_vec(values))
+            })
+            .collect::<Vec<_>>();
+
+        let mut df = DataFrame::new();
+        for (name, array) in names.into_iter().zip(arrays) {
+            df.add_col(name, array);
+        }
+        Ok((df, rejects.report(n_rows)))
+    }
+
+    fn piece_reader<'b>(&self, piece: &'b [u8]) -> csv::Reader<Cursor<&'b [u8]>> {
+        ReaderBuilder::new()
+            .has_headers(false)
+            .delimiter(self.delimiter)
+            .flexible(true)
+            .from_reader(Cursor::new(piece))
+    }
+
+    // Columns of the records in `piece`, which starts after `line_offset`
+    // lines of the csv, and the rows rejected from it. Under
+    // `BadRows::Fail`, rejecting a row is left to the caller so that the
+    // first bad row of the csv is the one reported.
+    fn split_fields(&self, piece: &[u8], line_offset: u64, width: usize) -> Result<(Vec<Vec<String>>, Rejects), Error> {
+        let policy = if self.bad_rows == BadRows::Fail { BadRows::Quarantine } else { self.bad_rows };
+        let mut rejects = Rejects::new(policy, self.delimiter);
+        let mut rdr = self.piece_reader(piece);
+        let mut cols: Vec<Vec<String>> = (0..width).map(|_| vec![]).collect();
+        let mut record = ByteRecord::new();
+        while read_row(&mut rdr, &mut record, line_offset, &mut cols, &mut rejects)?.is_some() {
+            // under Fail, the rest of the piece is moot
+            if self.bad_rows == BadRows::Fail && rejects.n_rejected > 0 {
+                break;
+            }
+        }
+        Ok((cols, rejects))
+    }
+
+    /// Like `read_path`, but yields DataFrames of up to `batch_rows` rows
+    /// as the file is read, so only one batch is in memory at a time.
+    pub fn read_path_batched<P: AsRef<Path>>(&self, path: P, batch_rows: usize) -> Result<CsvBatches<'static>, Error> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .map_err(|err| format_err!("couldn't open {}: {}", path.display(), err))?;
+        let compression = self.compression.unwrap_or_else(|| Compression::from_path(path));
+        self.batches(self.decoder(file, compression)?, batch_rows)
+    }
+
+    /// Like `read`, but yields DataFrames of up to `batch_rows` rows as
+    /// the input is read.
+    ///
+    /// Every batch has the same columns and dtypes. Dtypes are inferred
+    /// from the first batch only, and a later value that doesn't parse
+    /// as its column's dtype makes a bad row (see `bad_rows`), so make
+    /// the first batch large enough to be representative (or turn off
+    /// `infer_dtypes`).
+    pub fn read_batched<'a, R: Read + 'a>(&self, rdr: R, batch_rows: usize) -> Result<CsvBatches<'a>, Error> {
+        self.batches(self.decoder(rdr, self.compression.unwrap_or(Compression::None))?, batch_rows)
+    }
+
+    fn batches<'a>(&self, rdr: Box<dyn Read + 'a>, batch_rows: usize) -> Result<CsvBatches<'a>, Error> {
+        if batch_rows == 0 {
+            return Err(format_err!("batch rows must be positive"));
+        }
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(self.has_headers)
+            .delimiter(self.delimiter)
+            .flexible(true)
+            .from_reader(CrlfIndex {
+                rdr,
+                offset: 0,
+                last: 0,
+                lfs: VecDeque::new(),
+            });
+        let names = if self.has_headers {
+            rdr.headers()?.iter().map(|h| h.to_owned()).collect()
+        } else {
+            vec![]
+        };
+
+        Ok(CsvBatches {
+            rdr,
+            names,
+            schema: None,
+            batch_rows,
+            infer_dtypes: self.infer_dtypes,
+            rejects: Rejects::new(self.bad_rows, self.delimiter),
+            n_rows: 0,
+            done: false,
+        })
+    }
+}
+
+impl Default for CsvReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What `CsvReader` does with a bad row: one with a different number of
+/// fields than the header, invalid UTF-8, or in a batched read, a value
+/// that doesn't parse as its column's dtype.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BadRows {
+    /// Stop reading, with an error naming the row's line
+    Fail,
+    /// Leave the row out, counting it in `CsvReport::n_rejected`
+    Skip,
+    /// Leave the row out, keeping it in `CsvReport::rejected`
+    Quarantine,
+}
+
+/// Counts of the rows of a csv read and rejected, and the rejected rows
+/// themselves under `BadRows::Quarantine`.
+#[derive(Debug, Clone)]
+pub struct CsvReport {
+    n_rows: usize,
+    n_rejected: usize,
+    rejected: Vec<(u64, String, String)>,
+}
+
+impl CsvReport {
+    /// Rows read into the DataFrame, or so far into batches.
+    pub fn n_rows(&self) -> usize {
+        self.n_rows
+    }
+
+    /// Bad rows left out.
+    pub fn n_rejected(&self) -> usize {
+        self.n_rejected
+    }
+
+    /// A row per quarantined row: the "line" of the csv it starts on
+    /// (from 1), the "reason" it was rejected, and the "record" itself as
+    /// a line of csv. Empty unless reading with `BadRows::Quarantine`.
+    /// Write it with `write_csv` to keep the rows in a file.
+    pub fn rejected(&self) -> DataFrame {
+        let mut df = DataFrame::new();
+        df.add_col("line".to_owned(), Array::UInt64(ArrayData::from_vec(
+            self.rejected.iter().map(|row| row.0).collect()
+        )));
+        df.add_col("reason".to_owned(), Array::Str(ArrayData::from_vec(
+            self.rejected.iter().map(|row| row.1.clone()).collect()
+        )));
+        df.add_col("record".to_owned(), Array::Str(ArrayData::from_vec(
+            self.rejected.iter().map(|row| row.2.clone()).collect()
+        )));
+        df
+    }
+}
+
+// Bad rows seen so far, and what to do with the next one.
+#[derive(Debug, Clone)]
+struct Rejects {
+    policy: BadRows,
+    delimiter: u8,
+    n_rejected: usize,
+    rows: Vec<(u64, String, String)>,
+}
+
+impl Rejects {
+    fn new(policy: BadRows, delimiter: u8) -> Self {
+        Rejects {
+            policy,
+            delimiter,
+            n_rejected: 0,
+            rows: vec![],
+        }
+    }
+
+    fn reject(&mut self, line: u64, reason: String, record: &ByteRecord) -> Result<(), Error> {
+        match self.policy {
+            BadRows::Fail => return Err(format_err!("line {}: {}", line, reason)),
+            BadRows::Skip => {},
+            BadRows::Quarantine => {
+                let text = record_text(record, self.delimiter)?;
+                self.rows.push((line, reason, text));
+            },
+        }
+        self.n_rejected += 1;
+        Ok(())
+    }
+
+    // Takes on the rows rejected from a later piece of the csv.
+    fn extend(&mut self, other: Rejects) -> Result<(), Error> {
+        if let (BadRows::Fail, Some(&(line, ref reason, _))) = (self.policy, other.rows.first()) {
+            return Err(format_err!("line {}: {}", line, reason));
+        }
+        self.n_rejected += other.n_rejected;
+        self.rows.extend(other.rows);
+        Ok(())
+    }
+
+    fn report(&self, n_rows: usize) -> CsvReport {
+        CsvReport {
+            n_rows,
+            n_rejected: self.n_rejected,
+            rejected: self.rows.clone(),
+        }
+    }
+}
+
+// A record as a line of csv, for the quarantine.
+fn record_text(record: &ByteRecord, delimiter: u8) -> Result<String, Error> {
+    let mut wtr = WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(vec![]);
+    wtr.write_byte_record(record)?;
+    let bytes = wtr.into_inner().map_err(|err| err.into_error())?;
+    Ok(String::from_utf8_lossy(&bytes).trim_end_matches('\n').to_owned())
+}
+
+// Input that can tell whether it has a '\n' at a byte offset already
+// read. The csv crate ends a record at the '\r' of a "\r\n" and counts
+// the '\n' towards the next record, so a record starting on one is on
+// the line after the one the csv crate gives it.
+trait LfAt {
+    fn lf_at(&mut self, offset: u64) -> bool;
+}
+
+impl LfAt for Cursor<&[u8]> {
+    fn lf_at(&mut self, offset: u64) -> bool {
+        self.get_ref().get(offset as usize) == Some(&b'\n')
+    }
+}
+
+// Passes input through, noting where each "\r\n" has its '\n'. Offsets
+// are asked for in order, so only those the csv crate has buffered but
+// not yet parsed are kept.
+struct CrlfIndex<R> {
+    rdr: R,
+    offset: u64,
+    last: u8,
+    lfs: VecDeque<u64>,
+}
+
+impl<R: Read> Read for CrlfIndex<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.rdr.read(buf)?;
+        for &b in &buf[..n] {
+            if b == b'\n' && self.last == b'\r' {
+                self.lfs.push_back(self.offset);
+            }
+            self.last = b;
+            self.offset += 1;
+        }
+        Ok(n)
+    }
+}
+
+impl<R> LfAt for CrlfIndex<R> {
+    fn lf_at(&mut self, offset: u64) -> bool {
+        while self.lfs.front().is_some_and(|&lf| lf < offset) {
+            self.lfs.pop_front();
+        }
+        self.lfs.front() == Some(&offset)
+    }
+}
+
+// Reads the next good record of `rdr` onto `cols`, rejecting bad ones,
+// and returns the line it starts on (offset by `line_offset`), or None
+// at the end. Without headers `cols` starts empty, and the first record
+// sets the width.
+fn read_row<R: Read + LfAt>(
+    rdr: &mut csv::Reader<R>,
+    record: &mut ByteRecord,
+    line_offset: u64,
+    cols: &mut Vec<Vec<String>>,
+    rejects: &mut Rejects,
+) -> Result<Option<u64>, Error> {
+    while rdr.read_byte_record(record)? {
+        let line = match record.position() {
+            Some(pos) => line_offset + pos.line() + rdr.get_mut().lf_at(pos.byte()) as u64,
+            None => 0,
+        };
+        if cols.is_empty() {
+            cols.resize(record.len(), vec![]);
+        }
+        if record.len() != cols.len() {
+            let reason = format!("found {} fields, expected {}", record.len(), cols.len());
+            rejects.reject(line, reason, record)?;
+        } else if let Some(i) = record.iter().position(|field| str::from_utf8(field).is_err()) {
+            rejects.reject(line, format!("field {} is invalid UTF-8", i), record)?;
+        } else {
+            for (col, field) in cols.iter_mut().zip(record.iter()) {
+                col.push(String::from_utf8_lossy(field).into_owned());
+            }
+            return Ok(Some(line));
+        }
+    }
+    Ok(None)
+}
+
+// Rejects the rows with a value that doesn't parse as its column's dtype
+// in `schema`, taking them out of `cols` and `lines`.
+fn reject_unparsed(
+    cols: &mut [Vec<String>],
+    lines: &mut Vec<u64>,
+    schema: &[Array],
+    names: &[String],
+    rejects: &mut Rejects,
+) -> Result<(), Error> {
+    let mut reasons: Vec<Option<String>> = vec![None; lines.len()];
+    for ((values, like), name) in cols.iter().zip(schema).zip(names) {
+        for (reason, value) in reasons.iter_mut().zip(values) {
+            if reason.is_none() && !parses_as(value, like) {
+                *reason = Some(format!("column {}: can't parse {:?} as {}", name, value, like.dtype()));
+            }
+        }
+    }
+    if reasons.iter().all(Option::is_none) {
+        return Ok(());
+    }
+
+    for (i, reason) in reasons.iter().enumerate() {
+        if let Some(ref reason) = *reason {
+            let record = ByteRecord::from(cols.iter().map(|col| col[i].as_str()).collect::<Vec<_>>());
+            rejects.reject(lines[i], reason.clone(), &record)?;
+        }
+    }
+    for col in cols.iter_mut() {
+        let mut keep = reasons.iter().map(Option::is_none);
+        col.retain(|_| keep.next() == Some(true));
+    }
+    let mut keep = reasons.iter().map(Option::is_none);
+    lines.retain(|_| keep.next() == Some(true));
+    Ok(())
+}
+
+#[derive(Clone, Copy)]
+enum SplitState {
+    RecordStart,
+    FieldStart,
+    Unquoted,
+    Quoted,
+    QuoteInQuoted,
+}
+
+// Offsets that split `body` into about `n_pieces` runs of whole records,
+// each with the number of lines before it, ending with the length of
+// `body`. Follows the csv crate's quoting, so a line break in a quoted
+// field doesn't end a record, and skips empty lines like it does.
+fn split_records(body: &[u8], delimiter: u8, n_pieces: usize) -> Vec<(usize, u64)> {
+    let target = body.len() / n_pieces.max(1) + 1;
+    let mut splits = vec![(0, 0)];
+    let mut state = SplitState::RecordStart;
+    let mut n_lines = 0;
+    for (i, &b) in body.iter().enumerate() {
+        if b == b'\n' {
+            n_lines += 1;
+        }
+        state = match (state, b) {
+            (SplitState::Quoted, b'"') => SplitState::QuoteInQuoted,
+            (SplitState::Quoted, _) => SplitState::Quoted,
+            (SplitState::QuoteInQuoted, b'"') => SplitState::Quoted,
+            (SplitState::RecordStart, b'\r') | (SplitState::RecordStart, b'\n') => SplitState::RecordStart,
+            (SplitState::RecordStart, b'"') | (SplitState::FieldStart, b'"') => SplitState::Quoted,
+            (_, b'\r') | (_, b'\n') => {
+                if i + 1 - splits[splits.len() - 1].0 >= target {
+                    splits.push((i + 1, n_lines));
+                }
+                SplitState::RecordStart
+            },
+            (_, b) if b == delimiter => SplitState::FieldStart,
+            _ => SplitState::Unquoted,
+        };
+    }
+    if splits[splits.len() - 1].0 < body.len() {
+        splits.push((body.len(), n_lines));
+    }
+    splits
+}
+
+/// Iterator over DataFrames of up to `batch_rows` rows of a csv, from
+/// `CsvReader::read_batched`. Stops after the first error.
+pub struct CsvBatches<'a> {
+    rdr: csv::Reader<CrlfIndex<Box<dyn Read + 'a>>>,
+    names: Vec<String>,
+    // empty columns of the first batch, which later batches parse as
+    schema: Option<Vec<Array>>,
+    batch_rows: usize,
+    infer_dtypes: bool,
+    rejects: Rejects,
+    n_rows: usize,
+    done: bool,
+}
+
+impl<'a> CsvBatches<'a> {
+    /// Column names. Without headers, empty until the first batch.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Rows read and rejected so far.
+    pub fn report(&self) -> CsvReport {
+        self.rejects.report(self.n_rows)
+    }
+
+    fn next_batch(&mut self) -> Result<Option<DataFrame>, Error> {
+        let mut cols: Vec<Vec<String>> = self.names.iter().map(|_| vec![]).collect();
+        let mut lines = vec![];
+        let mut record = ByteRecord::new();
+        // batches short of rejected rows are fine, but empty ones would
+        // end the iteration
+        loop {
+            while lines.len() < self.batch_rows {
+                match read_row(&mut self.rdr, &mut record, 0, &mut cols, &mut self.rejects)? {
+                    Some(line) => lines.push(line),
+                    None => break,
+                }
+            }
+            if lines.is_empty() {
+                return Ok(None);
+            }
+            if let Some(ref schema) = self.schema {
+                reject_unparsed(&mut cols, &mut lines, schema, &self.names, &mut self.rejects)?;
+            }
+            if !lines.is_empty() {
+                break;
+            }
+        }
+        if self.names.is_empty() {
+            self.names = (0..cols.len()).map(|i| i.to_string()).collect();
+        }
+
+        let arrays = match self.schema {
+            Some(ref schema) => {
+                cols.into_iter().zip(schema).zip(&self.names)
+                    .map(|((values, like), name)| {
+                        parse_as(values, like).map_err(|err| format_err!("column {}: {}", name, err))
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?
+            },
+            None => {
+                let arrays: Vec<Array> = cols.into_iter()
+                    .map(|values| if self.infer_dtypes {
+                        infer_array(values)
+                    } else {
+                        Array::Str(ArrayData::from_vec(values))
+                    })
+                    .collect();
+                self.schema = Some(arrays.iter().map(|array| array.slice(0, 0)).collect::<Result<_, _>>()?);
+                arrays
+            },
+        };
+        self.n_rows += lines.len();
+
+        let mut df = DataFrame::new();
+        for (name, array) in self.names.iter().zip(arrays) {
+            df.add_col(name.clone(), array);
+        }
+        Ok(Some(df))
+    }
+
+    // The one batch of an unbatched read, with Str columns if empty.
+    fn read_all(mut self) -> Result<(DataFrame, CsvReport), Error> {
+        if let Some(df) = self.next_batch()? {
+            return Ok((df, self.report()));
+        }
+        let mut df = DataFrame::new();
+        for name in &self.names {
+            df.add_col(name.clone(), Array::Str(ArrayData::from_vec(vec![])));
+        }
+        Ok((df, self.report()))
+    }
+}
+
+impl<'a> Iterator for CsvBatches<'a> {
+    type Item = Result<DataFrame, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.next_batch() {
+            Ok(Some(df)) => Some(Ok(df)),
+            Ok(None) => {
+                self.done = true;
+                None
+            },
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            },
+        }
+    }
+}
+
+/// Options for writing a DataFrame to csv. By default compression is
+/// picked from the file extension (see `compression`) and the output is
+/// UTF-8 without a byte order mark (see `encoding`).
+#[derive(Debug, Clone)]
+pub struct CsvWriter {
+    has_headers: bool,
+    delimiter: u8,
+    compression: Option<Compression>,
+    encoding: Encoding,
+    encoding_errors: EncodingErrors,
+    bom: bool,
+}
+
+impl CsvWriter {
+    pub fn new() -> Self {
+        CsvWriter {
+            has_headers: true,
+            delimiter: b',',
+            compression: None,
+            encoding: Encoding::Utf8,
+            encoding_errors: EncodingErrors::Error,
+            bom: false,
+        }
+    }
+
+    pub fn has_headers(mut self, has_headers: bool) -> Self {
+        self.has_headers = has_headers;
+        self
+    }
+
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Compress the output with this codec. Unset, `write_path` goes by
+    /// the file extension (see `Compression::from_path`) and `write`
+    /// writes plain csv.
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Encode the output in this encoding, before any compression.
+    /// `Encoding::Detect` is for reading only.
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// What to do with characters the `encoding` can't represent. By
+    /// default they're an error.
+    pub fn encoding_errors(mut self, encoding_errors: EncodingErrors) -> Self {
+        self.encoding_errors = encoding_errors;
+        self
+    }
+
+    /// When true, start with a byte order mark, which UTF-8 and UTF-16
+    /// have. Some spreadsheet programs need one to read UTF-8.
+    pub fn bom(mut self, bom: bool) -> Self {
+        self.bom = bom;
+        self
+    }
+
+    pub fn write_path<P: AsRef<Path>>(&self, df: &DataFrame, path: P) -> Result<(), Error> {
+        let mut wtr = self.write_path_batched(path)?;
+        wtr.write_batch(df)?;
+        wtr.finish()?;
+        Ok(())
+    }
+
+    /// Errors if the columns aren't all the same length.
+    pub fn write<W: Write>(&self, df: &DataFrame, wtr: W) -> Result<(), Error> {
+        let mut wtr = self.write_batched(wtr)?;
+        wtr.write_batch(df)?;
+        wtr.finish()?;
+        Ok(())
+    }
+
+    /// Like `write_path`, but returns a writer that DataFrames are
+    /// written to one batch at a time.
+    pub fn write_path_batched<P: AsRef<Path>>(&self, path: P) -> Result<CsvBatchWriter<File>, Error> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .map_err(|err| format_err!("couldn't create {}: {}", path.display(), err))?;
+        let compression = self.compression.unwrap_or_else(|| Compression::from_path(path));
+        self.batch_writer(compression, file)
+    }
+
+    /// Like `write`, but returns a writer that DataFrames are written to
+    /// one batch at a time, e.g. the batches of `read_batched` after a
+    /// `filter` or `apply`.
+    pub fn write_batched<W: Write>(&self, wtr: W) -> Result<CsvBatchWriter<W>, Error> {
+        self.batch_writer(self.compression.unwrap_or(Compression::None), wtr)
+    }
+
+    fn batch_writer<W: Write>(&self, compression: Compression, wtr: W) -> Result<CsvBatchWriter<W>, Error> {
+        let wtr = self.encoding.encoder(compression.encoder(wtr)?, self.encoding_errors, self.bom)?;
+        let wtr = WriterBuilder::new()
+            .delimiter(self.delimiter)
+            .from_writer(wtr);
+        Ok(CsvBatchWriter {
+            wtr,
+            has_headers: self.has_headers,
+            names: None,
+        })
+    }
+}
+
+/// Writes DataFrames to one csv as they come, from
+/// `CsvWriter::write_batched`. The header is written with the first
+/// batch. Call `finish` when done, which ends the compressed stream.
+pub struct CsvBatchWriter<W: Write> {
+    wtr: csv::Writer<EncodeWriter<Encoder<W>>>,
+    has_headers: bool,
+    names: Option<Vec<String>>,
+}
+
+impl<W: Write> CsvBatchWriter<W> {
+    /// Errors if the columns aren't all the same length, or don't have
+    /// the names of the first batch's.
+    pub fn write_batch(&mut self, df: &DataFrame) -> Result<(), Error> {
+        let len = df.columns.values().next().map(|col| col.len()).unwrap_or(0);
+        if let Some((name, col)) = df.columns.iter().find(|&(_, col)| col.len() != len) {
+            return Err(format_err!("column {} has len {}, expected {}", name, col.len(), len));
+        }
+        match self.names {
+            Some(ref names) => {
+                if !df.columns.keys().eq(names.iter()) {
+                    return Err(format_err!("batch has columns {:?}, expected {:?}",
+                        df.columns.keys().collect::<Vec<_>>(), names));
+                }
+            },
+            None => {
+                if self.has_headers {
+                    self.wtr.write_record(df.columns.keys())?;
+                }
+                self.names = Some(df.columns.keys().cloned().collect());
+            },
+        }
+
+        // nulls are written as empty fields
+        let cols: Vec<Vec<Option<String>>> = df.columns.values().map(array_to_strings).collect();
+        for i in 0..len {
+            self.wtr.write_record(cols.iter().map(|col| col[i].as_ref().map_or("", |s| s.as_str())))?;
+        }
+        Ok(())
+    }
+
+    /// Flushes, ends the compressed stream if any, and returns the
+    /// underlying writer.
+    pub fn finish(self) -> Result<W, Error> {
+        Ok(self.wtr.into_inner().map_err(|err| err.into_error())?.finish()?.finish()?)
+    }
+}
+
+impl Default for CsvWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DataFrame {
+    /// Reads a csv with headers, inferring column dtypes, decompressing
+    /// `.gz`, `.zst` and `.bz2` files. Use `CsvReader` for other options.
+    pub fn read_csv<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        CsvReader::new().read_path(path)
+    }
+
+    /// Reads a csv with headers in DataFrames of up to `batch_rows`
+    /// rows, which share the columns and dtypes inferred from the first.
+    /// Use `CsvReader::read_path_batched` for other options.
+    pub fn read_csv_batched<P: AsRef<Path>>(path: P, batch_rows: usize) -> Result<CsvBatches<'static>, Error> {
+        CsvReader::new().read_path_batched(path, batch_rows)
+    }
+
+    /// Writes a csv with headers, compressed if the path ends in `.gz`,
+    /// `.zst` or `.bz2`. Use `CsvWriter` for other options.
+    pub fn write_csv<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        CsvWriter::new().write_path(self, path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dataframe::DataType;
+    use value::{column_values, Value};
+
+    const CSV: &str = "\
+state,year,founded,population,updated
+NY,2016,1788-07-26,19.7,2018-03-15T10:00:00
+CA,2016,1850-09-09,39.2,2018-03-16T11:30:00
+";
+
+    #[test]
+    fn test_read_csv_infers_dtypes() {
+        let df = CsvReader::new().read(CSV.as_bytes()).unwrap();
+        let dtypes: Vec<_> = df.columns.values().map(|col| col.dtype()).collect();
+        assert_eq!(dtypes, vec!["Str", "Int64", "Date", "Float64", "Datetime"]);
+
+        let x: Option<Option<&String>> = df.get_col("state").unwrap().get(1).unwrap();
+        assert_eq!(x, Some(Some(&"CA".to_owned())));
+
+        let df = CsvReader::new().infer_dtypes(false).read(CSV.as_bytes()).unwrap();
+        assert!(df.columns.values().all(|col| col.dtype() == "Str"));
+    }
+
+    #[test]
+    fn test_read_csv_options() {
+        let df = CsvReader::new()
+            .has_headers(false)
+            .delimiter(b';')
+            .read("a;1\nb;2\n".as_bytes())
+            .unwrap();
+        assert_eq!(df.columns.keys().collect::<Vec<_>>(), vec!["0", "1"]);
+        assert_eq!(df.get_col("1").unwrap().dtype(), "Int64");
+    }
+
+    #[test]
+    fn test_write_csv_round_trip() {
+        let mut df = CsvReader::new().read(CSV.as_bytes()).unwrap();
+        let cost = Array::Str(ArrayData::from_vec(vec!["$1,234.50".to_owned(), "0.5".to_owned()]));
+        df.add_col("cost".to_owned(), cost.parse_decimal(8, 2).unwrap());
+
+        let mut out = vec![];
+        CsvWriter::new().write(&df, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out, "\
+state,year,founded,population,updated,cost
+NY,2016,1788-07-26,19.7,2018-03-15T10:00:00,1234.50
+CA,2016,1850-09-09,39.2,2018-03-16T11:30:00,0.50
+");
+
+        let mut out = vec![];
+        CsvWriter::new().has_headers(false).delimiter(b'|').write(&df.slice(1, 1).unwrap(), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "CA|2016|1850-09-09|39.2|2018-03-16T11:30:00|0.50\n");
+
+        df.add_col("short".to_owned(), Array::Int8(ArrayData::from_vec(vec![1])));
+        assert!(CsvWriter::new().write(&df, vec![]).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn test_csv_compression_round_trip() {
+        let df = CsvReader::new().read(CSV.as_bytes()).unwrap();
+        for &compression in &[Compression::None, Compression::Gzip, Compression::Zstd, Compression::Bzip2] {
+            let mut out = vec![];
+            CsvWriter::new().compression(compression).write(&df, &mut out).unwrap();
+            assert_eq!(out.starts_with(b"state"), compression == Compression::None);
+            let back = CsvReader::new().compression(compression).read(out.as_slice()).unwrap();
+            assert_eq!(back.n_rows(), 2);
+            assert_eq!(back.get(1, "updated").unwrap(), df.get(1, "updated").unwrap());
+        }
+        assert!(CsvReader::new().compression(Compression::Gzip).read(CSV.as_bytes()).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn test_csv_compression_from_extension() {
+        assert_eq!(Compression::from_path("a.csv.gz"), Compression::Gzip);
+        assert_eq!(Compression::from_path("a.csv.zst"), Compression::Zstd);
+        assert_eq!(Compression::from_path("a.csv.bz2"), Compression::Bzip2);
+        assert_eq!(Compression::from_path("a.csv"), Compression::None);
+
+        let dir = ::std::env::temp_dir().join(format!("llamas2-csv-{}", ::std::process::id()));
+        ::std::fs::create_dir_all(&dir).unwrap();
+        let df = CsvReader::new().read(CSV.as_bytes()).unwrap();
+        for name in &["a.csv.gz", "a.csv.zst", "a.csv.bz2"] {
+            let path = dir.join(name);
+            df.write_csv(&path).unwrap();
+            assert!(!::std::fs::read(&path).unwrap().starts_with(b"state"));
+            assert_eq!(DataFrame::read_csv(&path).unwrap().get(0, "state").unwrap(), df.get(0, "state").unwrap());
+        }
+        // an explicit codec wins over the extension
+        let path = dir.join("plain.gz");
+        CsvWriter::new().compression(Compression::None).write_path(&df, &path).unwrap();
+        assert!(CsvReader::new().compression(Compression::None).read_path(&path).is_ok());
+        assert!(DataFrame::read_csv(&path).is_err());
+        ::std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_csv_batches() {
+        let csv = "id,score,day\n1,0.5,2018-03-15\n2,,\n3,1.5,2018-03-17\n4,2.5,2018-03-18\n5,3.5,\n";
+        let batches = CsvReader::new().read_batched(csv.as_bytes(), 2).unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(batches.iter().map(|df| df.n_rows()).collect::<Vec<_>>(), vec![2, 2, 1]);
+        for df in &batches {
+            let dtypes: Vec<_> = df.columns.values().map(|col| col.dtype()).collect();
+            assert_eq!(dtypes, vec!["Int64", "Float64", "Date"]);
+        }
+        assert_eq!(batches[2].get(0, "id").unwrap(), Value::Int64(5));
+
+        // write them back one at a time, keeping a single header
+        let mut wtr = CsvWriter::new().write_batched(vec![]).unwrap();
+        for df in &batches {
+            wtr.write_batch(df).unwrap();
+        }
+        assert_eq!(String::from_utf8(wtr.finish().unwrap()).unwrap(), "\
+id,score,day
+1,0.5,2018-03-15
+2,,
+3,1.5,2018-03-17
+4,2.5,2018-03-18
+5,3.5,
+");
+
+        // without headers, names come from the first record
+        let mut batches = CsvReader::new().has_headers(false).read_batched("a,1\nb,2\n".as_bytes(), 1).unwrap();
+        assert!(batches.names().is_empty());
+        assert_eq!(batches.next().unwrap().unwrap().columns.keys().collect::<Vec<_>>(), vec!["0", "1"]);
+        assert_eq!(batches.names(), &["0".to_owned(), "1".to_owned()]);
+        assert_eq!(batches.count(), 1);
+
+        assert_eq!(CsvReader::new().read_batched("a,b\n".as_bytes(), 10).unwrap().count(), 0);
+        assert!(CsvReader::new().read_batched(csv.as_bytes(), 0).is_err());
+    }
+
+    #[test]
+    fn test_csv_batches_errors() {
+        // Int64 from the first batch, then a float
+        let mut batches = CsvReader::new().read_batched("x\n1\n2\n2.5\n3\n".as_bytes(), 2).unwrap();
+        assert!(batches.next().unwrap().is_ok());
+        let err = batches.next().unwrap().unwrap_err();
+        assert_eq!(err.to_string(), "line 4: column x: can't parse \"2.5\" as Int64");
+        assert!(batches.next().is_none());
+
+        let mut wtr = CsvWriter::new().write_batched(vec![]).unwrap();
+        let df = CsvReader::new().read(CSV.as_bytes()).unwrap();
+        wtr.write_batch(&df).unwrap();
+        let mut other = df.clone();
+        other.add_col("extra".to_owned(), Array::Int8(ArrayData::from_vec(vec![1, 2])));
+        assert!(wtr.write_batch(&other).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn test_csv_batches_compressed_path() {
+        let dir = ::std::env::temp_dir().join(format!("llamas2-csv-batches-{}", ::std::process::id()));
+        ::std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("big.csv.gz");
+        let mut wtr = CsvWriter::new().write_path_batched(&path).unwrap();
+        for i in 0..10 {
+            let mut df = DataFrame::new();
+            df.add_col("n".to_owned(), Array::Int64(ArrayData::from_vec((i * 100..(i + 1) * 100).collect())));
+            wtr.write_batch(&df).unwrap();
+        }
+        wtr.finish().unwrap();
+
+        let mut total = 0;
+        for df in DataFrame::read_csv_batched(&path, 64).unwrap() {
+            let df = df.unwrap();
+            assert!(df.n_rows() <= 64);
+            assert_eq!(df.get(0, "n").unwrap(), Value::Int64(total));
+            total += df.n_rows() as i64;
+        }
+        assert_eq!(total, 1000);
+        ::std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_split_records() {
+        let body = b"a,\"x\ny\"\nb,\"say \"\"hi\"\"\nc\"\r\n\nc,d\"e\nlast,row";
+        let splits = split_records(body, b',', body.len());
+        let starts: Vec<_> = splits.iter().map(|&(i, row)| (&body[i..], row)).collect();
+        assert_eq!(starts, vec![
+            (&body[..], 0),
+            (&b"b,\"say \"\"hi\"\"\nc\"\r\n\nc,d\"e\nlast,row"[..], 2),
+            (&b"\n\nc,d\"e\nlast,row"[..], 3),
+            (&b"last,row"[..], 6),
+            (&b""[..], 6),
+        ]);
+        assert_eq!(split_records(b"", b',', 4), vec![(0, 0)]);
+        assert_eq!(split_records(body, b',', 1), vec![(0, 0), (body.len(), 6)]);
+    }
+
+    #[test]
+    fn test_read_csv_parallel() {
+        let mut csv = "id,name,score,day\n".to_owned();
+        for i in 0..500 {
+            let name = match i % 4 {
+                0 => "plain".to_owned(),
+                1 => format!("\"two\nlines {}\"", i),
+                2 => "\"quoted \"\"comma\"\", here\"".to_owned(),
+                _ => String::new(),
+            };
+            let score = if i == 250 { "0.5".to_owned() } else { i.to_string() };
+            csv.push_str(&format!("{},{},{},2018-03-{:02}\r\n", i, name, score, i % 28 + 1));
+        }
+
+        let serial = CsvReader::new().read(csv.as_bytes()).unwrap();
+        for &n_threads in &[0, 2, 3] {
+            let parallel = CsvReader::new().n_threads(n_threads).read(csv.as_bytes()).unwrap();
+            assert_eq!(parallel.columns.keys().collect::<Vec<_>>(), serial.columns.keys().collect::<Vec<_>>());
+            for name in serial.columns.keys() {
+                assert_eq!(parallel.get_col(name).unwrap().dtype(), serial.get_col(name).unwrap().dtype());
+                for i in 0..500 {
+                    assert_eq!(parallel.get(i, name).unwrap(), serial.get(i, name).unwrap());
+                }
+            }
+        }
+        assert_eq!(serial.get_col("score").unwrap().dtype(), "Float64");
+        assert_eq!(serial.get(1, "name").unwrap(), Value::Str("two\nlines 1".to_owned()));
+
+        let df = CsvReader::new().has_headers(false).infer_dtypes(false).n_threads(2)
+            .read("1,a\n2,b\n".as_bytes())
+            .unwrap();
+        assert_eq!(df.columns.keys().collect::<Vec<_>>(), vec!["0", "1"]);
+        assert_eq!(df.get(1, "0").unwrap(), Value::Str("2".to_owned()));
+        assert_eq!(CsvReader::new().n_threads(2).read("a,b\n".as_bytes()).unwrap().n_rows(), 0);
+
+        let ragged = csv.replace("\n300,", "\n300,extra,");
+        let err = CsvReader::new().read(ragged.as_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), "line 377: found 5 fields, expected 4");
+        let err = CsvReader::new().n_threads(4).read(ragged.as_bytes()).unwrap_err();
+        assert_eq!(err.to_string(), "line 377: found 5 fields, expected 4");
+    }
+
+    #[test]
+    fn test_read_csv_bad_rows() {
+        let mut csv = b"id,name\r\n1,a\r\n2\r\n3,\"two\r\nlines\"\r\n4,b,extra\r\n5,".to_vec();
+        csv.extend_from_slice(&[0xff, b'\r', b'\n']);
+        csv.extend_from_slice(b"6,c\r\n");
+
+        let err = CsvReader::new().read(csv.as_slice()).unwrap_err();
+        assert_eq!(err.to_string(), "line 3: found 1 fields, expected 2");
+
+        for &n_threads in &[1, 2] {
+            let (df, report) = CsvReader::new().bad_rows(BadRows::Skip).n_threads(n_threads)
+                .read_with_report(csv.as_slice())
+                .unwrap();
+            assert_eq!(column_values(&df, "id"), vec![Value::Int64(1), Value::Int64(3), Value::Int64(6)]);
+            assert_eq!((report.n_rows(), report.n_rejected()), (3, 3));
+            assert_eq!(report.rejected().n_rows(), 0);
+
+            let err = CsvReader::new().n_threads(n_threads).read(csv.as_slice()).unwrap_err();
+            assert_eq!(err.to_string(), "line 3: found 1 fields, expected 2");
+
+            let (_, report) = CsvReader::new().bad_rows(BadRows::Quarantine).n_threads(n_threads)
+                .read_with_report(csv.as_slice())
+                .unwrap();
+            let rejected = report.rejected();
+            assert_eq!(column_values(&rejected, "line"), vec![Value::UInt64(3), Value::UInt64(6), Value::UInt64(7)]);
+            assert_eq!(column_values(&rejected, "reason"), vec![
+                Value::Str("found 1 fields, expected 2".to_owned()),
+                Value::Str("found 3 fields, expected 2".to_owned()),
+                Value::Str("field 1 is invalid UTF-8".to_owned()),
+            ]);
+            assert_eq!(column_values(&rejected, "record"), vec![
+                Value::Str("2".to_owned()),
+                Value::Str("4,b,extra".to_owned()),
+                Value::Str("5,\u{fffd}".to_owned()),
+            ]);
+        }
+    }
+
+    #[test]
+    fn test_csv_batches_bad_rows() {
+        let csv = "x,y\n1,a\n2,b\nnope,c\n3,d\nbad,e\nworse,f\n4,g\n";
+        let mut batches = CsvReader::new().bad_rows(BadRows::Quarantine).read_batched(csv.as_bytes(), 2).unwrap();
+        let xs: Vec<Vec<Value>> = batches.by_ref().map(|df| column_values(&df.unwrap(), "x")).collect();
+        assert_eq!(xs, vec![
+            vec![Value::Int64(1), Value::Int64(2)],
+            vec![Value::Int64(3)],
+            vec![Value::Int64(4)],
+        ]);
+        let report = batches.report();
+        assert_eq!((report.n_rows(), report.n_rejected()), (4, 3));
+        let rejected = report.rejected();
+        assert_eq!(column_values(&rejected, "line"), vec![Value::UInt64(4), Value::UInt64(6), Value::UInt64(7)]);
+        assert_eq!(rejected.get(0, "reason").unwrap(), Value::Str("column x: can't parse \"nope\" as Int64".to_owned()));
+        assert_eq!(rejected.get(2, "record").unwrap(), Value::Str("worse,f".to_owned()));
+
+        let mut out = vec![];
+        CsvWriter::new().write(&rejected, &mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().starts_with("line,reason,record\n4,"));
+    }
+
+    #[test]
+    fn test_csv_encodings() {
+        let mut df = DataFrame::new();
+        df.add_col("city".to_owned(), Array::Str(ArrayData::from_vec(vec!["Zürich".to_owned(), "São Paulo".to_owned()])));
+        df.add_col("price".to_owned(), Array::Str(ArrayData::from_vec(vec!["€5".to_owned(), "R$3".to_owned()])));
+
+        let mut out = vec![];
+        CsvWriter::new().encoding(Encoding::Windows1252).write(&df, &mut out).unwrap();
+        assert_eq!(out, b"city,price\nZ\xfcrich,\x805\nS\xe3o Paulo,R$3\n".to_vec());
+        for &encoding in &[Encoding::Windows1252, Encoding::Detect] {
+            let back = CsvReader::new().encoding(encoding).read(out.as_slice()).unwrap();
+            assert_eq!(column_values(&back, "price"), column_values(&df, "price"));
+        }
+        // as UTF-8, the bytes make bad rows
+        let (back, report) = CsvReader::new().bad_rows(BadRows::Skip).read_with_report(out.as_slice()).unwrap();
+        assert_eq!((back.n_rows(), report.n_rejected()), (0, 2));
+        let back = CsvReader::new().encoding(Encoding::Utf8).encoding_errors(EncodingErrors::Lossy)
+            .read(out.as_slice())
+            .unwrap();
+        assert_eq!(back.get(0, "city").unwrap(), Value::Str("Z\u{fffd}rich".to_owned()));
+        assert!(CsvReader::new().encoding(Encoding::Utf8).read(out.as_slice()).is_err());
+
+        let mut out = vec![];
+        CsvWriter::new().encoding(Encoding::Utf16Le).bom(true).write(&df, &mut out).unwrap();
+        assert!(out.starts_with(b"\xff\xfec\x00"));
+        for &n_threads in &[1, 2] {
+            let back = CsvReader::new().encoding(Encoding::Detect).n_threads(n_threads).read(out.as_slice()).unwrap();
+            assert_eq!(column_values(&back, "city"), column_values(&df, "city"));
+        }
+
+        assert!(CsvWriter::new().encoding(Encoding::Latin1).write(&df, vec![]).is_err());
+        let mut out = vec![];
+        CsvWriter::new().encoding(Encoding::Latin1).encoding_errors(EncodingErrors::Replace('E'))
+            .write(&df, &mut out)
+            .unwrap();
+        assert_eq!(out, b"city,price\nZ\xfcrich,E5\nS\xe3o Paulo,R$3\n".to_vec());
+    }
+
+    #[test]
+    fn test_read_csv_ragged_row_errors() {
+        assert!(CsvReader::new().read("a,b\n1,2\n3\n".as_bytes()).is_err());
+        assert!(DataFrame::read_csv("does/not/exist.csv").is_err());
+    }
+}
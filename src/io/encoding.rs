@@ -0,0 +1,403 @@
+//! Character encodings for text formats. Text is decoded to UTF-8 as
+//! it's read, and encoded from UTF-8 as it's written.
+
+use encoding_rs::{DecoderResult, EncoderResult, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+use failure::Error;
+use std::io::{self, Read, Write};
+use std::str;
+
+// enough to tell UTF-16 and UTF-8 from a single byte encoding
+const DETECT_BYTES: usize = 64 * 1024;
+
+/// Character encoding of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// ISO 8859-1: each byte is the code point of the same number
+    Latin1,
+    Windows1252,
+    /// For reading only: UTF-16 if about half the bytes are zero on one
+    /// side, else UTF-8 if the start of the input is valid UTF-8, else
+    /// Windows-1252.
+    Detect,
+}
+
+/// What to do with byte sequences that aren't valid in the encoding
+/// when reading, and with characters the encoding can't represent when
+/// writing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodingErrors {
+    /// Put this character in their place
+    Replace(char),
+    /// Stop with an error naming the byte offset or character
+    Error,
+    /// Put U+FFFD REPLACEMENT CHARACTER in their place when reading,
+    /// and '?' when writing
+    Lossy,
+}
+
+impl Encoding {
+    fn byte_order_mark(self) -> Option<&'static [u8]> {
+        match self {
+            Encoding::Utf8 => Some(b"\xef\xbb\xbf"),
+            Encoding::Utf16Le => Some(b"\xff\xfe"),
+            Encoding::Utf16Be => Some(b"\xfe\xff"),
+            _ => None,
+        }
+    }
+
+    /// Decodes `rdr` to UTF-8 as it's read. A byte order mark at the
+    /// start picks the encoding, whatever `self` is, and is dropped.
+    pub(crate) fn decoder<'a, R: Read + 'a>(self, rdr: R, errors: EncodingErrors) -> Result<Box<dyn Read + 'a>, Error> {
+        Ok(Box::new(DecodeReader::new(rdr, self, errors)?))
+    }
+
+    /// Encodes the UTF-8 written to it as `self`, starting with a byte
+    /// order mark if `bom`. Call `EncodeWriter::finish` when done.
+    pub(crate) fn encoder<W: Write>(self, mut wtr: W, errors: EncodingErrors, bom: bool) -> Result<EncodeWriter<W>, Error> {
+        if self == Encoding::Detect {
+            return Err(format_err!("Detect is only for reading"));
+        }
+        if bom {
+            let mark = self.byte_order_mark()
+                .ok_or_else(|| format_err!("{:?} has no byte order mark", self))?;
+            wtr.write_all(mark)?;
+        }
+        Ok(EncodeWriter {
+            wtr,
+            encoding: self,
+            errors,
+            pending: vec![],
+            buf: vec![],
+        })
+    }
+}
+
+fn detect(bytes: &[u8]) -> Encoding {
+    // ASCII as UTF-16 has a zero in every other byte
+    let half = bytes.len() / 2;
+    let zeros_at = |parity| bytes.iter().skip(parity).step_by(2).filter(|&&b| b == 0).count();
+    let (even, odd) = (zeros_at(0), zeros_at(1));
+    if half > 0 && odd * 2 > half && even * 8 < half {
+        return Encoding::Utf16Le;
+    }
+    if half > 0 && even * 2 > half && odd * 8 < half {
+        return Encoding::Utf16Be;
+    }
+    match str::from_utf8(bytes) {
+        Ok(_) => Encoding::Utf8,
+        // cut off mid character by the end of the sample
+        Err(err) if err.error_len().is_none() => Encoding::Utf8,
+        Err(_) => Encoding::Windows1252,
+    }
+}
+
+enum Decoding {
+    // encoding_rs has no true Latin-1 (its "latin1" is Windows-1252)
+    Latin1,
+    Other(::encoding_rs::Decoder),
+}
+
+struct DecodeReader<R> {
+    rdr: R,
+    decoding: Decoding,
+    encoding: Encoding,
+    errors: EncodingErrors,
+    input: Vec<u8>,
+    // input bytes decoded before `input`, for errors
+    offset: u64,
+    eof: bool,
+    output: String,
+    output_pos: usize,
+}
+
+impl<R: Read> DecodeReader<R> {
+    fn new(mut rdr: R, encoding: Encoding, errors: EncodingErrors) -> io::Result<Self> {
+        let mut input = vec![];
+        let sample = if encoding == Encoding::Detect { DETECT_BYTES } else { 3 };
+        let eof = fill(&mut rdr, &mut input, sample)?;
+
+        let (encoding, bom_len) = if input.starts_with(b"\xef\xbb\xbf") {
+            (Encoding::Utf8, 3)
+        } else if input.starts_with(b"\xff\xfe") {
+            (Encoding::Utf16Le, 2)
+        } else if input.starts_with(b"\xfe\xff") {
+            (Encoding::Utf16Be, 2)
+        } else if encoding == Encoding::Detect {
+            (detect(&input), 0)
+        } else {
+            (encoding, 0)
+        };
+        input.drain(..bom_len);
+
+        let decoding = match encoding {
+            Encoding::Latin1 => Decoding::Latin1,
+            Encoding::Utf16Le => Decoding::Other(UTF_16LE.new_decoder_without_bom_handling()),
+            Encoding::Utf16Be => Decoding::Other(UTF_16BE.new_decoder_without_bom_handling()),
+            Encoding::Windows1252 => Decoding::Other(WINDOWS_1252.new_decoder_without_bom_handling()),
+            _ => Decoding::Other(UTF_8.new_decoder_without_bom_handling()),
+        };
+        Ok(DecodeReader {
+            rdr,
+            decoding,
+            encoding,
+            errors,
+            input,
+            offset: bom_len as u64,
+            eof,
+            output: String::new(),
+            output_pos: 0,
+        })
+    }
+
+    // Decodes the next piece of input into `output`.
+    fn decode(&mut self) -> io::Result<()> {
+        if self.input.is_empty() && !self.eof {
+            self.eof = fill(&mut self.rdr, &mut self.input, 8 * 1024)?;
+        }
+        self.output.clear();
+        self.output_pos = 0;
+
+        let decoder = match self.decoding {
+            Decoding::Latin1 => {
+                self.output.extend(self.input.iter().map(|&b| b as char));
+                self.offset += self.input.len() as u64;
+                self.input.clear();
+                return Ok(());
+            },
+            Decoding::Other(ref mut decoder) => decoder,
+        };
+        let mut read = 0;
+        loop {
+            let needed = decoder.max_utf8_buffer_length_without_replacement(self.input.len() - read)
+                .unwrap_or(self.input.len() * 3 + 16);
+            self.output.reserve(needed);
+            let (result, n) = decoder.decode_to_string_without_replacement(&self.input[read..], &mut self.output, self.eof);
+            read += n;
+            match result {
+                DecoderResult::InputEmpty => break,
+                DecoderResult::OutputFull => continue,
+                DecoderResult::Malformed(len, extra) => {
+                    match self.errors {
+                        EncodingErrors::Replace(c) => self.output.push(c),
+                        EncodingErrors::Lossy => self.output.push('\u{fffd}'),
+                        EncodingErrors::Error => {
+                            let at = self.offset + (read - len as usize - extra as usize) as u64;
+                            return Err(io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                format!("byte {}: invalid {:?}", at, self.encoding),
+                            ));
+                        },
+                    }
+                },
+            }
+        }
+        self.offset += read as u64;
+        self.input.clear();
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for DecodeReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.output_pos == self.output.len() {
+            if self.input.is_empty() && self.eof {
+                return Ok(0);
+            }
+            self.decode()?;
+        }
+        let out = &self.output.as_bytes()[self.output_pos..];
+        let n = out.len().min(buf.len());
+        buf[..n].copy_from_slice(&out[..n]);
+        self.output_pos += n;
+        Ok(n)
+    }
+}
+
+// Reads up to `n` more bytes onto `buf`, and whether the input ended.
+fn fill<R: Read>(rdr: &mut R, buf: &mut Vec<u8>, n: usize) -> io::Result<bool> {
+    let want = buf.len() + n;
+    rdr.take(n as u64).read_to_end(buf)?;
+    Ok(buf.len() < want)
+}
+
+pub(crate) struct EncodeWriter<W> {
+    wtr: W,
+    encoding: Encoding,
+    errors: EncodingErrors,
+    // the start of a character split across writes
+    pending: Vec<u8>,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> EncodeWriter<W> {
+    /// Errors on a character left incomplete, and returns the
+    /// underlying writer.
+    pub(crate) fn finish(mut self) -> io::Result<W> {
+        if !self.pending.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "incomplete UTF-8 at the end"));
+        }
+        self.wtr.flush()?;
+        Ok(self.wtr)
+    }
+
+    fn encode(&mut self, s: &str) -> io::Result<()> {
+        self.buf.clear();
+        match self.encoding {
+            Encoding::Utf16Le => self.buf.extend(s.encode_utf16().flat_map(|u| u.to_le_bytes())),
+            Encoding::Utf16Be => self.buf.extend(s.encode_utf16().flat_map(|u| u.to_be_bytes())),
+            Encoding::Latin1 | Encoding::Windows1252 => {
+                for c in s.chars() {
+                    let b = match single_byte(c, self.encoding) {
+                        Some(b) => b,
+                        None => {
+                            let replacement = match self.errors {
+                                EncodingErrors::Replace(replacement) => replacement,
+                                EncodingErrors::Lossy => '?',
+                                EncodingErrors::Error => return Err(unmappable(c, self.encoding)),
+                            };
+                            single_byte(replacement, self.encoding)
+                                .ok_or_else(|| unmappable(replacement, self.encoding))?
+                        },
+                    };
+                    self.buf.push(b);
+                }
+            },
+            _ => self.buf.extend_from_slice(s.as_bytes()),
+        }
+        self.wtr.write_all(&self.buf)
+    }
+}
+
+// `c` in a single byte encoding, if it's there.
+fn single_byte(c: char, encoding: Encoding) -> Option<u8> {
+    if c.is_ascii() || (encoding == Encoding::Latin1 && (c as u32) <= 0xff) {
+        return Some(c as u32 as u8);
+    }
+    if encoding != Encoding::Windows1252 {
+        return None;
+    }
+    let mut utf8 = [0; 4];
+    let mut out = [0; 1];
+    let (result, _, written) = WINDOWS_1252.new_encoder()
+        .encode_from_utf8_without_replacement(c.encode_utf8(&mut utf8), &mut out, true);
+    match result {
+        EncoderResult::InputEmpty if written == 1 => Some(out[0]),
+        _ => None,
+    }
+}
+
+fn unmappable(c: char, encoding: Encoding) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("can't encode {:?} as {:?}", c, encoding))
+}
+
+impl<W: Write> Write for EncodeWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        let valid = match str::from_utf8(&self.pending) {
+            Ok(s) => s.len(),
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            Err(_) => return Err(io::Error::new(io::ErrorKind::InvalidData, "writing invalid UTF-8")),
+        };
+        let pending = self.pending.split_off(valid);
+        let text = ::std::mem::replace(&mut self.pending, pending);
+        self.encode(str::from_utf8(&text).expect("checked above"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.wtr.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // hands out a byte at a time, to split characters across reads
+    struct Trickle<'a>(&'a [u8]);
+
+    impl<'a> Read for Trickle<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.split_first() {
+                Some((&b, rest)) if !buf.is_empty() => {
+                    buf[0] = b;
+                    self.0 = rest;
+                    Ok(1)
+                },
+                _ => Ok(0),
+            }
+        }
+    }
+
+    fn decode(bytes: &[u8], encoding: Encoding, errors: EncodingErrors) -> Result<String, Error> {
+        let mut out = String::new();
+        encoding.decoder(Trickle(bytes), errors)?.read_to_string(&mut out)?;
+        Ok(out)
+    }
+
+    fn encode(s: &str, encoding: Encoding, errors: EncodingErrors, bom: bool) -> Result<Vec<u8>, Error> {
+        let mut wtr = encoding.encoder(vec![], errors, bom)?;
+        // a byte at a time, to split characters across writes
+        for b in s.as_bytes() {
+            wtr.write_all(&[*b])?;
+        }
+        Ok(wtr.finish()?)
+    }
+
+    #[test]
+    fn test_decode() {
+        let strict = EncodingErrors::Error;
+        assert_eq!(decode(b"caf\xc3\xa9 \xe2\x82\xac", Encoding::Utf8, strict).unwrap(), "café €");
+        assert_eq!(decode(b"caf\xe9 \x80", Encoding::Windows1252, strict).unwrap(), "café €");
+        assert_eq!(decode(b"caf\xe9 \x80", Encoding::Latin1, strict).unwrap(), "café \u{80}");
+        assert_eq!(decode(b"c\x00\xe9\x00\xac\x20", Encoding::Utf16Le, strict).unwrap(), "cé€");
+        assert_eq!(decode(b"\x00c\x00\xe9\x20\xac", Encoding::Utf16Be, strict).unwrap(), "cé€");
+
+        // a byte order mark wins, and is dropped
+        assert_eq!(decode(b"\xff\xfec\x00", Encoding::Windows1252, strict).unwrap(), "c");
+        assert_eq!(decode(b"\xef\xbb\xbfcaf\xc3\xa9", Encoding::Latin1, strict).unwrap(), "café");
+        assert_eq!(decode(b"", Encoding::Utf8, strict).unwrap(), "");
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let bytes = b"a\xffb\xc3";
+        let err = decode(bytes, Encoding::Utf8, EncodingErrors::Error).unwrap_err();
+        assert_eq!(err.to_string(), "byte 1: invalid Utf8");
+        assert_eq!(decode(bytes, Encoding::Utf8, EncodingErrors::Replace('?')).unwrap(), "a?b?");
+        assert_eq!(decode(bytes, Encoding::Utf8, EncodingErrors::Lossy).unwrap(), "a\u{fffd}b\u{fffd}");
+        // an unpaired surrogate
+        assert_eq!(decode(b"a\x00\x00\xd8", Encoding::Utf16Le, EncodingErrors::Lossy).unwrap(), "a\u{fffd}");
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect(b"id,name\n1,caf\xc3\xa9\n"), Encoding::Utf8);
+        assert_eq!(detect(b"id,name\n1,caf\xe9\n"), Encoding::Windows1252);
+        assert_eq!(detect(b"i\x00d\x00,\x00n\x00"), Encoding::Utf16Le);
+        assert_eq!(detect(b"\x00i\x00d\x00,\x00n"), Encoding::Utf16Be);
+        // the sample ends mid character
+        assert_eq!(detect(b"caf\xc3"), Encoding::Utf8);
+        assert_eq!(decode(b"id\n1,caf\xe9\n", Encoding::Detect, EncodingErrors::Error).unwrap(), "id\n1,café\n");
+    }
+
+    #[test]
+    fn test_encode() {
+        let strict = EncodingErrors::Error;
+        assert_eq!(encode("café €", Encoding::Windows1252, strict, false).unwrap(), b"caf\xe9 \x80");
+        assert_eq!(encode("cé€", Encoding::Utf16Le, strict, true).unwrap(), b"\xff\xfec\x00\xe9\x00\xac\x20");
+        assert_eq!(encode("cé", Encoding::Utf8, strict, true).unwrap(), b"\xef\xbb\xbfc\xc3\xa9");
+
+        let err = encode("café €", Encoding::Latin1, strict, false).unwrap_err();
+        assert_eq!(err.to_string(), "can't encode '€' as Latin1");
+        assert_eq!(encode("café €", Encoding::Latin1, EncodingErrors::Lossy, false).unwrap(), b"caf\xe9 ?");
+        assert_eq!(encode("a→b", Encoding::Windows1252, EncodingErrors::Replace('-'), false).unwrap(), b"a-b");
+        assert!(encode("a→b", Encoding::Windows1252, EncodingErrors::Replace('⇒'), false).is_err());
+
+        assert!(encode("a", Encoding::Latin1, strict, true).is_err());
+        assert!(encode("a", Encoding::Detect, strict, false).is_err());
+    }
+}
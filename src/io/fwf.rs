@@ -0,0 +1,561 @@
+//! Fixed width text, where each column is a set range of characters
+//! on every line, as described by an `FwfLayout`.
+
+use chrono::{NaiveDate, NaiveDateTime};
+use csv::ReaderBuilder;
+use failure::Error;
+use serde_json::{self, Value as JsonValue};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+use dataframe::{Array, DataFrame};
+use decimal::parse_decimal;
+use io::compression::Compression;
+use io::encoding::{Encoding, EncodingErrors};
+use io::{array_to_strings, ISO_DATE, ISO_DATETIMES};
+use value::Value;
+
+// Compact layouts that legacy files use besides ISO 8601
+const COMPACT_DATE: &str = "%Y%m%d";
+const COMPACT_DATETIME: &str = "%Y%m%d%H%M%S";
+
+/// A column of a fixed width file: `width` characters from the 0-based
+/// character offset `start` of each line, parsed as `dtype`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FwfColumn {
+    pub name: String,
+    pub start: usize,
+    pub width: usize,
+    pub dtype: String,
+}
+
+/// The columns of a fixed width file, in the order they become
+/// DataFrame columns. Columns may be listed out of line order, and
+/// characters between columns are ignored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FwfLayout {
+    columns: Vec<FwfColumn>,
+}
+
+impl FwfLayout {
+    /// Errors on a width of 0, a repeated name, or a dtype that's not
+    /// found. Duration can't be read back from text, so isn't supported.
+    pub fn new(specs: &[(&str, usize, usize, &str)]) -> Result<Self, Error> {
+        let columns = specs.iter()
+            .map(|&(name, start, width, dtype)| FwfColumn {
+                name: name.to_owned(),
+                start,
+                width,
+                dtype: dtype.to_owned(),
+            })
+            .collect();
+        FwfLayout::from_columns(columns)
+    }
+
+    pub fn from_columns(columns: Vec<FwfColumn>) -> Result<Self, Error> {
+        for (i, col) in columns.iter().enumerate() {
+            if col.width == 0 {
+                return Err(format_err!("column {}: width must be at least 1", col.name));
+            }
+            if columns[..i].iter().any(|other| other.name == col.name) {
+                return Err(format_err!("column {} is in the layout twice", col.name));
+            }
+            if Array::new(&col.dtype)?.dtype() == "Duration" {
+                return Err(format_err!("column {}: Duration isn't supported in fixed width files", col.name));
+            }
+        }
+        Ok(FwfLayout { columns })
+    }
+
+    /// Reads a layout from a csv with the header `name,start,width,dtype`
+    /// (in any order), one row per column.
+    pub fn from_csv<R: Read>(rdr: R) -> Result<Self, Error> {
+        let mut rdr = ReaderBuilder::new().from_reader(rdr);
+        let headers = rdr.headers()?.clone();
+        let position = |field: &str| {
+            headers.iter()
+                .position(|header| header.trim() == field)
+                .ok_or_else(|| format_err!("layout csv has no {} column", field))
+        };
+        let (name, start, width, dtype) = (position("name")?, position("start")?, position("width")?, position("dtype")?);
+
+        let mut columns = Vec::new();
+        for (i, record) in rdr.records().enumerate() {
+            let record = record?;
+            let field = |j: usize| record.get(j).unwrap_or("").trim();
+            let number = |j: usize| field(j).parse::<usize>()
+                .map_err(|_| format_err!("layout row {}: can't parse {:?} as a position", i + 1, field(j)));
+            columns.push(FwfColumn {
+                name: field(name).to_owned(),
+                start: number(start)?,
+                width: number(width)?,
+                dtype: field(dtype).to_owned(),
+            });
+        }
+        FwfLayout::from_columns(columns)
+    }
+
+    pub fn from_csv_path<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        FwfLayout::from_csv(open(path.as_ref())?)
+    }
+
+    /// Reads a layout from a JSON array of objects with the keys `name`,
+    /// `start`, `width` and `dtype`, one per column.
+    pub fn from_json<R: Read>(rdr: R) -> Result<Self, Error> {
+        let specs = match serde_json::from_reader(rdr)? {
+            JsonValue::Array(specs) => specs,
+            _ => return Err(format_err!("layout json must be an array of columns")),
+        };
+        let columns = specs.iter()
+            .enumerate()
+            .map(|(i, spec)| {
+                let string = |key: &str| spec.get(key)
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_owned())
+                    .ok_or_else(|| format_err!("layout column {}: {} must be a string", i, key));
+                let number = |key: &str| spec.get(key)
+                    .and_then(|v| v.as_u64())
+                    .map(|x| x as usize)
+                    .ok_or_else(|| format_err!("layout column {}: {} must be a non-negative integer", i, key));
+                Ok(FwfColumn {
+                    name: string("name")?,
+                    start: number("start")?,
+                    width: number("width")?,
+                    dtype: string("dtype")?,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        FwfLayout::from_columns(columns)
+    }
+
+    pub fn from_json_path<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        FwfLayout::from_json(open(path.as_ref())?)
+    }
+
+    pub fn columns(&self) -> &[FwfColumn] {
+        &self.columns
+    }
+
+    /// Width of a line, to the end of the last column.
+    pub fn line_width(&self) -> usize {
+        self.columns.iter().map(|col| col.start + col.width).max().unwrap_or(0)
+    }
+}
+
+fn open(path: &Path) -> Result<File, Error> {
+    File::open(path).map_err(|err| format_err!("couldn't open {}: {}", path.display(), err))
+}
+
+/// Options for reading a fixed width file into a DataFrame.
+///
+/// By default compression is picked from the file extension (see
+/// `compression`) and the input is UTF-8 (see `encoding`).
+///
+/// Each field is trimmed of spaces, and a blank field is null in every
+/// dtype, Str included. A line that ends before a column is blank in
+/// it. Empty lines are skipped.
+#[derive(Debug, Clone)]
+pub struct FwfReader {
+    compression: Option<Compression>,
+    encoding: Option<Encoding>,
+    encoding_errors: EncodingErrors,
+}
+
+impl FwfReader {
+    pub fn new() -> Self {
+        FwfReader {
+            compression: None,
+            encoding: None,
+            encoding_errors: EncodingErrors::Error,
+        }
+    }
+
+    /// Decompress the input with this codec. Unset, `read_path` goes by
+    /// the file extension (see `Compression::from_path`) and `read`
+    /// reads it as is.
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Decode the input from this encoding, after any decompression.
+    /// Column positions count characters, not bytes, so they hold in
+    /// any encoding.
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    /// What to do with bytes that aren't valid in the `encoding`, if set.
+    /// By default they're an error.
+    pub fn encoding_errors(mut self, encoding_errors: EncodingErrors) -> Self {
+        self.encoding_errors = encoding_errors;
+        self
+    }
+
+    pub fn read_path<P: AsRef<Path>>(&self, path: P, layout: &FwfLayout) -> Result<DataFrame, Error> {
+        let path = path.as_ref();
+        let compression = self.compression.unwrap_or_else(|| Compression::from_path(path));
+        self.read_decoded(self.decoder(open(path)?, compression)?, layout)
+    }
+
+    /// Errors on a field that doesn't parse as its column's dtype. Dates
+    /// are ISO 8601 or `%Y%m%d`, datetimes ISO 8601 without an offset or
+    /// `%Y%m%d%H%M%S`, and Bools any case of true/false, t/f, y/n or 1/0.
+    pub fn read<R: Read>(&self, rdr: R, layout: &FwfLayout) -> Result<DataFrame, Error> {
+        self.read_decoded(self.decoder(rdr, self.compression.unwrap_or(Compression::None))?, layout)
+    }
+
+    fn decoder<'a, R: Read + 'a>(&self, rdr: R, compression: Compression) -> Result<Box<dyn Read + 'a>, Error> {
+        let rdr = compression.decoder(rdr)?;
+        match self.encoding {
+            Some(encoding) => encoding.decoder(rdr, self.encoding_errors),
+            None => Ok(rdr),
+        }
+    }
+
+    fn read_decoded<'a>(&self, rdr: Box<dyn Read + 'a>, layout: &FwfLayout) -> Result<DataFrame, Error> {
+        let mut arrays = layout.columns.iter()
+            .map(|col| Array::new(&col.dtype))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        for (i, line) in BufReader::new(rdr).lines().enumerate() {
+            let line = line.map_err(|err| format_err!("line {}: {}", i + 1, err))?;
+            let line = line.trim_end_matches('\r');
+            if line.is_empty() {
+                continue;
+            }
+            let offsets = char_offsets(line);
+            for (col, array) in layout.columns.iter().zip(arrays.iter_mut()) {
+                let field = field(line, &offsets, col.start, col.width).trim();
+                let value = parse_field(field, array)
+                    .ok_or_else(|| format_err!("line {}: column {}: can't parse {:?} as {}", i + 1, col.name, field, col.dtype))?;
+                array.push_value(value)
+                    .map_err(|err| format_err!("line {}: column {}: {}", i + 1, col.name, err))?;
+            }
+        }
+
+        let mut df = DataFrame::new();
+        for (col, array) in layout.columns.iter().zip(arrays) {
+            df.add_col(col.name.clone(), array);
+        }
+        Ok(df)
+    }
+}
+
+impl Default for FwfReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Byte offset of each character, and of the end, unless the line is
+// ascii and they're the character offsets
+fn char_offsets(line: &str) -> Option<Vec<usize>> {
+    if line.is_ascii() {
+        None
+    } else {
+        Some(line.char_indices().map(|(i, _)| i).chain(Some(line.len())).collect())
+    }
+}
+
+// Characters `start..start + width` of the line, cut short at its end
+fn field<'a>(line: &'a str, offsets: &Option<Vec<usize>>, start: usize, width: usize) -> &'a str {
+    match *offsets {
+        None => {
+            let end = (start + width).min(line.len());
+            if start < end { &line[start..end] } else { "" }
+        },
+        Some(ref offsets) => {
+            let n_chars = offsets.len() - 1;
+            let end = (start + width).min(n_chars);
+            if start < end { &line[offsets[start]..offsets[end]] } else { "" }
+        },
+    }
+}
+
+// The trimmed field as a value of the array's dtype, None if it doesn't
+// parse
+fn parse_field(field: &str, array: &Array) -> Option<Value> {
+    if field.is_empty() {
+        return Some(Value::Null);
+    }
+    match *array {
+        Array::Int8(_) => field.parse().ok().map(Value::Int8),
+        Array::Int16(_) => field.parse().ok().map(Value::Int16),
+        Array::Int32(_) => field.parse().ok().map(Value::Int32),
+        Array::Int64(_) => field.parse().ok().map(Value::Int64),
+        Array::UInt8(_) => field.parse().ok().map(Value::UInt8),
+        Array::UInt16(_) => field.parse().ok().map(Value::UInt16),
+        Array::UInt32(_) => field.parse().ok().map(Value::UInt32),
+        Array::UInt64(_) => field.parse().ok().map(Value::UInt64),
+        Array::Float32(_) => field.parse().ok().map(Value::Float32),
+        Array::Float64(_) => field.parse().ok().map(Value::Float64),
+        Array::Bool(_) => match field.to_lowercase().as_str() {
+            "true" | "t" | "y" | "1" => Some(Value::Bool(true)),
+            "false" | "f" | "n" | "0" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        Array::Str(_) | Array::Categorical(_) => Some(Value::Str(field.to_owned())),
+        Array::Date(_) => [ISO_DATE, COMPACT_DATE].iter()
+            .filter_map(|fmt| NaiveDate::parse_from_str(field, fmt).ok())
+            .next()
+            .map(Value::Date),
+        Array::Datetime(_) => ISO_DATETIMES.iter().chain(Some(&COMPACT_DATETIME))
+            .filter_map(|fmt| NaiveDateTime::parse_from_str(field, fmt).ok())
+            .next()
+            .map(Value::Datetime),
+        Array::Decimal(ref dec_data) => parse_decimal(field, dec_data.scale()).ok()
+            .map(|x| Value::Decimal(x, dec_data.scale())),
+        Array::Duration(_) => None,
+    }
+}
+
+/// Options for writing a DataFrame as fixed width text.
+///
+/// By default compression is picked from the file extension (see
+/// `compression`) and the output is UTF-8 (see `encoding`).
+///
+/// Numbers are right aligned in their columns and everything else left
+/// aligned, nulls are blank, and characters between columns are spaces.
+/// Values are written as in csv, so dates and datetimes are ISO 8601,
+/// except Bools, which are T or F.
+#[derive(Debug, Clone)]
+pub struct FwfWriter {
+    compression: Option<Compression>,
+    encoding: Encoding,
+    encoding_errors: EncodingErrors,
+}
+
+impl FwfWriter {
+    pub fn new() -> Self {
+        FwfWriter {
+            compression: None,
+            encoding: Encoding::Utf8,
+            encoding_errors: EncodingErrors::Error,
+        }
+    }
+
+    /// Compress the output with this codec. Unset, `write_path` goes by
+    /// the file extension (see `Compression::from_path`) and `write`
+    /// writes plain text.
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = Some(compression);
+        self
+    }
+
+    /// Encode the output in this encoding, before any compression.
+    /// `Encoding::Detect` is for reading only.
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// What to do with characters the `encoding` can't represent. By
+    /// default they're an error.
+    pub fn encoding_errors(mut self, encoding_errors: EncodingErrors) -> Self {
+        self.encoding_errors = encoding_errors;
+        self
+    }
+
+    pub fn write_path<P: AsRef<Path>>(&self, df: &DataFrame, path: P, layout: &FwfLayout) -> Result<(), Error> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .map_err(|err| format_err!("couldn't create {}: {}", path.display(), err))?;
+        let compression = self.compression.unwrap_or_else(|| Compression::from_path(path));
+        self.write_compressed(df, file, compression, layout)
+    }
+
+    /// Errors if the DataFrame's columns have different lengths, the
+    /// layout's columns overlap, a column isn't in the DataFrame or has
+    /// another dtype, or a value is wider than its column.
+    pub fn write<W: Write>(&self, df: &DataFrame, wtr: W, layout: &FwfLayout) -> Result<(), Error> {
+        self.write_compressed(df, wtr, self.compression.unwrap_or(Compression::None), layout)
+    }
+
+    fn write_compressed<W: Write>(&self, df: &DataFrame, wtr: W, compression: Compression, layout: &FwfLayout)
+        -> Result<(), Error>
+    {
+        df.columns_of(&[])?;
+        let mut by_start: Vec<&FwfColumn> = layout.columns.iter().collect();
+        by_start.sort_by_key(|col| col.start);
+        for pair in by_start.windows(2) {
+            if pair[0].start + pair[0].width > pair[1].start {
+                return Err(format_err!("columns {} and {} overlap", pair[0].name, pair[1].name));
+            }
+        }
+
+        let mut cols = Vec::with_capacity(by_start.len());
+        for col in by_start {
+            let array = df.get_col(&col.name)
+                .ok_or_else(|| format_err!("column {} not found", col.name))?;
+            if array.dtype() != col.dtype {
+                return Err(format_err!("column {} is {}, layout says {}", col.name, array.dtype(), col.dtype));
+            }
+            let mut strings = array_to_strings(array);
+            if let Array::Bool(_) = *array {
+                // one character, to fit a flag column
+                for s in strings.iter_mut().flatten() {
+                    *s = if s == "true" { "T".to_owned() } else { "F".to_owned() };
+                }
+            }
+            cols.push((col, is_numeric(array), strings));
+        }
+
+        let mut wtr = self.encoding.encoder(compression.encoder(wtr)?, self.encoding_errors, false)?;
+        let mut line = String::with_capacity(layout.line_width() + 1);
+        for i in 0..df.n_rows() {
+            line.clear();
+            let mut n_chars = 0;
+            for &(col, right_align, ref strings) in &cols {
+                let value = strings[i].as_ref().map_or("", |s| s.as_str());
+                let len = value.chars().count();
+                if len > col.width {
+                    return Err(format_err!("row {}: column {}: {:?} is wider than {}", i, col.name, value, col.width));
+                }
+                let (gap, padding) = (col.start - n_chars, col.width - len);
+                let (before, after) = if right_align { (gap + padding, 0) } else { (gap, padding) };
+                line.extend(::std::iter::repeat_n(' ', before));
+                line.push_str(value);
+                line.extend(::std::iter::repeat_n(' ', after));
+                n_chars = col.start + col.width;
+            }
+            line.push('\n');
+            wtr.write_all(line.as_bytes())?;
+        }
+        wtr.finish()?.finish()?;
+        Ok(())
+    }
+}
+
+impl Default for FwfWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn is_numeric(array: &Array) -> bool {
+    matches!(*array,
+        Array::Int8(_) | Array::Int16(_) | Array::Int32(_) | Array::Int64(_) |
+        Array::UInt8(_) | Array::UInt16(_) | Array::UInt32(_) | Array::UInt64(_) |
+        Array::Float32(_) | Array::Float64(_) | Array::Decimal(_))
+}
+
+impl DataFrame {
+    /// Reads a fixed width file with columns `(name, start, width,
+    /// dtype)`, decompressing `.gz`, `.zst` and `.bz2` files. Use
+    /// `FwfLayout` to load the layout from a file, and `FwfReader` for
+    /// other options.
+    pub fn read_fwf<P: AsRef<Path>>(path: P, specs: &[(&str, usize, usize, &str)]) -> Result<Self, Error> {
+        FwfReader::new().read_path(path, &FwfLayout::new(specs)?)
+    }
+
+    /// Writes the columns `(name, start, width, dtype)` as a fixed width
+    /// file, compressed if the path ends in `.gz`, `.zst` or `.bz2`. Use
+    /// `FwfWriter` for other options.
+    pub fn write_fwf<P: AsRef<Path>>(&self, path: P, specs: &[(&str, usize, usize, &str)]) -> Result<(), Error> {
+        FwfWriter::new().write_path(self, path, &FwfLayout::new(specs)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dataframe::ArrayData;
+    use value::{column_values, Value};
+
+    const FWF: &str = "\
+00001Ann       2018-03-15   12.50Y
+00002Bob       20180316    (3.00)n
+00003          2018-03-17       \r
+";
+
+    const SPECS: &[(&str, usize, usize, &str)] = &[
+        ("id", 0, 5, "Int64"),
+        ("name", 5, 10, "Str"),
+        ("date", 15, 10, "Date"),
+        ("amount", 25, 8, "Decimal(8,2)"),
+        ("flag", 33, 1, "Bool"),
+    ];
+
+    #[test]
+    fn test_read_fwf() {
+        let layout = FwfLayout::new(SPECS).unwrap();
+        let df = FwfReader::new().read(FWF.as_bytes(), &layout).unwrap();
+
+        assert_eq!(df.columns.keys().collect::<Vec<_>>(), vec!["id", "name", "date", "amount", "flag"]);
+        assert_eq!(column_values(&df, "id"), vec![Value::Int64(1), Value::Int64(2), Value::Int64(3)]);
+        assert_eq!(column_values(&df, "name"), vec![Value::Str("Ann".into()), Value::Str("Bob".into()), Value::Null]);
+        assert_eq!(column_values(&df, "date"), vec![
+            Value::Date(NaiveDate::from_ymd_opt(2018, 3, 15).unwrap()),
+            Value::Date(NaiveDate::from_ymd_opt(2018, 3, 16).unwrap()),
+            Value::Date(NaiveDate::from_ymd_opt(2018, 3, 17).unwrap()),
+        ]);
+        assert_eq!(column_values(&df, "amount"), vec![Value::Decimal(1250, 2), Value::Decimal(-300, 2), Value::Null]);
+        assert_eq!(column_values(&df, "flag"), vec![Value::Bool(true), Value::Bool(false), Value::Null]);
+
+        let bad = "0000x\n";
+        let err = FwfReader::new().read(bad.as_bytes(), &layout).unwrap_err();
+        assert_eq!(err.to_string(), "line 1: column id: can't parse \"0000x\" as Int64");
+    }
+
+    #[test]
+    fn test_fwf_counts_characters() {
+        let layout = FwfLayout::new(&[("city", 0, 6, "Str"), ("pop", 6, 3, "UInt32")]).unwrap();
+        let df = FwfReader::new()
+            .encoding(Encoding::Latin1)
+            .read(&b"K\xf6ln  100\n"[..], &layout)
+            .unwrap();
+        assert_eq!(column_values(&df, "city"), vec![Value::Str("Köln".into())]);
+        assert_eq!(column_values(&df, "pop"), vec![Value::UInt32(100)]);
+    }
+
+    #[test]
+    fn test_layout_files() {
+        let expected = FwfLayout::new(&[("id", 0, 5, "Int64"), ("name", 5, 10, "Str")]).unwrap();
+
+        let csv = "name,start,width,dtype\nid,0,5,Int64\nname,5,10,Str\n";
+        assert_eq!(FwfLayout::from_csv(csv.as_bytes()).unwrap(), expected);
+
+        let json = r#"[{"name": "id", "start": 0, "width": 5, "dtype": "Int64"},
+                       {"name": "name", "start": 5, "width": 10, "dtype": "Str"}]"#;
+        assert_eq!(FwfLayout::from_json(json.as_bytes()).unwrap(), expected);
+
+        assert!(FwfLayout::new(&[("id", 0, 0, "Int64")]).is_err());
+        assert!(FwfLayout::new(&[("id", 0, 5, "Int64"), ("id", 5, 5, "Str")]).is_err());
+        assert!(FwfLayout::new(&[("id", 0, 5, "Int")]).is_err());
+        assert!(FwfLayout::from_csv("name,start,dtype\nid,0,Int64\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_write_fwf_round_trips() {
+        let layout = FwfLayout::new(SPECS).unwrap();
+        let df = FwfReader::new().read(FWF.as_bytes(), &layout).unwrap();
+
+        let mut buf = Vec::new();
+        FwfWriter::new().write(&df, &mut buf, &layout).unwrap();
+        assert_eq!(String::from_utf8(buf.clone()).unwrap(), "    1Ann       2018-03-15   12.50T
+    2Bob       2018-03-16   -3.00F
+    3          2018-03-17         
+");
+        let read_back = FwfReader::new().read(&buf[..], &layout).unwrap();
+        for name in df.columns.keys() {
+            assert_eq!(column_values(&read_back, name), column_values(&df, name));
+        }
+
+        let narrow = FwfLayout::new(&[("id", 0, 5, "Int64"), ("name", 5, 2, "Str")]).unwrap();
+        let err = FwfWriter::new().write(&df, Vec::new(), &narrow).unwrap_err();
+        assert_eq!(err.to_string(), "row 0: column name: \"Ann\" is wider than 2");
+
+        let overlapping = FwfLayout::new(&[("id", 0, 5, "Int64"), ("name", 4, 10, "Str")]).unwrap();
+        assert!(FwfWriter::new().write(&df, Vec::new(), &overlapping).is_err());
+        let wrong_dtype = FwfLayout::new(&[("id", 0, 5, "Int32")]).unwrap();
+        assert!(FwfWriter::new().write(&df, Vec::new(), &wrong_dtype).is_err());
+
+        let mut ragged = df.clone();
+        ragged.add_col("id".to_owned(), Array::Int64(ArrayData::from_vec(vec![1])));
+        let err = FwfWriter::new().write(&ragged, Vec::new(), &layout).unwrap_err();
+        assert_eq!(err.to_string(), "column name has len 3, column id has len 1");
+    }
+}
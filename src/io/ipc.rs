@@ -0,0 +1,233 @@
+//! Arrow IPC reading and writing, in the file (`.arrow`) and stream
+//! (`.arrows`) formats. Needs the `ipc` feature, on by default. See
+//! `io::arrow` for how Arrays map to Arrow types.
+
+use arrow_ipc::reader::{FileReader, StreamReader};
+use arrow_ipc::writer::{FileWriter, StreamWriter};
+use failure::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Cursor, Read, Seek, Write};
+use std::path::Path;
+
+use dataframe::DataFrame;
+
+/// Options for reading Arrow IPC into a DataFrame. By default reads
+/// the file format.
+#[derive(Debug, Clone)]
+pub struct IpcReader {
+    stream: bool,
+}
+
+impl IpcReader {
+    pub fn new() -> Self {
+        IpcReader {
+            stream: false,
+        }
+    }
+
+    /// When true, read the streaming format instead of the file format.
+    pub fn stream(mut self, stream: bool) -> Self {
+        self.stream = stream;
+        self
+    }
+
+    pub fn read_path<P: AsRef<Path>>(&self, path: P) -> Result<DataFrame, Error> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .map_err(|err| format_err!("couldn't open {}: {}", path.display(), err))?;
+        if self.stream {
+            self.read(file)
+        } else {
+            read_file(BufReader::new(file))
+        }
+    }
+
+    /// The file format needs random access, so it's read into memory
+    /// first. Errors on Arrow types with no matching Array.
+    pub fn read<R: Read>(&self, mut rdr: R) -> Result<DataFrame, Error> {
+        if self.stream {
+            let reader = StreamReader::try_new(BufReader::new(rdr), None)?;
+            let schema = reader.schema();
+            let batches = reader.collect::<Result<Vec<_>, _>>()?;
+            DataFrame::from_record_batches(schema, &batches)
+        } else {
+            let mut bytes = vec![];
+            rdr.read_to_end(&mut bytes)?;
+            read_file(Cursor::new(bytes))
+        }
+    }
+}
+
+impl Default for IpcReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn read_file<R: Read + Seek>(rdr: R) -> Result<DataFrame, Error> {
+    let reader = FileReader::try_new(rdr, None)?;
+    let schema = reader.schema();
+    let batches = reader.collect::<Result<Vec<_>, _>>()?;
+    DataFrame::from_record_batches(schema, &batches)
+}
+
+/// Options for writing a DataFrame as Arrow IPC, in one record batch.
+/// By default writes the file format.
+#[derive(Debug, Clone)]
+pub struct IpcWriter {
+    stream: bool,
+}
+
+impl IpcWriter {
+    pub fn new() -> Self {
+        IpcWriter {
+            stream: false,
+        }
+    }
+
+    /// When true, write the streaming format instead of the file format.
+    pub fn stream(mut self, stream: bool) -> Self {
+        self.stream = stream;
+        self
+    }
+
+    pub fn write_path<P: AsRef<Path>>(&self, df: &DataFrame, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .map_err(|err| format_err!("couldn't create {}: {}", path.display(), err))?;
+        self.write(df, BufWriter::new(file))
+    }
+
+    /// Errors if the columns aren't all the same length.
+    pub fn write<W: Write>(&self, df: &DataFrame, wtr: W) -> Result<(), Error> {
+        let batch = df.to_record_batch()?;
+        if self.stream {
+            let mut writer = StreamWriter::try_new(wtr, &batch.schema())?;
+            writer.write(&batch)?;
+            writer.finish()?;
+            writer.into_inner()?.flush()?;
+        } else {
+            let mut writer = FileWriter::try_new(wtr, &batch.schema())?;
+            writer.write(&batch)?;
+            writer.finish()?;
+            writer.into_inner()?.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for IpcWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DataFrame {
+    /// Reads an Arrow IPC file. Use `IpcReader` for the stream format.
+    pub fn read_ipc<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        IpcReader::new().read_path(path)
+    }
+
+    /// Writes an Arrow IPC file. Use `IpcWriter` for the stream format.
+    pub fn write_ipc<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        IpcWriter::new().write_path(self, path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use arrow_array::{Int64Array, RecordBatch};
+    use arrow_schema::{DataType as ArrowType, Field, Schema, TimeUnit};
+    use chrono::{Duration, FixedOffset, NaiveDate};
+    use std::sync::Arc;
+    use dataframe::{Array, ArrayData};
+    use value::{column_values, Value};
+
+    fn epoch() -> NaiveDate {
+        NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()
+    }
+
+    // Written by arrow-rs in two record batches, with nanosecond
+    // timestamps, millisecond durations and Int32 dictionary keys.
+    fn fixture(name: &str) -> String {
+        format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    fn check_alltypes(df: &DataFrame) {
+        let dtypes: Vec<_> = df.columns.values().map(|col| col.dtype()).collect();
+        assert_eq!(dtypes, vec!["Int8", "UInt64", "Float64", "Bool", "Str", "Categorical",
+            "Date", "Datetime", "Duration", "Decimal(10,2)"]);
+        assert_eq!(column_values(df, "int8"), vec![Value::Int8(1), Value::Null, Value::Int8(-3)]);
+        assert_eq!(column_values(df, "uint64"), vec![Value::UInt64(10), Value::UInt64(u64::MAX), Value::UInt64(0)]);
+        assert_eq!(column_values(df, "float64"), vec![Value::Float64(1.5), Value::Float64(-0.25), Value::Null]);
+        assert_eq!(column_values(df, "bool"), vec![Value::Bool(true), Value::Null, Value::Bool(false)]);
+        assert_eq!(column_values(df, "utf8"), vec![Value::Str("a".to_owned()), Value::Str("ß".to_owned()), Value::Null]);
+        assert_eq!(column_values(df, "dict"), vec![Value::Str("x".to_owned()), Value::Str("y".to_owned()), Value::Null]);
+        assert_eq!(column_values(df, "date32"), vec![
+            Value::Date(epoch()), Value::Date(NaiveDate::from_ymd_opt(2018, 3, 15).unwrap()), Value::Null,
+        ]);
+        assert_eq!(column_values(df, "timestamp"), vec![
+            Value::Datetime(NaiveDate::from_ymd_opt(2018, 3, 15).unwrap().and_hms_opt(10, 0, 0).unwrap()),
+            Value::Null,
+            Value::Datetime(epoch().and_hms_micro_opt(0, 0, 0, 1).unwrap()),
+        ]);
+        match *df.get_col("timestamp").unwrap() {
+            Array::Datetime(ref dt_data) => assert_eq!(dt_data.offset(), FixedOffset::east_opt(5 * 3600)),
+            ref other => panic!("expected Datetime, found {:?}", other),
+        }
+        assert_eq!(column_values(df, "duration"), vec![
+            Value::Duration(Duration::seconds(90)), Value::Duration(Duration::milliseconds(-1)), Value::Null,
+        ]);
+        assert_eq!(column_values(df, "decimal"), vec![Value::Decimal(123456, 2), Value::Null, Value::Decimal(-1, 2)]);
+    }
+
+    #[test]
+    fn test_read_ipc_fixtures() {
+        check_alltypes(&DataFrame::read_ipc(fixture("alltypes.arrow")).unwrap());
+        check_alltypes(&IpcReader::new().stream(true).read_path(fixture("alltypes.arrows")).unwrap());
+
+        // the formats aren't interchangeable
+        assert!(IpcReader::new().read_path(fixture("alltypes.arrows")).is_err());
+    }
+
+    #[test]
+    fn test_write_ipc_round_trip() {
+        let df = DataFrame::read_ipc(fixture("alltypes.arrow")).unwrap();
+        for &stream in &[false, true] {
+            let mut out = vec![];
+            IpcWriter::new().stream(stream).write(&df, &mut out).unwrap();
+            check_alltypes(&IpcReader::new().stream(stream).read(out.as_slice()).unwrap());
+        }
+
+        let types: Vec<ArrowType> = df.to_record_batch().unwrap().schema().fields().iter()
+            .map(|field| field.data_type().clone())
+            .collect();
+        assert_eq!(types[5], ArrowType::Dictionary(Box::new(ArrowType::UInt32), Box::new(ArrowType::Utf8)));
+        assert_eq!(types[7], ArrowType::Timestamp(TimeUnit::Microsecond, Some("+05:00".into())));
+        assert_eq!(types[8], ArrowType::Duration(TimeUnit::Microsecond));
+
+        let mut empty = df.slice(0, 0).unwrap();
+        let mut out = vec![];
+        IpcWriter::new().write(&empty, &mut out).unwrap();
+        assert_eq!(IpcReader::new().read(out.as_slice()).unwrap().n_rows(), 0);
+
+        empty.add_col("short".to_owned(), Array::Int8(ArrayData::from_vec(vec![1])));
+        assert!(IpcWriter::new().write(&empty, vec![]).is_err());
+    }
+
+    #[test]
+    fn test_read_ipc_duplicate_fields() {
+        let field = Field::new("x", ArrowType::Int64, true);
+        let schema = Arc::new(Schema::new(vec![field.clone(), field]));
+        let column = Arc::new(Int64Array::from(vec![1, 2]));
+        let batch = RecordBatch::try_new(schema.clone(), vec![column.clone(), column]).unwrap();
+        let mut writer = FileWriter::try_new(vec![], &schema).unwrap();
+        writer.write(&batch).unwrap();
+        writer.finish().unwrap();
+        let out = writer.into_inner().unwrap();
+
+        let err = IpcReader::new().read(out.as_slice()).unwrap_err();
+        assert_eq!(err.to_string(), "duplicate column x");
+    }
+}
@@ -0,0 +1,365 @@
+use failure::Error;
+use indexmap::IndexMap;
+use serde_json::{self, Map, Value as JsonValue};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use dataframe::{Array, ArrayData, DataFrame};
+use io::{array_to_strings, infer_temporal};
+use value::Value;
+
+/// Options for reading JSON records into a DataFrame.
+///
+/// By default the input is one array of objects, and column dtypes are
+/// inferred (see `infer_dtypes`). Columns are in order of first
+/// appearance, and rows missing a key are null in that column.
+#[derive(Debug, Clone)]
+pub struct JsonReader {
+    lines: bool,
+    infer_dtypes: bool,
+}
+
+impl JsonReader {
+    pub fn new() -> Self {
+        JsonReader {
+            lines: false,
+            infer_dtypes: true,
+        }
+    }
+
+    /// When true, read newline delimited JSON: one object per line,
+    /// skipping blank lines.
+    pub fn lines(mut self, lines: bool) -> Self {
+        self.lines = lines;
+        self
+    }
+
+    /// When true, a column of all booleans is Bool, all integers is
+    /// Int64 (UInt64 if some are above `i64::MAX` and none are
+    /// negative), all numbers is Float64, and all strings is Date or
+    /// Datetime if they're ISO 8601, else Str. Anything else, or
+    /// everything when false, is Str, with non-strings as JSON text.
+    pub fn infer_dtypes(mut self, infer_dtypes: bool) -> Self {
+        self.infer_dtypes = infer_dtypes;
+        self
+    }
+
+    pub fn read_path<P: AsRef<Path>>(&self, path: P) -> Result<DataFrame, Error> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .map_err(|err| format_err!("couldn't open {}: {}", path.display(), err))?;
+        self.read(BufReader::new(file))
+    }
+
+    /// Errors on invalid JSON or records that aren't objects.
+    pub fn read<R: Read>(&self, rdr: R) -> Result<DataFrame, Error> {
+        let records = if self.lines {
+            read_lines(rdr)?
+        } else {
+            match serde_json::from_reader(rdr)? {
+                JsonValue::Array(values) => {
+                    values.into_iter()
+                        .enumerate()
+                        .map(|(i, value)| match value {
+                            JsonValue::Object(record) => Ok(record),
+                            _ => Err(format_err!("record {} is not an object", i)),
+                        })
+                        .collect::<Result<Vec<_>, Error>>()?
+                },
+                _ => return Err(format_err!("expected an array of records")),
+            }
+        };
+
+        let mut cols: IndexMap<String, Vec<JsonValue>> = IndexMap::new();
+        for (i, record) in records.into_iter().enumerate() {
+            for (key, value) in record {
+                cols.entry(key).or_insert_with(|| vec![JsonValue::Null; i]).push(value);
+            }
+            for col in cols.values_mut() {
+                if col.len() == i {
+                    col.push(JsonValue::Null);
+                }
+            }
+        }
+
+        let mut df = DataFrame::new();
+        for (name, values) in cols {
+            let array = if self.infer_dtypes {
+                infer_json(values)
+            } else {
+                json_texts(&values)
+            };
+            df.add_col(name, array);
+        }
+        Ok(df)
+    }
+}
+
+impl Default for JsonReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn read_lines<R: Read>(rdr: R) -> Result<Vec<Map<String, JsonValue>>, Error> {
+    let mut records = vec![];
+    for (i, line) in BufReader::new(rdr).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line).map_err(|err| format_err!("line {}: {}", i + 1, err))? {
+            JsonValue::Object(record) => records.push(record),
+            _ => return Err(format_err!("line {} is not an object", i + 1)),
+        }
+    }
+    Ok(records)
+}
+
+fn infer_json(values: Vec<JsonValue>) -> Array {
+    let present: Vec<&JsonValue> = values.iter().filter(|v| !v.is_null()).collect();
+    if present.is_empty() {
+        return json_texts(&values);
+    }
+    if present.iter().all(|v| v.is_boolean()) {
+        return Array::Bool(ArrayData::from_options(values.iter().map(|v| v.as_bool()).collect()));
+    }
+    if present.iter().all(|v| v.is_i64()) {
+        return Array::Int64(ArrayData::from_options(values.iter().map(|v| v.as_i64()).collect()));
+    }
+    if present.iter().all(|v| v.is_u64()) {
+        return Array::UInt64(ArrayData::from_options(values.iter().map(|v| v.as_u64()).collect()));
+    }
+    if present.iter().all(|v| v.is_number()) {
+        return Array::Float64(ArrayData::from_options(values.iter().map(|v| v.as_f64()).collect()));
+    }
+    if present.iter().all(|v| v.is_string()) {
+        // infer_temporal reads empty strings as nulls
+        let strings: Vec<String> = values.iter().map(|v| v.as_str().unwrap_or("").to_owned()).collect();
+        if strings.iter().any(|s| !s.is_empty()) {
+            if let Some(array) = infer_temporal(&strings) {
+                return array;
+            }
+        }
+    }
+    json_texts(&values)
+}
+
+// Str, with strings as is and other values as JSON text
+fn json_texts(values: &[JsonValue]) -> Array {
+    let texts = values.iter()
+        .map(|v| match *v {
+            JsonValue::Null => None,
+            JsonValue::String(ref s) => Some(s.clone()),
+            ref other => Some(other.to_string()),
+        })
+        .collect();
+    Array::Str(ArrayData::from_options(texts))
+}
+
+/// Layout of the JSON written by `JsonWriter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonOrient {
+    /// `[{"a": 1, "b": "x"}, ...]`
+    Records,
+    /// `{"a": 1, "b": "x"}` on each line, newline delimited
+    Lines,
+    /// `{"a": [1, ...], "b": ["x", ...]}`
+    Columns,
+    /// `{"columns": ["a", "b"], "data": [[1, "x"], ...]}`
+    Split,
+}
+
+/// Options for writing a DataFrame as JSON.
+///
+/// Integers, floats and bools are JSON numbers and booleans, with NaN
+/// and infinite floats written as null. Every other dtype is written
+/// as a string, in the same text form as csv, so that dates and
+/// datetimes read back as the same dtype.
+#[derive(Debug, Clone)]
+pub struct JsonWriter {
+    orient: JsonOrient,
+}
+
+impl JsonWriter {
+    pub fn new() -> Self {
+        JsonWriter {
+            orient: JsonOrient::Records,
+        }
+    }
+
+    pub fn orient(mut self, orient: JsonOrient) -> Self {
+        self.orient = orient;
+        self
+    }
+
+    pub fn write_path<P: AsRef<Path>>(&self, df: &DataFrame, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .map_err(|err| format_err!("couldn't create {}: {}", path.display(), err))?;
+        self.write(df, BufWriter::new(file))
+    }
+
+    /// Errors if the columns aren't all the same length.
+    pub fn write<W: Write>(&self, df: &DataFrame, mut wtr: W) -> Result<(), Error> {
+        let names: Vec<&String> = df.columns.keys().collect();
+        let cols: Vec<Vec<JsonValue>> = df.columns_of(&[])?.into_iter().map(json_values).collect();
+        let len = df.n_rows();
+        let record = |i: usize| -> JsonValue {
+            names.iter().zip(&cols).map(|(&name, col)| (name.clone(), col[i].clone())).collect()
+        };
+
+        match self.orient {
+            JsonOrient::Records => {
+                let records: Vec<JsonValue> = (0..len).map(record).collect();
+                serde_json::to_writer(&mut wtr, &records)?;
+            },
+            JsonOrient::Lines => {
+                for i in 0..len {
+                    serde_json::to_writer(&mut wtr, &record(i))?;
+                    wtr.write_all(b"\n")?;
+                }
+            },
+            JsonOrient::Columns => {
+                let columns: Map<String, JsonValue> = names.iter().zip(cols)
+                    .map(|(&name, col)| (name.clone(), JsonValue::Array(col)))
+                    .collect();
+                serde_json::to_writer(&mut wtr, &columns)?;
+            },
+            JsonOrient::Split => {
+                let data: Vec<JsonValue> = (0..len)
+                    .map(|i| JsonValue::Array(cols.iter().map(|col| col[i].clone()).collect()))
+                    .collect();
+                let mut split = Map::new();
+                split.insert("columns".to_owned(), names.iter().map(|&name| name.clone()).collect());
+                split.insert("data".to_owned(), JsonValue::Array(data));
+                serde_json::to_writer(&mut wtr, &split)?;
+            },
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+impl Default for JsonWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn json_values(array: &Array) -> Vec<JsonValue> {
+    let strings = array_to_strings(array);
+    strings.into_iter()
+        .enumerate()
+        .map(|(i, s)| match array.get_value(i).expect("index in bounds") {
+            Value::Int8(x) => x.into(),
+            Value::Int16(x) => x.into(),
+            Value::Int32(x) => x.into(),
+            Value::Int64(x) => x.into(),
+            Value::UInt8(x) => x.into(),
+            Value::UInt16(x) => x.into(),
+            Value::UInt32(x) => x.into(),
+            Value::UInt64(x) => x.into(),
+            Value::Float32(x) => x.into(),
+            Value::Float64(x) => x.into(),
+            Value::Bool(x) => x.into(),
+            _ => s.map_or(JsonValue::Null, JsonValue::String),
+        })
+        .collect()
+}
+
+impl DataFrame {
+    /// Reads a JSON array of records, inferring column dtypes. Use
+    /// `JsonReader` for other options.
+    pub fn read_json<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        JsonReader::new().read_path(path)
+    }
+
+    /// Reads newline delimited JSON records, inferring column dtypes.
+    pub fn read_ndjson<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        JsonReader::new().lines(true).read_path(path)
+    }
+
+    /// Writes a JSON array of records. Use `JsonWriter` for other
+    /// layouts.
+    pub fn write_json<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        JsonWriter::new().write_path(self, path)
+    }
+
+    /// Writes newline delimited JSON records.
+    pub fn write_ndjson<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        JsonWriter::new().orient(JsonOrient::Lines).write_path(self, path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const NDJSON: &str = r#"{"id": 1, "name": "ann", "score": 1.5, "born": "1990-01-02", "tags": ["a"]}
+
+{"id": 2, "score": 2, "active": true, "born": null, "tags": null}
+"#;
+
+    fn write(df: &DataFrame, orient: JsonOrient) -> String {
+        let mut out = vec![];
+        JsonWriter::new().orient(orient).write(df, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_read_json_infers_dtypes() {
+        let df = JsonReader::new().lines(true).read(NDJSON.as_bytes()).unwrap();
+        let names: Vec<_> = df.columns.keys().collect();
+        assert_eq!(names, vec!["id", "name", "score", "born", "tags", "active"]);
+        let dtypes: Vec<_> = df.columns.values().map(|col| col.dtype()).collect();
+        assert_eq!(dtypes, vec!["Int64", "Str", "Float64", "Date", "Str", "Bool"]);
+        assert!(df.get_col("name").unwrap().is_null(1));
+        assert!(df.get_col("active").unwrap().is_null(0));
+        assert_eq!(df.get(0, "tags").unwrap(), Value::Str(r#"["a"]"#.to_owned()));
+
+        let df = JsonReader::new().infer_dtypes(false).read(r#"[{"a": 1}, {"a": "x"}]"#.as_bytes()).unwrap();
+        assert_eq!(df.get_col("a").unwrap().dtype(), "Str");
+
+        let df = JsonReader::new().read(r#"[{"a": 18446744073709551615}, {"a": 9007199254740993}]"#.as_bytes()).unwrap();
+        assert_eq!(df.get(0, "a").unwrap(), Value::UInt64(u64::MAX));
+        assert_eq!(df.get(1, "a").unwrap(), Value::UInt64(9007199254740993));
+
+        let df = JsonReader::new().read(r#"[{"a": ""}, {"a": null}]"#.as_bytes()).unwrap();
+        assert_eq!(df.get_col("a").unwrap().dtype(), "Str");
+        assert_eq!(df.get_col("a").unwrap().null_count(), 1);
+
+        assert!(JsonReader::new().read(r#"{"a": 1}"#.as_bytes()).is_err());
+        assert!(JsonReader::new().read("[1]".as_bytes()).is_err());
+        assert!(JsonReader::new().lines(true).read("{\"a\": 1}\n{\"a\":".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_write_json_orients() {
+        let df = JsonReader::new().read(r#"[{"a": 1, "b": "x"}, {"a": null, "b": "2018-03-15"}]"#.as_bytes()).unwrap();
+        assert_eq!(write(&df, JsonOrient::Records), r#"[{"a":1,"b":"x"},{"a":null,"b":"2018-03-15"}]"#);
+        assert_eq!(write(&df, JsonOrient::Lines), "{\"a\":1,\"b\":\"x\"}\n{\"a\":null,\"b\":\"2018-03-15\"}\n");
+        assert_eq!(write(&df, JsonOrient::Columns), r#"{"a":[1,null],"b":["x","2018-03-15"]}"#);
+        assert_eq!(write(&df, JsonOrient::Split), r#"{"columns":["a","b"],"data":[[1,"x"],[null,"2018-03-15"]]}"#);
+    }
+
+    #[test]
+    fn test_write_json_round_trip() {
+        let df = JsonReader::new().lines(true).read(NDJSON.as_bytes()).unwrap();
+        let mut df = df.slice(0, 2).unwrap();
+        let cost = Array::Str(ArrayData::from_vec(vec!["1.50".to_owned(), "2".to_owned()]));
+        df.add_col("cost".to_owned(), cost.parse_decimal(8, 2).unwrap());
+        df.add_col("nan".to_owned(), Array::Float32(ArrayData::from_vec(vec![f32::NAN, 0.5])));
+
+        let json = write(&df, JsonOrient::Records);
+        assert!(json.contains(r#""cost":"1.50","nan":null"#), "{}", json);
+        let back = JsonReader::new().read(json.as_bytes()).unwrap();
+        for name in &["id", "name", "score", "born", "active"] {
+            assert_eq!(back.get_col(name).unwrap().dtype(), df.get_col(name).unwrap().dtype());
+            for i in 0..2 {
+                assert_eq!(back.get(i, name).unwrap(), df.get(i, name).unwrap());
+            }
+        }
+    }
+}
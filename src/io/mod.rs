@@ -0,0 +1,222 @@
+//! Reading and writing DataFrames.
+
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod compression;
+pub mod csv;
+pub mod encoding;
+pub mod fwf;
+#[cfg(feature = "ipc")]
+pub mod ipc;
+pub mod json;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
+use failure::Error;
+
+use dataframe::{Array, ArrayData};
+use temporal::DatetimeData;
+
+// ISO 8601 layouts recognised during inference, tried in order
+pub(crate) const ISO_DATE: &str = "%Y-%m-%d";
+pub(crate) const ISO_DATETIMES: &[&str] = &["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"];
+const ISO_DATETIME_OFFSET: &str = "%Y-%m-%dT%H:%M:%S%.f%:z";
+
+/// Picks the narrowest dtype that every value parses as: Int64,
+/// Float64, Date, then Datetime (ISO 8601 layouts only, with or without
+/// an offset), falling back to Str.
+///
+/// Empty strings are nulls in the typed columns, but are kept as
+/// empty strings in a Str column.
+pub(crate) fn infer_array(values: Vec<String>) -> Array {
+    if values.iter().all(|s| s.is_empty()) {
+        return Array::Str(ArrayData::from_vec(values));
+    }
+    if let Some(xs) = parse_all(&values, |s| s.parse::<i64>().ok()) {
+        return Array::Int64(xs);
+    }
+    if let Some(xs) = parse_all(&values, |s| s.parse::<f64>().ok()) {
+        return Array::Float64(xs);
+    }
+    if let Some(array) = infer_temporal(&values) {
+        return array;
+    }
+
+    Array::Str(ArrayData::from_vec(values))
+}
+
+/// The Date or Datetime parse of `values` if every non-empty value is
+/// ISO 8601, as in `infer_array`.
+pub(crate) fn infer_temporal(values: &[String]) -> Option<Array> {
+    if let Some(xs) = parse_all(values, |s| NaiveDate::parse_from_str(s, ISO_DATE).ok()) {
+        return Some(Array::Date(xs));
+    }
+    for fmt in ISO_DATETIMES {
+        if let Some(xs) = parse_all(values, |s| NaiveDateTime::parse_from_str(s, fmt).ok()) {
+            return Some(Array::Datetime(DatetimeData::new(xs, None)));
+        }
+    }
+    if let Some(xs) = parse_all(values, |s| DateTime::parse_from_rfc3339(s).ok()) {
+        let offset = xs.iter().filter_map(|dt| dt.map(|dt| *dt.offset())).next();
+        return Some(Array::Datetime(DatetimeData::new(xs.map_values(|dt| dt.naive_utc()), offset)));
+    }
+    None
+}
+
+/// Parses `values` as the dtype that `infer_array` picked for `like`,
+/// so that later batches of a column match the first. A Datetime keeps
+/// the offset of `like`.
+pub(crate) fn parse_as(values: Vec<String>, like: &Array) -> Result<Array, Error> {
+    if let Array::Str(_) = *like {
+        return Ok(Array::Str(ArrayData::from_vec(values)));
+    }
+    try_parse_as(&values, like).ok_or_else(|| {
+        match values.iter().position(|s| !parses_as(s, like)) {
+            Some(i) => format_err!("row {}: can't parse {:?} as {}", i, values[i], like.dtype()),
+            None => format_err!("values don't all parse as {}", like.dtype()),
+        }
+    })
+}
+
+fn try_parse_as(values: &[String], like: &Array) -> Option<Array> {
+    match *like {
+        Array::Int64(_) => parse_all(values, |s| s.parse::<i64>().ok()).map(Array::Int64),
+        Array::Float64(_) => parse_all(values, |s| s.parse::<f64>().ok()).map(Array::Float64),
+        Array::Date(_) => parse_all(values, |s| NaiveDate::parse_from_str(s, ISO_DATE).ok()).map(Array::Date),
+        Array::Datetime(ref dt_data) => match dt_data.offset() {
+            Some(offset) => parse_all(values, |s| DateTime::parse_from_rfc3339(s).ok().map(|dt| dt.naive_utc()))
+                .map(|xs| Array::Datetime(DatetimeData::new(xs, Some(offset)))),
+            None => ISO_DATETIMES.iter()
+                .filter_map(|fmt| parse_all(values, |s| NaiveDateTime::parse_from_str(s, fmt).ok()))
+                .next()
+                .map(|xs| Array::Datetime(DatetimeData::new(xs, None))),
+        },
+        _ => None,
+    }
+}
+
+/// Whether `value` on its own parses as the dtype of `like`, as in
+/// `parse_as`. Empty strings are nulls, so always parse.
+pub(crate) fn parses_as(value: &str, like: &Array) -> bool {
+    if value.is_empty() {
+        return true;
+    }
+    match *like {
+        Array::Str(_) => true,
+        Array::Int64(_) => value.parse::<i64>().is_ok(),
+        Array::Float64(_) => value.parse::<f64>().is_ok(),
+        Array::Date(_) => NaiveDate::parse_from_str(value, ISO_DATE).is_ok(),
+        Array::Datetime(ref dt_data) => match dt_data.offset() {
+            Some(_) => DateTime::parse_from_rfc3339(value).is_ok(),
+            None => ISO_DATETIMES.iter().any(|fmt| NaiveDateTime::parse_from_str(value, fmt).is_ok()),
+        },
+        _ => false,
+    }
+}
+
+// None unless every non-empty value parses
+fn parse_all<T, F>(values: &[String], parse: F) -> Option<ArrayData<T>>
+    where T: Send + Sync + Clone + Default,
+          F: Fn(&str) -> Option<T>
+{
+    values.iter()
+        .map(|s| if s.is_empty() { Some(None) } else { parse(s).map(Some) })
+        .collect::<Option<Vec<_>>>()
+        .map(ArrayData::from_options)
+}
+
+fn to_strings<T: ToString>(values: &[T]) -> Vec<String> {
+    values.iter().map(|v| v.to_string()).collect()
+}
+
+/// Text form of each value, as written to csv, with `None` for nulls.
+/// Floats always keep a decimal point, and dates and datetimes are
+/// ISO 8601, so that `infer_array` reads them back as the same dtype.
+pub(crate) fn array_to_strings(array: &Array) -> Vec<Option<String>> {
+    let strings = match *array {
+        Array::Int8(ref array_data) => to_strings(array_data.as_slice()),
+        Array::Int16(ref array_data) => to_strings(array_data.as_slice()),
+        Array::Int32(ref array_data) => to_strings(array_data.as_slice()),
+        Array::Int64(ref array_data) => to_strings(array_data.as_slice()),
+        Array::UInt8(ref array_data) => to_strings(array_data.as_slice()),
+        Array::UInt16(ref array_data) => to_strings(array_data.as_slice()),
+        Array::UInt32(ref array_data) => to_strings(array_data.as_slice()),
+        Array::UInt64(ref array_data) => to_strings(array_data.as_slice()),
+        Array::Float32(ref array_data) => array_data.as_slice().iter().map(|v| format!("{:?}", v)).collect(),
+        Array::Float64(ref array_data) => array_data.as_slice().iter().map(|v| format!("{:?}", v)).collect(),
+        Array::Bool(ref array_data) => to_strings(array_data.as_slice()),
+        Array::Str(ref array_data) => array_data.as_slice().to_vec(),
+        Array::Categorical(ref cat_data) => cat_data.decode().as_slice().to_vec(),
+        Array::Date(ref array_data) => to_strings(array_data.as_slice()),
+        Array::Datetime(ref dt_data) => {
+            let fmt = if dt_data.offset().is_some() { ISO_DATETIME_OFFSET } else { ISO_DATETIMES[0] };
+            match array.format_temporal(fmt) {
+                Ok(Array::Str(ref formatted)) => formatted.as_slice().to_vec(),
+                _ => unreachable!("Datetime formats to Str"),
+            }
+        },
+        Array::Duration(ref array_data) => to_strings(array_data.as_slice()),
+        Array::Decimal(ref dec_data) => dec_data.to_strings(),
+    };
+
+    strings.into_iter()
+        .enumerate()
+        .map(|(i, s)| if array.is_null(i) { None } else { Some(s) })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn infer(xs: &[&str]) -> Array {
+        infer_array(xs.iter().map(|s| s.to_string()).collect())
+    }
+
+    #[test]
+    fn test_infer_array() {
+        assert_eq!(infer(&["1", "-2"]).dtype(), "Int64");
+        assert_eq!(infer(&["1", "2.5"]).dtype(), "Float64");
+        assert_eq!(infer(&["2018-03-15", "2017-12-31"]).dtype(), "Date");
+        assert_eq!(infer(&["2018-03-15T10:00:00", "2018-03-15T10:00:00.5"]).dtype(), "Datetime");
+        assert_eq!(infer(&["2018-03-15 10:00:00"]).dtype(), "Datetime");
+        assert_eq!(infer(&["2018-03-15", "yesterday"]).dtype(), "Str");
+        assert_eq!(infer(&[]).dtype(), "Str");
+
+        match infer(&["2018-03-15T10:00:00+05:00"]) {
+            Array::Datetime(ref data) => assert!(data.offset().is_some()),
+            ref other => panic!("expected Datetime, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_array_to_strings_round_trips() {
+        for xs in &[
+            vec!["1", "-2"],
+            vec!["1.0", "2.5"],
+            vec!["2018-03-15"],
+            vec!["2018-03-15T10:00:00", "2018-03-15T10:00:00.500"],
+            vec!["2018-03-15T10:00:00+05:00"],
+        ] {
+            let array = infer(xs);
+            let strings: Vec<_> = array_to_strings(&array).into_iter().map(|s| s.unwrap()).collect();
+            assert_eq!(&strings, xs);
+        }
+    }
+
+    #[test]
+    fn test_infer_nulls() {
+        let array = infer(&["1", "", "3"]);
+        assert_eq!(array.dtype(), "Int64");
+        assert_eq!(array.null_count(), 1);
+        assert_eq!(array_to_strings(&array), vec![Some("1".to_owned()), None, Some("3".to_owned())]);
+
+        // Str keeps empty strings
+        let array = infer(&["a", ""]);
+        assert_eq!(array.null_count(), 0);
+        assert_eq!(infer(&["", ""]).dtype(), "Str");
+    }
+}
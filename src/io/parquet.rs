@@ -0,0 +1,283 @@
+//! Parquet reading and writing, through Arrow record batches. Needs the
+//! `parquet` feature, on by default. See `io::arrow` for how Arrays map
+//! to Arrow types; every dtype but Duration can be written.
+//!
+//! The Arrow schema is stored in the file metadata, so dtypes with no
+//! exact Parquet type (Categorical, a Datetime offset) read back the
+//! same.
+
+use arrow_array::RecordBatchReader;
+use bytes::Bytes;
+use failure::Error;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::{ArrowWriter, ProjectionMask};
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use parquet::file::reader::ChunkReader;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+
+use dataframe::{Array, DataFrame};
+
+/// Options for reading a Parquet file into a DataFrame. By default
+/// reads every column and row group.
+#[derive(Debug, Clone)]
+pub struct ParquetReader {
+    columns: Option<Vec<String>>,
+    row_groups: Option<Vec<usize>>,
+}
+
+impl ParquetReader {
+    pub fn new() -> Self {
+        ParquetReader {
+            columns: None,
+            row_groups: None,
+        }
+    }
+
+    /// Only read these columns, which are skipped on disk rather than
+    /// read and dropped. Columns keep their file order.
+    pub fn columns(mut self, columns: &[&str]) -> Self {
+        self.columns = Some(columns.iter().map(|name| name.to_string()).collect());
+        self
+    }
+
+    /// Only read these row groups, by index, in this order.
+    pub fn row_groups(mut self, row_groups: &[usize]) -> Self {
+        self.row_groups = Some(row_groups.to_vec());
+        self
+    }
+
+    pub fn read_path<P: AsRef<Path>>(&self, path: P) -> Result<DataFrame, Error> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .map_err(|err| format_err!("couldn't open {}: {}", path.display(), err))?;
+        self.read_chunks(file)
+    }
+
+    /// Parquet needs random access, so the input is read into memory
+    /// first. Errors on unknown columns or row groups.
+    pub fn read<R: Read>(&self, mut rdr: R) -> Result<DataFrame, Error> {
+        let mut bytes = vec![];
+        rdr.read_to_end(&mut bytes)?;
+        self.read_chunks(Bytes::from(bytes))
+    }
+
+    fn read_chunks<T: ChunkReader + 'static>(&self, chunks: T) -> Result<DataFrame, Error> {
+        let mut builder = ParquetRecordBatchReaderBuilder::try_new(chunks)?;
+
+        if let Some(ref columns) = self.columns {
+            let fields = builder.schema().fields();
+            let indices = columns.iter()
+                .map(|name| {
+                    fields.iter()
+                        .position(|field| field.name() == name)
+                        .ok_or_else(|| format_err!("column {} not found", name))
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            let mask = ProjectionMask::roots(builder.parquet_schema(), indices);
+            builder = builder.with_projection(mask);
+        }
+        if let Some(ref row_groups) = self.row_groups {
+            let n_row_groups = builder.metadata().num_row_groups();
+            if let Some(i) = row_groups.iter().find(|&&i| i >= n_row_groups) {
+                return Err(format_err!("row group {} out of range for {} row groups", i, n_row_groups));
+            }
+            builder = builder.with_row_groups(row_groups.clone());
+        }
+
+        let reader = builder.build()?;
+        let schema = reader.schema();
+        let batches = reader.collect::<Result<Vec<_>, _>>()?;
+        DataFrame::from_record_batches(schema, &batches)
+    }
+}
+
+impl Default for ParquetReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Compression codec for the pages of a Parquet file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParquetCompression {
+    Uncompressed,
+    Snappy,
+    /// Level 1 to 22, higher is smaller and slower
+    Zstd(i32),
+}
+
+/// Options for writing a DataFrame as Parquet. By default Snappy
+/// compressed, dictionary encoded and with row groups of up to
+/// 1,048,576 rows.
+#[derive(Debug, Clone)]
+pub struct ParquetWriter {
+    compression: ParquetCompression,
+    row_group_size: usize,
+    dictionary: bool,
+}
+
+impl ParquetWriter {
+    pub fn new() -> Self {
+        ParquetWriter {
+            compression: ParquetCompression::Snappy,
+            row_group_size: 1024 * 1024,
+            dictionary: true,
+        }
+    }
+
+    pub fn compression(mut self, compression: ParquetCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Maximum number of rows per row group.
+    pub fn row_group_size(mut self, row_group_size: usize) -> Self {
+        self.row_group_size = row_group_size;
+        self
+    }
+
+    /// When true, columns are dictionary encoded, which mostly helps
+    /// Str columns with repeated values. Categorical columns keep their
+    /// dictionary either way.
+    pub fn dictionary(mut self, dictionary: bool) -> Self {
+        self.dictionary = dictionary;
+        self
+    }
+
+    pub fn write_path<P: AsRef<Path>>(&self, df: &DataFrame, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .map_err(|err| format_err!("couldn't create {}: {}", path.display(), err))?;
+        self.write(df, BufWriter::new(file))
+    }
+
+    /// Errors if the columns aren't all the same length, on Duration
+    /// columns, or on an invalid zstd level or zero row group size.
+    pub fn write<W: Write + Send>(&self, df: &DataFrame, wtr: W) -> Result<(), Error> {
+        if let Some((name, _)) = df.columns.iter().find(|&(_, col)| matches!(*col, Array::Duration(_))) {
+            return Err(format_err!("column {}: Duration can't be written to parquet", name));
+        }
+        if self.row_group_size == 0 {
+            return Err(format_err!("row group size must be positive"));
+        }
+        let compression = match self.compression {
+            ParquetCompression::Uncompressed => Compression::UNCOMPRESSED,
+            ParquetCompression::Snappy => Compression::SNAPPY,
+            ParquetCompression::Zstd(level) => Compression::ZSTD(ZstdLevel::try_new(level)?),
+        };
+        let props = WriterProperties::builder()
+            .set_compression(compression)
+            .set_max_row_group_size(self.row_group_size)
+            .set_dictionary_enabled(self.dictionary)
+            .build();
+
+        let batch = df.to_record_batch()?;
+        let mut writer = ArrowWriter::try_new(wtr, batch.schema(), Some(props))?;
+        writer.write(&batch)?;
+        writer.into_inner()?.flush()?;
+        Ok(())
+    }
+}
+
+impl Default for ParquetWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DataFrame {
+    /// Reads every column of a Parquet file. Use `ParquetReader` to
+    /// pick columns or row groups.
+    pub fn read_parquet<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        ParquetReader::new().read_path(path)
+    }
+
+    /// Writes a Snappy compressed Parquet file. Use `ParquetWriter` for
+    /// other options.
+    pub fn write_parquet<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        ParquetWriter::new().write_path(self, path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dataframe::ArrayData;
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    use value::{column_values, Value};
+
+    fn frame() -> DataFrame {
+        let mut df = DataFrame::new();
+        let n = 10;
+        df.add_col("id".to_owned(), Array::Int32(ArrayData::from_vec((0..n).collect())));
+        df.add_col("score".to_owned(), Array::Float64(ArrayData::from_options(
+            (0..n).map(|i| if i % 3 == 0 { None } else { Some(i as f64 / 2.0) }).collect()
+        )));
+        df.add_col("flag".to_owned(), Array::Bool(ArrayData::from_options(
+            (0..n).map(|i| if i == 4 { None } else { Some(i % 2 == 0) }).collect()
+        )));
+        df.add_col("city".to_owned(), Array::Str(ArrayData::from_options(
+            (0..n).map(|i| if i == 7 { None } else { Some(["NY", "LA"][i as usize % 2].to_owned()) }).collect()
+        )));
+        df.add_col("big".to_owned(), Array::UInt64(ArrayData::from_vec((0..n).map(|i| u64::MAX - i as u64).collect())));
+        df
+    }
+
+    fn write(df: &DataFrame, writer: &ParquetWriter) -> Vec<u8> {
+        let mut out = vec![];
+        writer.write(df, &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_parquet_round_trip() {
+        let df = frame();
+        for &compression in &[ParquetCompression::Uncompressed, ParquetCompression::Snappy, ParquetCompression::Zstd(3)] {
+            let out = write(&df, &ParquetWriter::new().compression(compression));
+            let back = ParquetReader::new().read(out.as_slice()).unwrap();
+            assert_eq!(back.columns.keys().collect::<Vec<_>>(), df.columns.keys().collect::<Vec<_>>());
+            for name in df.columns.keys() {
+                assert_eq!(back.get_col(name).unwrap().dtype(), df.get_col(name).unwrap().dtype());
+                assert_eq!(column_values(&back, name), column_values(&df, name), "{}", name);
+            }
+        }
+
+        let mut categorical = DataFrame::new();
+        categorical.add_col("city".to_owned(), df.get_col("city").unwrap().to_categorical().unwrap());
+        let out = write(&categorical, &ParquetWriter::new());
+        let back = ParquetReader::new().read(out.as_slice()).unwrap();
+        assert_eq!(back.get_col("city").unwrap().dtype(), "Categorical");
+        assert_eq!(column_values(&back, "city"), column_values(&df, "city"));
+    }
+
+    #[test]
+    fn test_parquet_row_groups_and_projection() {
+        let df = frame();
+        let out = write(&df, &ParquetWriter::new().row_group_size(4).dictionary(false));
+        let metadata = SerializedFileReader::new(Bytes::from(out.clone())).unwrap().metadata().clone();
+        assert_eq!(metadata.num_row_groups(), 3);
+
+        let back = ParquetReader::new()
+            .columns(&["city", "id"])
+            .row_groups(&[2, 0])
+            .read(out.as_slice())
+            .unwrap();
+        assert_eq!(back.columns.keys().collect::<Vec<_>>(), vec!["id", "city"]);
+        assert_eq!(column_values(&back, "id"), [8, 9, 0, 1, 2, 3].iter().map(|&i| Value::Int32(i)).collect::<Vec<_>>());
+
+        assert!(ParquetReader::new().columns(&["nope"]).read(out.as_slice()).is_err());
+        assert!(ParquetReader::new().row_groups(&[3]).read(out.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_write_parquet_errors() {
+        let mut df = frame();
+        assert!(ParquetWriter::new().row_group_size(0).write(&df, vec![]).is_err());
+        assert!(ParquetWriter::new().compression(ParquetCompression::Zstd(99)).write(&df, vec![]).is_err());
+        df.add_col("took".to_owned(), Array::Duration(ArrayData::from_vec(vec![::chrono::Duration::seconds(1); 10])));
+        assert!(ParquetWriter::new().write(&df, vec![]).is_err());
+    }
+}
@@ -0,0 +1,463 @@
+//! SQLite reading and writing. Needs the `sqlite` feature, on by
+//! default, which builds a bundled SQLite.
+//!
+//! Arrays are written to columns with these declared types, which give
+//! the column's SQLite affinity:
+//!
+//! - ints: INTEGER (a UInt64 above `i64::MAX` is an error)
+//! - floats: REAL
+//! - Bool: BOOLEAN, stored as 0 or 1
+//! - Str and Categorical: TEXT
+//! - Date and Datetime: DATE and DATETIME, stored as ISO 8601 text
+//! - Decimal: DECIMAL_TEXT(precision,scale), stored as text, since a
+//!   column with NUMERIC affinity would turn more than 15 digits into
+//!   an inexact REAL
+//!
+//! Duration has no SQLite type, so isn't supported.
+//!
+//! Read back, a declared BOOLEAN, DATE, DATETIME (or TIMESTAMP) or
+//! DECIMAL_TEXT(p,s) (or DECIMAL(p,s) or NUMERIC(p,s)) column gets
+//! that dtype, and other
+//! columns go by affinity: INTEGER to Int64, REAL to Float64 and TEXT
+//! to Str. Columns with no declared type, such as expressions, or
+//! NUMERIC or BLOB affinity are Int64 if every value is an integer,
+//! Float64 if every value is a number, and otherwise Str.
+
+use failure::Error;
+use rusqlite::params_from_iter;
+use rusqlite::types::Value as SqlValue;
+use std::path::Path;
+
+pub use rusqlite::Connection;
+
+use dataframe::{Array, ArrayData, DataFrame};
+use decimal::{parse_decimal, parse_decimal_dtype, DecimalData};
+use io::{array_to_strings, infer_temporal};
+use sql::quote_ident;
+use value::Value;
+
+/// What to do when writing to a table that already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IfExists {
+    /// Error, leaving the table as is
+    Fail,
+    /// Drop the table and create it again
+    Replace,
+    /// Insert into the table, matching columns by name
+    Append,
+}
+
+/// Declared SQLite column type for an array (see the module docs).
+/// Errors on Duration.
+pub fn sqlite_type(array: &Array) -> Result<String, Error> {
+    match *array {
+        Array::Int8(_) | Array::Int16(_) | Array::Int32(_) | Array::Int64(_) |
+        Array::UInt8(_) | Array::UInt16(_) | Array::UInt32(_) | Array::UInt64(_) => Ok("INTEGER".to_owned()),
+        Array::Float32(_) | Array::Float64(_) => Ok("REAL".to_owned()),
+        Array::Bool(_) => Ok("BOOLEAN".to_owned()),
+        Array::Str(_) | Array::Categorical(_) => Ok("TEXT".to_owned()),
+        Array::Date(_) => Ok("DATE".to_owned()),
+        Array::Datetime(_) => Ok("DATETIME".to_owned()),
+        Array::Decimal(ref dec_data) => Ok(format!("DECIMAL_TEXT({},{})", dec_data.precision(), dec_data.scale())),
+        Array::Duration(_) => Err(format_err!("Duration isn't supported in SQLite")),
+    }
+}
+
+/// Options for writing a DataFrame to a SQLite table. By default it's
+/// an error if the table exists (see `if_exists`), and rows are written
+/// in transactions of 10,000 (see `batch_rows`).
+#[derive(Debug, Clone)]
+pub struct SqliteWriter {
+    if_exists: IfExists,
+    batch_rows: usize,
+}
+
+impl SqliteWriter {
+    pub fn new() -> Self {
+        SqliteWriter {
+            if_exists: IfExists::Fail,
+            batch_rows: 10_000,
+        }
+    }
+
+    /// What to do if the table exists. A table that doesn't is created,
+    /// even under `IfExists::Append`.
+    pub fn if_exists(mut self, if_exists: IfExists) -> Self {
+        self.if_exists = if_exists;
+        self
+    }
+
+    /// Commit after every this many rows. The table is created or
+    /// replaced in the first transaction, so on an error later batches
+    /// aren't written but earlier ones stay.
+    pub fn batch_rows(mut self, batch_rows: usize) -> Self {
+        self.batch_rows = batch_rows;
+        self
+    }
+
+    /// Opens or creates the database file at `path` and writes to it.
+    pub fn write_path<P: AsRef<Path>>(&self, df: &DataFrame, path: P, table: &str) -> Result<(), Error> {
+        let path = path.as_ref();
+        let mut conn = Connection::open(path)
+            .map_err(|err| format_err!("couldn't open {}: {}", path.display(), err))?;
+        self.write(df, &mut conn, table)
+    }
+
+    /// Errors if the columns have different lengths, a column is
+    /// Duration, a value doesn't fit in SQLite, `batch_rows` is 0, or the
+    /// connection is already in a transaction.
+    pub fn write(&self, df: &DataFrame, conn: &mut Connection, table: &str) -> Result<(), Error> {
+        if self.batch_rows == 0 {
+            return Err(format_err!("batch_rows must be at least 1"));
+        }
+        df.columns_of(&[])?;
+        let types = df.columns.iter()
+            .map(|(name, array)| sqlite_type(array).map_err(|err| format_err!("column {}: {}", name, err)))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let names: Vec<String> = df.columns.keys().map(|name| quote_ident(name)).collect();
+        let params: Vec<String> = (1..=names.len()).map(|i| format!("?{}", i)).collect();
+        let insert = format!("INSERT INTO {} ({}) VALUES ({})", quote_ident(table), names.join(", "), params.join(", "));
+
+        let n_rows = df.n_rows();
+        let mut offset = 0;
+        loop {
+            let tx = conn.transaction()?;
+            if offset == 0 {
+                let exists: bool = tx.query_row(
+                    "SELECT count(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = ?1 COLLATE NOCASE",
+                    [table],
+                    |row| row.get(0),
+                )?;
+                if exists {
+                    match self.if_exists {
+                        IfExists::Fail => return Err(format_err!("table {} already exists", table)),
+                        IfExists::Replace => { tx.execute(&format!("DROP TABLE {}", quote_ident(table)), [])?; },
+                        IfExists::Append => (),
+                    }
+                }
+                if !exists || self.if_exists == IfExists::Replace {
+                    let cols: Vec<String> = names.iter().zip(&types).map(|(name, ty)| format!("{} {}", name, ty)).collect();
+                    tx.execute(&format!("CREATE TABLE {} ({})", quote_ident(table), cols.join(", ")), [])?;
+                }
+            }
+
+            let len = self.batch_rows.min(n_rows - offset);
+            let batch = df.slice(offset, len)?;
+            let cols = batch.columns.iter()
+                .map(|(name, array)| sql_values(array).map_err(|err| format_err!("column {}: {}", name, err)))
+                .collect::<Result<Vec<_>, Error>>()?;
+            {
+                let mut stmt = tx.prepare(&insert)?;
+                for i in 0..len {
+                    stmt.execute(params_from_iter(cols.iter().map(|col| &col[i])))?;
+                }
+            }
+            tx.commit()?;
+
+            offset += len;
+            if offset >= n_rows {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl Default for SqliteWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Values to bind for each row of the array
+fn sql_values(array: &Array) -> Result<Vec<SqlValue>, Error> {
+    match *array {
+        Array::Date(_) | Array::Datetime(_) | Array::Decimal(_) => {
+            return Ok(array_to_strings(array).into_iter().map(|s| s.map_or(SqlValue::Null, SqlValue::Text)).collect());
+        },
+        _ => (),
+    }
+    (0..array.len())
+        .map(|i| match array.get_value(i).unwrap_or(Value::Null) {
+            Value::Null => Ok(SqlValue::Null),
+            Value::Int8(x) => Ok(SqlValue::Integer(x.into())),
+            Value::Int16(x) => Ok(SqlValue::Integer(x.into())),
+            Value::Int32(x) => Ok(SqlValue::Integer(x.into())),
+            Value::Int64(x) => Ok(SqlValue::Integer(x)),
+            Value::UInt8(x) => Ok(SqlValue::Integer(x.into())),
+            Value::UInt16(x) => Ok(SqlValue::Integer(x.into())),
+            Value::UInt32(x) => Ok(SqlValue::Integer(x.into())),
+            Value::UInt64(x) => {
+                if x > i64::MAX as u64 {
+                    Err(format_err!("row {}: {} doesn't fit in a SQLite INTEGER", i, x))
+                } else {
+                    Ok(SqlValue::Integer(x as i64))
+                }
+            },
+            Value::Float32(x) => Ok(SqlValue::Real(x.into())),
+            Value::Float64(x) => Ok(SqlValue::Real(x)),
+            Value::Bool(x) => Ok(SqlValue::Integer(x.into())),
+            Value::Str(s) => Ok(SqlValue::Text(s)),
+            value => Err(format_err!("row {}: can't write {:?} to SQLite", i, value)),
+        })
+        .collect()
+}
+
+/// Runs `query` and reads the result into a DataFrame, with dtypes from
+/// the columns' declared types or values (see the module docs).
+pub fn read_sql(conn: &Connection, query: &str) -> Result<DataFrame, Error> {
+    let mut stmt = conn.prepare(query)?;
+    let columns: Vec<(String, Option<String>)> = stmt.columns().iter()
+        .map(|col| (col.name().to_owned(), col.decl_type().map(|ty| ty.to_uppercase())))
+        .collect();
+
+    let mut values: Vec<Vec<SqlValue>> = vec![Vec::new(); columns.len()];
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        for (j, col) in values.iter_mut().enumerate() {
+            col.push(row.get(j)?);
+        }
+    }
+
+    let mut df = DataFrame::new();
+    for ((name, decl_type), col) in columns.into_iter().zip(values) {
+        let array = read_column(decl_type.as_ref().map(|ty| ty.trim()), col)
+            .map_err(|err| format_err!("column {}: {}", name, err))?;
+        df.add_col(name, array);
+    }
+    Ok(df)
+}
+
+fn read_column(decl_type: Option<&str>, values: Vec<SqlValue>) -> Result<Array, Error> {
+    let decl_type = decl_type.unwrap_or("");
+    let dtype = match decl_type {
+        "BOOLEAN" | "BOOL" => "Bool",
+        "DATE" => "Date",
+        "DATETIME" | "TIMESTAMP" => "Datetime",
+        _ if ["DECIMAL_TEXT(", "DECIMAL(", "NUMERIC("].iter().any(|prefix| decl_type.starts_with(prefix)) => {
+            // parse_decimal_dtype wants Decimal(p,s)
+            let args = &decl_type[decl_type.find('(').unwrap_or(0)..];
+            let (precision, scale) = parse_decimal_dtype(&format!("Decimal{}", args))
+                .ok_or_else(|| format_err!("can't read declared type {}", decl_type))?;
+            return read_decimal(values, precision, scale);
+        },
+        // SQLite's affinity rules, in its order
+        _ if decl_type.contains("INT") => "Int64",
+        _ if decl_type.contains("CHAR") || decl_type.contains("CLOB") || decl_type.contains("TEXT") => "Str",
+        _ if decl_type.contains("REAL") || decl_type.contains("FLOA") || decl_type.contains("DOUB") => "Float64",
+        _ => infer_dtype(&values)?,
+    };
+
+    if dtype == "Date" || dtype == "Datetime" {
+        return read_temporal(values, dtype);
+    }
+    let mut array = Array::new(dtype)?;
+    for (i, value) in values.into_iter().enumerate() {
+        let value = match (dtype, value) {
+            (_, SqlValue::Null) => Value::Null,
+            ("Int64", SqlValue::Integer(x)) => Value::Int64(x),
+            ("Float64", SqlValue::Integer(x)) => Value::Float64(x as f64),
+            ("Float64", SqlValue::Real(x)) => Value::Float64(x),
+            ("Bool", SqlValue::Integer(x)) => Value::Bool(x != 0),
+            ("Str", SqlValue::Text(s)) => Value::Str(s),
+            ("Str", SqlValue::Integer(x)) => Value::Str(x.to_string()),
+            ("Str", SqlValue::Real(x)) => Value::Str(x.to_string()),
+            (_, value) => return Err(format_err!("row {}: can't read {:?} as {}", i, value, dtype)),
+        };
+        array.push_value(value)?;
+    }
+    Ok(array)
+}
+
+// Int64 for all integers, Float64 for all numbers, else Str
+fn infer_dtype(values: &[SqlValue]) -> Result<&'static str, Error> {
+    let (mut ints, mut reals, mut texts) = (false, false, false);
+    for value in values {
+        match *value {
+            SqlValue::Null => (),
+            SqlValue::Integer(_) => ints = true,
+            SqlValue::Real(_) => reals = true,
+            SqlValue::Text(_) => texts = true,
+            SqlValue::Blob(_) => return Err(format_err!("BLOB isn't supported")),
+        }
+    }
+    Ok(match (ints, reals, texts) {
+        (true, false, false) => "Int64",
+        (_, true, false) => "Float64",
+        _ => "Str",
+    })
+}
+
+fn read_temporal(values: Vec<SqlValue>, dtype: &str) -> Result<Array, Error> {
+    let strings = values.into_iter()
+        .enumerate()
+        .map(|(i, value)| match value {
+            SqlValue::Null => Ok(String::new()),
+            SqlValue::Text(s) => Ok(s),
+            value => Err(format_err!("row {}: can't read {:?} as {}", i, value, dtype)),
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    if strings.iter().all(|s| s.is_empty()) {
+        let mut array = Array::new(dtype)?;
+        for _ in 0..strings.len() {
+            array.push_null();
+        }
+        return Ok(array);
+    }
+    // a DATE column is read as Datetime if it holds datetimes, but not
+    // the other way round
+    let array = infer_temporal(&strings).filter(|array| dtype == "Date" || array.dtype() == "Datetime");
+    array.ok_or_else(|| {
+        let i = strings.iter()
+            .position(|s| infer_temporal(::std::slice::from_ref(s)).is_none())
+            .unwrap_or(0);
+        format_err!("row {}: can't read {:?} as {}", i, strings[i], dtype)
+    })
+}
+
+fn read_decimal(values: Vec<SqlValue>, precision: u8, scale: u8) -> Result<Array, Error> {
+    let xs = values.into_iter()
+        .enumerate()
+        .map(|(i, value)| {
+            let s = match value {
+                SqlValue::Null => return Ok(None),
+                SqlValue::Integer(x) => x.to_string(),
+                // rounded to the scale, to drop binary float noise
+                SqlValue::Real(x) => format!("{:.*}", scale as usize, x),
+                SqlValue::Text(s) => s,
+                value => return Err(format_err!("row {}: can't read {:?} as a decimal", i, value)),
+            };
+            parse_decimal(&s, scale).map(Some).map_err(|err| format_err!("row {}: {}", i, err))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    Ok(Array::Decimal(DecimalData::new(ArrayData::from_options(xs), precision, scale)?))
+}
+
+impl DataFrame {
+    /// Runs `query` on a SQLite connection and reads the result. See
+    /// `io::sqlite` for how dtypes are picked.
+    pub fn read_sql(conn: &Connection, query: &str) -> Result<Self, Error> {
+        read_sql(conn, query)
+    }
+
+    /// Writes to `table` on a SQLite connection, in transactions of
+    /// 10,000 rows. Use `SqliteWriter` for other options.
+    pub fn write_sqlite(&self, conn: &mut Connection, table: &str, if_exists: IfExists) -> Result<(), Error> {
+        SqliteWriter::new().if_exists(if_exists).write(self, conn, table)
+    }
+
+    /// Like `write_sqlite`, to the database file at `path`, which is
+    /// created if it doesn't exist.
+    pub fn write_sqlite_path<P: AsRef<Path>>(&self, path: P, table: &str, if_exists: IfExists) -> Result<(), Error> {
+        SqliteWriter::new().if_exists(if_exists).write_path(self, path, table)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::{NaiveDate, NaiveDateTime};
+    use value::column_values;
+
+    fn frame() -> DataFrame {
+        let mut df = DataFrame::new();
+        df.add_col("id".to_owned(), Array::UInt32(ArrayData::from_vec(vec![1, 2, 3])));
+        df.add_col("score".to_owned(), Array::Float64(ArrayData::from_options(vec![Some(1.5), None, Some(-2.0)])));
+        df.add_col("active".to_owned(), Array::Bool(ArrayData::from_vec(vec![true, false, true])));
+        df.add_col("name".to_owned(), Array::Str(ArrayData::from_options(vec![
+            Some("Ann".to_owned()), Some("O'Brien".to_owned()), None,
+        ])));
+        df.add_col("day".to_owned(), Array::Date(ArrayData::from_options(vec![
+            NaiveDate::from_ymd_opt(2018, 3, 15), None, NaiveDate::from_ymd_opt(2018, 3, 17),
+        ])));
+        let amount = Array::Str(ArrayData::from_vec(vec!["12.50".to_owned(), "0.10".to_owned(), "-3".to_owned()]));
+        df.add_col("amount".to_owned(), amount.parse_decimal(10, 2).unwrap());
+        df
+    }
+
+    #[test]
+    fn test_sqlite_round_trip() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let df = frame();
+        SqliteWriter::new().batch_rows(2).write(&df, &mut conn, "people").unwrap();
+
+        let read = DataFrame::read_sql(&conn, "SELECT * FROM people").unwrap();
+        assert_eq!(read.columns.keys().collect::<Vec<_>>(), df.columns.keys().collect::<Vec<_>>());
+        let dtypes: Vec<String> = read.columns.values().map(|col| col.dtype()).collect();
+        assert_eq!(dtypes, vec!["Int64", "Float64", "Bool", "Str", "Date", "Decimal(10,2)"]);
+        assert_eq!(column_values(&read, "id"), vec![Value::Int64(1), Value::Int64(2), Value::Int64(3)]);
+        for name in &["score", "active", "name", "day", "amount"] {
+            assert_eq!(column_values(&read, name), column_values(&df, name));
+        }
+
+        // expressions have no declared type, so go by value
+        let read = DataFrame::read_sql(&conn, "SELECT id * 2 AS twice, score / 2 AS half, upper(name) AS loud FROM people").unwrap();
+        let dtypes: Vec<String> = read.columns.values().map(|col| col.dtype()).collect();
+        assert_eq!(dtypes, vec!["Int64", "Float64", "Str"]);
+    }
+
+    #[test]
+    fn test_sqlite_decimals_keep_every_digit() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let mut df = DataFrame::new();
+        let big = Array::Str(ArrayData::from_vec(vec!["123456789012345678.91".to_owned(), "-0.01".to_owned()]));
+        df.add_col("big".to_owned(), big.parse_decimal(20, 2).unwrap());
+        df.write_sqlite(&mut conn, "t", IfExists::Fail).unwrap();
+
+        let read = DataFrame::read_sql(&conn, "SELECT * FROM t").unwrap();
+        assert_eq!(read.get_col("big").unwrap().dtype(), "Decimal(20,2)");
+        assert_eq!(column_values(&read, "big"), vec![Value::Decimal(12345678901234567891, 2), Value::Decimal(-1, 2)]);
+
+        conn.execute_batch("CREATE TABLE n (x NUMERIC(5,2)); INSERT INTO n VALUES (1.5)").unwrap();
+        assert_eq!(column_values(&DataFrame::read_sql(&conn, "SELECT * FROM n").unwrap(), "x"), vec![Value::Decimal(150, 2)]);
+    }
+
+    #[test]
+    fn test_write_sqlite_if_exists() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let df = frame();
+        let count = |conn: &Connection| conn.query_row("SELECT count(*) FROM people", [], |row| row.get::<_, i64>(0)).unwrap();
+
+        df.write_sqlite(&mut conn, "people", IfExists::Fail).unwrap();
+        assert_eq!(df.write_sqlite(&mut conn, "people", IfExists::Fail).unwrap_err().to_string(),
+            "table people already exists");
+        df.write_sqlite(&mut conn, "people", IfExists::Append).unwrap();
+        assert_eq!(count(&conn), 6);
+        df.slice(0, 1).unwrap().write_sqlite(&mut conn, "people", IfExists::Replace).unwrap();
+        assert_eq!(count(&conn), 1);
+
+        // table names are case-insensitive in SQLite
+        assert!(df.write_sqlite(&mut conn, "PEOPLE", IfExists::Fail).is_err());
+        df.write_sqlite(&mut conn, "People", IfExists::Append).unwrap();
+        assert_eq!(count(&conn), 4);
+
+        let mut ragged = df.clone();
+        ragged.add_col("short".to_owned(), Array::Int64(ArrayData::from_vec(vec![1, 2])));
+        assert!(SqliteWriter::new().batch_rows(2).write(&ragged, &mut conn, "ragged").is_err());
+        let tables: i64 = conn.query_row("SELECT count(*) FROM sqlite_master WHERE name = 'ragged'", [], |row| row.get(0)).unwrap();
+        assert_eq!(tables, 0);
+
+        let mut durations = DataFrame::new();
+        durations.add_col("wait".to_owned(), Array::Duration(ArrayData::from_vec(vec![])));
+        assert!(durations.write_sqlite(&mut conn, "waits", IfExists::Fail).is_err());
+    }
+
+    #[test]
+    fn test_read_sql_datetimes() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("
+            CREATE TABLE events (at TIMESTAMP, note VARCHAR(10), n BIGINT);
+            INSERT INTO events VALUES ('2018-03-15 10:00:00', 'start', 1), (NULL, NULL, NULL);
+        ").unwrap();
+        let df = DataFrame::read_sql(&conn, "SELECT * FROM events").unwrap();
+        assert_eq!(column_values(&df, "at"), vec![
+            Value::Datetime(NaiveDateTime::parse_from_str("2018-03-15 10:00:00", "%Y-%m-%d %H:%M:%S").unwrap()),
+            Value::Null,
+        ]);
+        assert_eq!(column_values(&df, "note"), vec![Value::Str("start".to_owned()), Value::Null]);
+        assert_eq!(column_values(&df, "n"), vec![Value::Int64(1), Value::Null]);
+
+        conn.execute_batch("INSERT INTO events VALUES ('yesterday', NULL, NULL)").unwrap();
+        assert!(DataFrame::read_sql(&conn, "SELECT * FROM events").is_err());
+    }
+}
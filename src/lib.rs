@@ -1,10 +1,55 @@
+#[cfg(feature = "arrow")]
+extern crate arrow_array;
+#[cfg(feature = "arrow")]
+extern crate arrow_buffer;
+#[cfg(feature = "ipc")]
+extern crate arrow_ipc;
+#[cfg(feature = "arrow")]
+extern crate arrow_schema;
+#[cfg(feature = "parquet")]
+extern crate bytes;
+#[cfg(feature = "compression")]
+extern crate bzip2;
+extern crate chrono;
+extern crate csv;
+extern crate encoding_rs;
 #[macro_use]
 extern crate failure;
-#[macro_use]
+#[cfg(feature = "compression")]
+extern crate flate2;
+#[cfg_attr(test, macro_use)]
 extern crate indexmap;
+#[cfg(feature = "parquet")]
+extern crate parquet;
 extern crate rayon;
+#[cfg(feature = "sqlite")]
+extern crate rusqlite;
+#[macro_use]
+extern crate serde;
+extern crate serde_json;
+#[cfg(feature = "compression")]
+extern crate zstd;
+#[cfg(test)]
+#[macro_use]
+extern crate serde_derive;
 
+pub mod bitmap;
+pub mod categorical;
 pub mod dataframe;
+pub mod decimal;
+pub mod groupby;
+pub mod io;
+pub mod missing;
+pub mod rank;
+pub mod records;
+pub mod row;
+pub mod sql;
+pub mod stats;
+pub mod temporal;
+pub mod typed;
+pub mod unique;
+pub mod value;
+pub mod window;
 
 #[cfg(test)]
 mod tests {
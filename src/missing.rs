@@ -0,0 +1,243 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use failure::Error;
+
+use dataframe::{Array, ArrayData, DataFrame};
+
+/// Replaces nulls with a value of the array's own type, like
+/// `DataType<T>` is for `apply`.
+pub trait FillNull<T> {
+    /// Errors if `T` doesn't match the array's dtype.
+    fn fill_null(&self, value: T) -> Result<Array, Error>;
+}
+
+macro_rules! impl_fill_null_for_array {
+    ($t:ty, $p: path) => {
+        impl FillNull<$t> for Array {
+            fn fill_null(&self, value: $t) -> Result<Array, Error> {
+                match *self {
+                    $p(ref array_data) => Ok($p(array_data.fill_null(value))),
+                    _ => Err(format_err!("fill value type mismatch, array is {}", self.dtype())),
+                }
+            }
+        }
+    };
+}
+
+impl_fill_null_for_array!(i8, Array::Int8);
+impl_fill_null_for_array!(i16, Array::Int16);
+impl_fill_null_for_array!(i32, Array::Int32);
+impl_fill_null_for_array!(i64, Array::Int64);
+impl_fill_null_for_array!(u8, Array::UInt8);
+impl_fill_null_for_array!(u16, Array::UInt16);
+impl_fill_null_for_array!(u32, Array::UInt32);
+impl_fill_null_for_array!(u64, Array::UInt64);
+impl_fill_null_for_array!(f32, Array::Float32);
+impl_fill_null_for_array!(f64, Array::Float64);
+impl_fill_null_for_array!(bool, Array::Bool);
+impl_fill_null_for_array!(NaiveDate, Array::Date);
+impl_fill_null_for_array!(NaiveDateTime, Array::Datetime);
+impl_fill_null_for_array!(Duration, Array::Duration);
+
+/// For Decimal, `value` is unscaled and must fit in the precision.
+impl FillNull<i128> for Array {
+    fn fill_null(&self, value: i128) -> Result<Array, Error> {
+        match *self {
+            Array::Decimal(ref dec_data) => Ok(Array::Decimal(dec_data.fill_null(value)?)),
+            _ => Err(format_err!("fill value type mismatch, array is {}", self.dtype())),
+        }
+    }
+}
+
+impl FillNull<String> for Array {
+    fn fill_null(&self, value: String) -> Result<Array, Error> {
+        match *self {
+            Array::Str(ref array_data) => Ok(Array::Str(array_data.fill_null(value))),
+            Array::Categorical(ref cat_data) => Ok(Array::Categorical(cat_data.fill_null(value))),
+            _ => Err(format_err!("fill value type mismatch, array is {}", self.dtype())),
+        }
+    }
+}
+
+/// Direction for `Array::fill_null_strategy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FillStrategy {
+    /// Copy the last value before each null
+    Forward,
+    /// Copy the next value after each null
+    Backward,
+}
+
+/// Which rows `DataFrame::drop_nulls` drops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum How {
+    /// Rows with a null in any of the columns
+    Any,
+    /// Rows that are null in all of the columns
+    All,
+}
+
+// Source row for each row when filling forward through `indices`:
+// the row itself if valid, else the last valid row at most `limit`
+// rows back.
+fn fill_sources<I>(array: &Array, indices: I, limit: Option<usize>) -> Vec<Option<usize>>
+    where I: Iterator<Item = usize>
+{
+    let mut sources = vec![None; array.len()];
+    let mut last_valid = None;
+    let mut gap = 0;
+    for i in indices {
+        if !array.is_null(i) {
+            last_valid = Some(i);
+            gap = 0;
+            sources[i] = Some(i);
+        } else {
+            gap += 1;
+            if limit.is_none_or(|limit| gap <= limit) {
+                sources[i] = last_valid;
+            }
+        }
+    }
+    sources
+}
+
+impl Array {
+    /// Fills nulls from the nearest valid row before (Forward) or after
+    /// (Backward), filling at most `limit` nulls in a row. Works on
+    /// any dtype. Nulls with nothing to copy from stay null.
+    pub fn fill_null_strategy(&self, strategy: FillStrategy, limit: Option<usize>) -> Array {
+        let sources = match strategy {
+            FillStrategy::Forward => fill_sources(self, 0..self.len(), limit),
+            FillStrategy::Backward => fill_sources(self, (0..self.len()).rev(), limit),
+        };
+        self.take_opt(&sources)
+    }
+
+    /// Fills nulls on a straight line between the valid values either
+    /// side of them, as Float64. Leading and trailing nulls stay null.
+    /// Errors if the array isn't numeric.
+    pub fn interpolate(&self) -> Result<Array, Error> {
+        let mut values = self.numeric_values("interpolate")?;
+        let valid: Vec<usize> = (0..values.len()).filter(|&i| values[i].is_some()).collect();
+        for pair in valid.windows(2) {
+            let (start, end) = (pair[0], pair[1]);
+            if let (Some(a), Some(b)) = (values[start], values[end]) {
+                for (i, value) in values.iter_mut().enumerate().take(end).skip(start + 1) {
+                    *value = Some(a + (b - a) * (i - start) as f64 / (end - start) as f64);
+                }
+            }
+        }
+        Ok(Array::Float64(ArrayData::from_options(values)))
+    }
+}
+
+impl DataFrame {
+    /// Drops rows with nulls in the `subset` columns (every column, if
+    /// `subset` is empty): any null, or only all nulls, per `how`.
+    /// Errors if any columns have different lengths.
+    pub fn drop_nulls(&self, subset: &[&str], how: How) -> Result<Self, Error> {
+        self.columns_of(&[])?;
+        let columns = self.columns_of(subset)?;
+        let len = columns.first().map_or(0, |c| c.len());
+        let indices: Vec<usize> = (0..len)
+            .filter(|&i| match how {
+                How::Any => columns.iter().all(|c| !c.is_null(i)),
+                How::All => columns.iter().any(|c| !c.is_null(i)),
+            })
+            .collect();
+        Ok(self.take(&indices))
+    }
+
+    /// First non-null value across `cols`, row by row. Errors unless
+    /// the columns have the same dtype and length.
+    pub fn coalesce(&self, cols: &[&str]) -> Result<Array, Error> {
+        if cols.is_empty() {
+            return Err(format_err!("coalesce needs at least one column"));
+        }
+        let columns = self.columns_of(cols)?;
+        let len = columns[0].len();
+        // rows of column c start at c * len in the concatenated array
+        let stacked = Array::concat(&columns)?;
+        let indices: Vec<Option<usize>> = (0..len)
+            .map(|i| columns.iter().position(|c| !c.is_null(i)).map(|c| c * len + i))
+            .collect();
+        Ok(stacked.take_opt(&indices))
+    }
+
+    /// One row with the number of nulls in each column, as UInt64.
+    pub fn null_count(&self) -> Self {
+        let mut df = DataFrame::new();
+        for (name, array) in &self.columns {
+            df.add_col(name.clone(), Array::UInt64(ArrayData::from_vec(vec![array.null_count() as u64])));
+        }
+        df
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dataframe::DataTypeIterator;
+    use decimal::DecimalData;
+
+    fn ints(array: &Array) -> Vec<Option<i64>> {
+        DataTypeIterator::<i64>::values(array).unwrap().map(|x| x.cloned()).collect()
+    }
+
+    fn int_array(xs: Vec<Option<i64>>) -> Array {
+        Array::Int64(ArrayData::from_options(xs))
+    }
+
+    #[test]
+    fn test_fill_null() {
+        let array = int_array(vec![None, Some(1), None, None, Some(4), None]);
+        assert_eq!(ints(&array.fill_null(0i64).unwrap()), vec![Some(0), Some(1), Some(0), Some(0), Some(4), Some(0)]);
+        assert!(array.fill_null(0.0f64).is_err());
+
+        assert_eq!(ints(&array.fill_null_strategy(FillStrategy::Forward, None)),
+            vec![None, Some(1), Some(1), Some(1), Some(4), Some(4)]);
+        assert_eq!(ints(&array.fill_null_strategy(FillStrategy::Forward, Some(1))),
+            vec![None, Some(1), Some(1), None, Some(4), Some(4)]);
+        assert_eq!(ints(&array.fill_null_strategy(FillStrategy::Backward, None)),
+            vec![Some(1), Some(1), Some(4), Some(4), Some(4), None]);
+
+        let mut names = Array::Str(ArrayData::from_vec(vec!["a".to_owned()])).to_categorical().unwrap();
+        names.push_null();
+        let filled = names.fill_null("b".to_owned()).unwrap();
+        assert_eq!(DataTypeIterator::<String>::values(&filled).unwrap().map(|x| x.cloned()).collect::<Vec<_>>(),
+            vec![Some("a".to_owned()), Some("b".to_owned())]);
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let array = int_array(vec![None, Some(1), None, None, Some(4), None]);
+        let filled = array.interpolate().unwrap();
+        assert_eq!(DataTypeIterator::<f64>::values(&filled).unwrap().map(|x| x.cloned()).collect::<Vec<_>>(),
+            vec![None, Some(1.0), Some(2.0), Some(3.0), Some(4.0), None]);
+
+        let prices = Array::Decimal(DecimalData::new(ArrayData::from_options(vec![Some(50), None, Some(150)]), 4, 2).unwrap());
+        let filled = prices.interpolate().unwrap();
+        assert_eq!(DataTypeIterator::<f64>::values(&filled).unwrap().map(|x| x.cloned()).collect::<Vec<_>>(),
+            vec![Some(0.5), Some(1.0), Some(1.5)]);
+    }
+
+    #[test]
+    fn test_drop_nulls_and_coalesce() {
+        let mut df = DataFrame::new();
+        df.add_col("a".to_owned(), int_array(vec![Some(1), None, None]));
+        df.add_col("b".to_owned(), int_array(vec![Some(10), Some(20), None]));
+
+        assert_eq!(ints(df.drop_nulls(&[], How::Any).unwrap().get_col("b").unwrap()), vec![Some(10)]);
+        assert_eq!(ints(df.drop_nulls(&[], How::All).unwrap().get_col("b").unwrap()), vec![Some(10), Some(20)]);
+        assert_eq!(ints(df.drop_nulls(&["b"], How::Any).unwrap().get_col("a").unwrap()), vec![Some(1), None]);
+        let mut ragged = df.clone();
+        ragged.add_col("short".to_owned(), int_array(vec![Some(1)]));
+        assert!(ragged.drop_nulls(&["a"], How::Any).is_err());
+
+        assert_eq!(ints(&df.coalesce(&["a", "b"]).unwrap()), vec![Some(1), Some(20), None]);
+        df.add_col("c".to_owned(), Array::Float64(ArrayData::from_vec(vec![1.0, 2.0, 3.0])));
+        assert!(df.coalesce(&["a", "c"]).is_err());
+
+        let counts = df.null_count();
+        assert_eq!(DataTypeIterator::<u64>::values(counts.get_col("a").unwrap()).unwrap().next(), Some(Some(&2)));
+    }
+}
@@ -0,0 +1,159 @@
+use failure::Error;
+
+use dataframe::{Array, ArrayData, DataFrame};
+use groupby::{key_values, GroupBy};
+
+/// How `Array::rank` numbers tied values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RankMethod {
+    /// Mean of the ranks the ties would have had (Float64)
+    Average,
+    /// Lowest rank of the ties
+    Min,
+    /// Highest rank of the ties
+    Max,
+    /// Like Min, but the next value ranks one higher rather than
+    /// skipping past the ties
+    Dense,
+    /// Ties ranked in row order, so every rank is distinct
+    Ordinal,
+}
+
+// Non-null rows in sorted order, split into runs of equal values.
+// Each run keeps row order, so ties stay stable when descending.
+fn tie_runs(array: &Array, descending: bool) -> Vec<Vec<usize>> {
+    let keys = key_values(array);
+    let mut runs: Vec<Vec<usize>> = vec![];
+    for i in array.sort_indices() {
+        if array.is_null(i) {
+            // nulls sort last
+            break;
+        }
+        match runs.last_mut() {
+            Some(ref mut run) if keys[run[0]] == keys[i] => run.push(i),
+            _ => runs.push(vec![i]),
+        }
+    }
+    if descending {
+        runs.reverse();
+    }
+    runs
+}
+
+fn rank_runs(runs: &[Vec<usize>], len: usize, method: RankMethod) -> Array {
+    let mut ranks = vec![None; len];
+    let mut position = 0;
+    for (dense, run) in runs.iter().enumerate() {
+        for (k, &i) in run.iter().enumerate() {
+            ranks[i] = Some(match method {
+                RankMethod::Average => position as f64 + (run.len() + 1) as f64 / 2.0,
+                RankMethod::Min => (position + 1) as f64,
+                RankMethod::Max => (position + run.len()) as f64,
+                RankMethod::Dense => (dense + 1) as f64,
+                RankMethod::Ordinal => (position + k + 1) as f64,
+            });
+        }
+        position += run.len();
+    }
+
+    if method == RankMethod::Average {
+        Array::Float64(ArrayData::from_options(ranks))
+    } else {
+        let ranks = ranks.into_iter().map(|r| r.map(|r| r as u64)).collect();
+        Array::UInt64(ArrayData::from_options(ranks))
+    }
+}
+
+// 1 to len in sorted order, with nulls numbered last in row order.
+fn row_numbers(array: &Array, descending: bool) -> Array {
+    let mut numbers = vec![0; array.len()];
+    let sorted = tie_runs(array, descending).into_iter()
+        .flat_map(|run| run.into_iter())
+        .chain((0..array.len()).filter(|&i| array.is_null(i)));
+    for (number, i) in sorted.enumerate() {
+        numbers[i] = number as u64 + 1;
+    }
+    Array::UInt64(ArrayData::from_vec(numbers))
+}
+
+impl Array {
+    /// Ranks from 1, smallest value first. Nulls get a null rank.
+    /// Average gives Float64, the other methods UInt64.
+    pub fn rank(&self, method: RankMethod) -> Array {
+        rank_runs(&tie_runs(self, false), self.len(), method)
+    }
+
+    /// Like `rank`, but largest value first.
+    pub fn rank_desc(&self, method: RankMethod) -> Array {
+        rank_runs(&tie_runs(self, true), self.len(), method)
+    }
+}
+
+impl DataFrame {
+    /// 1, 2, 3... in the current row order, as UInt64.
+    pub fn row_number(&self) -> Array {
+        Array::UInt64(ArrayData::from_vec((1..=self.n_rows() as u64).collect()))
+    }
+}
+
+impl<'a> GroupBy<'a> {
+    /// `Array::rank` of `order_by` within each group.
+    pub fn rank(&self, order_by: &str, method: RankMethod) -> Result<Array, Error> {
+        self.transform(order_by, |array| Ok(array.rank(method)))
+    }
+
+    /// `Array::rank_desc` of `order_by` within each group.
+    pub fn rank_desc(&self, order_by: &str, method: RankMethod) -> Result<Array, Error> {
+        self.transform(order_by, |array| Ok(array.rank_desc(method)))
+    }
+
+    /// Numbers the rows of each group from 1, ordered by `order_by`.
+    /// Ties keep row order and rows with a null `order_by` come last.
+    /// UInt64.
+    pub fn row_number(&self, order_by: &str) -> Result<Array, Error> {
+        self.transform(order_by, |array| Ok(row_numbers(array, false)))
+    }
+
+    /// Like `row_number`, but largest `order_by` first. With
+    /// `row_number_desc(..) == 1` this picks the latest row per group.
+    pub fn row_number_desc(&self, order_by: &str) -> Result<Array, Error> {
+        self.transform(order_by, |array| Ok(row_numbers(array, true)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dataframe::DataTypeIterator;
+
+    fn uints(array: &Array) -> Vec<Option<u64>> {
+        DataTypeIterator::<u64>::values(array).unwrap().map(|x| x.cloned()).collect()
+    }
+
+    #[test]
+    fn test_rank_methods() {
+        let array = Array::Int32(ArrayData::from_options(vec![Some(20), Some(10), None, Some(20), Some(30)]));
+        let average = array.rank(RankMethod::Average);
+        assert_eq!(DataTypeIterator::<f64>::values(&average).unwrap().map(|x| x.cloned()).collect::<Vec<_>>(),
+            vec![Some(2.5), Some(1.0), None, Some(2.5), Some(4.0)]);
+        assert_eq!(uints(&array.rank(RankMethod::Min)), vec![Some(2), Some(1), None, Some(2), Some(4)]);
+        assert_eq!(uints(&array.rank(RankMethod::Max)), vec![Some(3), Some(1), None, Some(3), Some(4)]);
+        assert_eq!(uints(&array.rank(RankMethod::Dense)), vec![Some(2), Some(1), None, Some(2), Some(3)]);
+        assert_eq!(uints(&array.rank(RankMethod::Ordinal)), vec![Some(2), Some(1), None, Some(3), Some(4)]);
+        assert_eq!(uints(&array.rank_desc(RankMethod::Ordinal)), vec![Some(2), Some(4), None, Some(3), Some(1)]);
+    }
+
+    #[test]
+    fn test_row_number() {
+        let mut df = DataFrame::new();
+        let keys = ["a", "b", "a", "a", "b"].iter().map(|s| s.to_string()).collect();
+        df.add_col("key".to_owned(), Array::Str(ArrayData::from_vec(keys)));
+        df.add_col("version".to_owned(), Array::Int64(ArrayData::from_options(vec![Some(1), Some(5), None, Some(3), Some(2)])));
+        assert_eq!(uints(&df.row_number()), vec![Some(1), Some(2), Some(3), Some(4), Some(5)]);
+
+        let gb = df.group_by(&["key"]).unwrap();
+        assert_eq!(uints(&gb.row_number("version").unwrap()), vec![Some(1), Some(2), Some(3), Some(2), Some(1)]);
+        assert_eq!(uints(&gb.row_number_desc("version").unwrap()), vec![Some(2), Some(1), Some(3), Some(1), Some(2)]);
+        assert_eq!(uints(&gb.rank("version", RankMethod::Min).unwrap()), vec![Some(1), Some(2), None, Some(2), Some(1)]);
+    }
+}
@@ -0,0 +1,500 @@
+use failure::Error;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, Unexpected, Visitor};
+use serde::ser::{self, Impossible, Serialize, SerializeStruct};
+use std::fmt;
+use std::vec;
+
+use dataframe::{Array, DataFrame};
+use decimal::format_decimal;
+use value::Value;
+
+// serde needs its own error type on both sides; it's turned into a
+// failure::Error (with the record or row number) at the edges.
+#[derive(Debug)]
+struct RecordError(String);
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl ::std::error::Error for RecordError {}
+
+impl ser::Error for RecordError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        RecordError(msg.to_string())
+    }
+}
+
+impl de::Error for RecordError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        RecordError(msg.to_string())
+    }
+}
+
+fn unsupported(what: &str) -> RecordError {
+    RecordError(format!("unsupported type {}, fields must be scalars or Options of scalars", what))
+}
+
+// Serializes a single field into a Value.
+struct ValueSerializer;
+
+macro_rules! serialize_as {
+    ($method:ident, $t:ty, $variant:path) => {
+        fn $method(self, v: $t) -> Result<Value, RecordError> {
+            Ok($variant(v))
+        }
+    };
+}
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = RecordError;
+    type SerializeSeq = Impossible<Value, RecordError>;
+    type SerializeTuple = Impossible<Value, RecordError>;
+    type SerializeTupleStruct = Impossible<Value, RecordError>;
+    type SerializeTupleVariant = Impossible<Value, RecordError>;
+    type SerializeMap = Impossible<Value, RecordError>;
+    type SerializeStruct = Impossible<Value, RecordError>;
+    type SerializeStructVariant = Impossible<Value, RecordError>;
+
+    serialize_as!(serialize_bool, bool, Value::Bool);
+    serialize_as!(serialize_i8, i8, Value::Int8);
+    serialize_as!(serialize_i16, i16, Value::Int16);
+    serialize_as!(serialize_i32, i32, Value::Int32);
+    serialize_as!(serialize_i64, i64, Value::Int64);
+    serialize_as!(serialize_u8, u8, Value::UInt8);
+    serialize_as!(serialize_u16, u16, Value::UInt16);
+    serialize_as!(serialize_u32, u32, Value::UInt32);
+    serialize_as!(serialize_u64, u64, Value::UInt64);
+    serialize_as!(serialize_f32, f32, Value::Float32);
+    serialize_as!(serialize_f64, f64, Value::Float64);
+
+    fn serialize_char(self, v: char) -> Result<Value, RecordError> {
+        Ok(Value::Str(v.to_string()))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, RecordError> {
+        Ok(Value::Str(v.to_owned()))
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Value, RecordError> {
+        Err(unsupported("bytes"))
+    }
+
+    fn serialize_none(self) -> Result<Value, RecordError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<Value, RecordError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, RecordError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, RecordError> {
+        Ok(Value::Null)
+    }
+
+    // Fieldless enum variants are stored by name
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<Value, RecordError> {
+        Ok(Value::Str(variant.to_owned()))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, value: &T) -> Result<Value, RecordError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, name: &'static str, _index: u32, _variant: &'static str, _value: &T)
+        -> Result<Value, RecordError>
+    {
+        Err(unsupported(name))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, RecordError> {
+        Err(unsupported("sequence"))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, RecordError> {
+        Err(unsupported("tuple"))
+    }
+
+    fn serialize_tuple_struct(self, name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct, RecordError> {
+        Err(unsupported(name))
+    }
+
+    fn serialize_tuple_variant(self, name: &'static str, _index: u32, _variant: &'static str, _len: usize)
+        -> Result<Self::SerializeTupleVariant, RecordError>
+    {
+        Err(unsupported(name))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, RecordError> {
+        Err(unsupported("map"))
+    }
+
+    fn serialize_struct(self, name: &'static str, _len: usize) -> Result<Self::SerializeStruct, RecordError> {
+        Err(unsupported(name))
+    }
+
+    fn serialize_struct_variant(self, name: &'static str, _index: u32, _variant: &'static str, _len: usize)
+        -> Result<Self::SerializeStructVariant, RecordError>
+    {
+        Err(unsupported(name))
+    }
+}
+
+// Serializes a struct into its field names and values. Anything else
+// is an error.
+struct RecordSerializer;
+
+struct RecordFields(Vec<(&'static str, Value)>);
+
+impl SerializeStruct for RecordFields {
+    type Ok = Vec<(&'static str, Value)>;
+    type Error = RecordError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<(), RecordError> {
+        let value = value.serialize(ValueSerializer)
+            .map_err(|err| RecordError(format!("field {}: {}", key, err)))?;
+        self.0.push((key, value));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, RecordError> {
+        Ok(self.0)
+    }
+}
+
+fn not_a_struct() -> RecordError {
+    RecordError("records must be structs".to_owned())
+}
+
+macro_rules! not_a_struct {
+    ($($method:ident($($arg:ty),*);)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<Self::Ok, RecordError> {
+                Err(not_a_struct())
+            }
+        )*
+    };
+}
+
+type Fields = Vec<(&'static str, Value)>;
+
+impl ser::Serializer for RecordSerializer {
+    type Ok = Fields;
+    type Error = RecordError;
+    type SerializeSeq = Impossible<Fields, RecordError>;
+    type SerializeTuple = Impossible<Fields, RecordError>;
+    type SerializeTupleStruct = Impossible<Fields, RecordError>;
+    type SerializeTupleVariant = Impossible<Fields, RecordError>;
+    type SerializeMap = Impossible<Fields, RecordError>;
+    type SerializeStruct = RecordFields;
+    type SerializeStructVariant = Impossible<Fields, RecordError>;
+
+    not_a_struct! {
+        serialize_bool(bool);
+        serialize_i8(i8);
+        serialize_i16(i16);
+        serialize_i32(i32);
+        serialize_i64(i64);
+        serialize_u8(u8);
+        serialize_u16(u16);
+        serialize_u32(u32);
+        serialize_u64(u64);
+        serialize_f32(f32);
+        serialize_f64(f64);
+        serialize_char(char);
+        serialize_str(&str);
+        serialize_bytes(&[u8]);
+        serialize_none();
+        serialize_unit();
+        serialize_unit_struct(&'static str);
+        serialize_unit_variant(&'static str, u32, &'static str);
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> Result<Fields, RecordError> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, value: &T) -> Result<Fields, RecordError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _name: &'static str, _index: u32, _variant: &'static str, _value: &T)
+        -> Result<Fields, RecordError>
+    {
+        Err(not_a_struct())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, RecordError> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, RecordError> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct, RecordError> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize)
+        -> Result<Self::SerializeTupleVariant, RecordError>
+    {
+        Err(not_a_struct())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, RecordError> {
+        Err(not_a_struct())
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<RecordFields, RecordError> {
+        Ok(RecordFields(Vec::with_capacity(len)))
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize)
+        -> Result<Self::SerializeStructVariant, RecordError>
+    {
+        Err(not_a_struct())
+    }
+}
+
+// Deserializes a single cell. Dates, times, durations and decimals
+// are handed over as strings.
+struct ValueDeserializer(Value);
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer {
+    type Error = RecordError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RecordError> {
+        match self.0 {
+            Value::Null => Err(de::Error::invalid_type(Unexpected::Other("null"), &visitor)),
+            Value::Int8(x) => visitor.visit_i8(x),
+            Value::Int16(x) => visitor.visit_i16(x),
+            Value::Int32(x) => visitor.visit_i32(x),
+            Value::Int64(x) => visitor.visit_i64(x),
+            Value::UInt8(x) => visitor.visit_u8(x),
+            Value::UInt16(x) => visitor.visit_u16(x),
+            Value::UInt32(x) => visitor.visit_u32(x),
+            Value::UInt64(x) => visitor.visit_u64(x),
+            Value::Float32(x) => visitor.visit_f32(x),
+            Value::Float64(x) => visitor.visit_f64(x),
+            Value::Bool(x) => visitor.visit_bool(x),
+            Value::Str(x) => visitor.visit_string(x),
+            Value::Date(x) => visitor.visit_string(x.to_string()),
+            Value::Datetime(x) => visitor.visit_string(x.format("%Y-%m-%dT%H:%M:%S%.f").to_string()),
+            Value::Duration(x) => visitor.visit_string(x.to_string()),
+            Value::Decimal(x, scale) => visitor.visit_string(format_decimal(x, scale)),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RecordError> {
+        match self.0 {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, RecordError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // Fieldless enum variants, stored by name
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V)
+        -> Result<V::Value, RecordError>
+    {
+        match self.0 {
+            Value::Str(variant) => visitor.visit_enum(variant.into_deserializer()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+// Deserializes a row as a map from column name to cell.
+struct RowDeserializer<'a> {
+    names: &'a [&'a str],
+    values: vec::IntoIter<Value>,
+}
+
+struct RowAccess<'a> {
+    names: ::std::slice::Iter<'a, &'a str>,
+    values: vec::IntoIter<Value>,
+    current: &'a str,
+}
+
+impl<'de, 'a> MapAccess<'de> for RowAccess<'a> {
+    type Error = RecordError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, RecordError> {
+        match self.names.next() {
+            Some(name) => {
+                self.current = name;
+                seed.deserialize(name.into_deserializer()).map(Some)
+            },
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, RecordError> {
+        let value = self.values.next().unwrap_or(Value::Null);
+        seed.deserialize(ValueDeserializer(value))
+            .map_err(|err| RecordError(format!("field {}: {}", self.current, err)))
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for RowDeserializer<'a> {
+    type Error = RecordError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, RecordError> {
+        visitor.visit_map(RowAccess {
+            names: self.names.iter(),
+            values: self.values,
+            current: "",
+        })
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl DataFrame {
+    /// One column per struct field, in field order, with the dtype of
+    /// the field's first non-null value (all-null fields become Str).
+    /// `Option` fields map `None` to null and fieldless enums are
+    /// stored by variant name.
+    ///
+    /// Errors, naming the field, on nested fields (structs, sequences,
+    /// maps) or values that don't match the column's dtype.
+    pub fn from_records<T: Serialize>(records: &[T]) -> Result<Self, Error> {
+        let mut names: Vec<&'static str> = vec![];
+        let mut columns: Vec<Vec<Value>> = vec![];
+        for (i, record) in records.iter().enumerate() {
+            let fields = record.serialize(RecordSerializer)
+                .map_err(|err| format_err!("record {}: {}", i, err))?;
+            if i == 0 {
+                names = fields.iter().map(|&(name, _)| name).collect();
+                columns = vec![Vec::with_capacity(records.len()); names.len()];
+            } else if fields.len() != names.len() || fields.iter().zip(&names).any(|(&(a, _), b)| a != *b) {
+                return Err(format_err!("record {} has different fields to record 0", i));
+            }
+            for (column, (_, value)) in columns.iter_mut().zip(fields) {
+                column.push(value);
+            }
+        }
+
+        let mut df = DataFrame::new();
+        for (name, values) in names.into_iter().zip(columns) {
+            let dtype = values.iter().filter_map(|v| v.dtype()).next().unwrap_or_else(|| "Str".to_owned());
+            let mut array = Array::new(&dtype)?;
+            for value in values {
+                array.push_value(value).map_err(|err| format_err!("field {}: {}", name, err))?;
+            }
+            df.add_col(name.to_owned(), array);
+        }
+        Ok(df)
+    }
+
+    /// Deserializes each row into a `T`, matching fields to columns by
+    /// name (extra columns are ignored). Nulls deserialize as `None`.
+    /// Date, Datetime, Duration and Decimal cells are given to serde as
+    /// strings.
+    ///
+    /// Errors, naming the row and field, on a missing column or a cell
+    /// that doesn't fit its field, and on columns of different lengths.
+    pub fn to_records<T: DeserializeOwned>(&self) -> Result<Vec<T>, Error> {
+        let names: Vec<&str> = self.columns.keys().map(|name| name.as_str()).collect();
+        self.rows()?
+            .enumerate()
+            .map(|(i, row)| {
+                T::deserialize(RowDeserializer {
+                    names: &names,
+                    values: row.into_iter(),
+                })
+                .map_err(|err| format_err!("row {}: {}", i, err))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dataframe::ArrayData;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    enum Kind {
+        City,
+        Town,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Place {
+        name: String,
+        population: Option<u32>,
+        area: f64,
+        kind: Kind,
+    }
+
+    fn places() -> Vec<Place> {
+        vec![
+            Place { name: "Springfield".to_owned(), population: Some(30_000), area: 12.5, kind: Kind::Town },
+            Place { name: "Metropolis".to_owned(), population: None, area: 340.0, kind: Kind::City },
+        ]
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let df = DataFrame::from_records(&places()).unwrap();
+        let dtypes: Vec<_> = df.columns.values().map(|a| a.dtype()).collect();
+        assert_eq!(dtypes, vec!["Str", "UInt32", "Float64", "Str"]);
+        assert_eq!(df.get(1, "population").unwrap(), Value::Null);
+
+        let back: Vec<Place> = df.to_records().unwrap();
+        assert_eq!(back, places());
+    }
+
+    #[test]
+    fn test_errors_name_the_field() {
+        #[derive(Serialize)]
+        struct Nested {
+            tags: Vec<String>,
+        }
+        let err = DataFrame::from_records(&[Nested { tags: vec![] }]).unwrap_err();
+        assert!(err.to_string().contains("tags"), "{}", err);
+
+        #[derive(Debug, Deserialize)]
+        struct WrongType {
+            #[allow(dead_code)]
+            area: bool,
+        }
+        let df = DataFrame::from_records(&places()).unwrap();
+        let err = df.to_records::<WrongType>().unwrap_err();
+        assert!(err.to_string().contains("field area"), "{}", err);
+
+        #[derive(Debug, Deserialize)]
+        struct NotNull {
+            #[allow(dead_code)]
+            population: u32,
+        }
+        let err = df.to_records::<NotNull>().unwrap_err();
+        assert!(err.to_string().contains("row 1: field population"), "{}", err);
+
+        let mut ragged = df.clone();
+        ragged.add_col("area".to_owned(), Array::Float64(ArrayData::from_vec(vec![1.0])));
+        assert!(ragged.to_records::<Place>().is_err());
+    }
+}
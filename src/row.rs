@@ -0,0 +1,166 @@
+//! Traits behind `#[derive(DataFrameRow)]` (in the `llamas2_derive`
+//! crate), which converts between `Vec<MyStruct>` and a DataFrame
+//! column by column, with each field's Array variant chosen at compile
+//! time through `Column`.
+
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+pub use failure::Error;
+
+use dataframe::{Array, ArrayData, DataFrame};
+use temporal::DatetimeData;
+
+/// Rows that can be turned into a DataFrame, one column per field.
+pub trait IntoDataFrame: Sized {
+    fn into_dataframe(rows: Vec<Self>) -> DataFrame;
+}
+
+/// Rows that can be read back out of a DataFrame, matching fields to
+/// columns by name.
+pub trait FromDataFrame: Sized {
+    fn from_dataframe(df: &DataFrame) -> Result<Vec<Self>, Error>;
+}
+
+/// A field type with a matching Array variant. `Option<T>` fields
+/// map `None` to null; plain fields error on reading a null.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be a DataFrameRow field",
+    note = "fields must be integers, floats, bool, String, NaiveDate, NaiveDateTime, Duration, or an Option of one"
+)]
+pub trait Column: Sized {
+    fn into_array(values: Vec<Self>) -> Array;
+    fn from_array(array: &Array) -> Result<Vec<Self>, Error>;
+}
+
+fn no_nulls<T: Clone + Send + Sync>(array_data: &ArrayData<T>) -> Result<Vec<T>, Error> {
+    if array_data.null_count() > 0 {
+        return Err(format_err!("has {} nulls, use an Option field", array_data.null_count()));
+    }
+    Ok(array_data.as_slice().to_vec())
+}
+
+fn with_nulls<T: Clone + Send + Sync>(array_data: &ArrayData<T>) -> Vec<Option<T>> {
+    array_data.iter().map(|x| x.cloned()).collect()
+}
+
+macro_rules! impl_column {
+    ($t:ty, $p:path) => {
+        impl Column for $t {
+            fn into_array(values: Vec<Self>) -> Array {
+                $p(ArrayData::from_vec(values))
+            }
+
+            fn from_array(array: &Array) -> Result<Vec<Self>, Error> {
+                match *array {
+                    $p(ref array_data) => no_nulls(array_data),
+                    _ => Err(format_err!("type mismatch, array is {}", array.dtype())),
+                }
+            }
+        }
+
+        impl Column for Option<$t> {
+            fn into_array(values: Vec<Self>) -> Array {
+                $p(ArrayData::from_options(values))
+            }
+
+            fn from_array(array: &Array) -> Result<Vec<Self>, Error> {
+                match *array {
+                    $p(ref array_data) => Ok(with_nulls(array_data)),
+                    _ => Err(format_err!("type mismatch, array is {}", array.dtype())),
+                }
+            }
+        }
+    };
+}
+
+impl_column!(i8, Array::Int8);
+impl_column!(i16, Array::Int16);
+impl_column!(i32, Array::Int32);
+impl_column!(i64, Array::Int64);
+impl_column!(u8, Array::UInt8);
+impl_column!(u16, Array::UInt16);
+impl_column!(u32, Array::UInt32);
+impl_column!(u64, Array::UInt64);
+impl_column!(f32, Array::Float32);
+impl_column!(f64, Array::Float64);
+impl_column!(bool, Array::Bool);
+impl_column!(NaiveDate, Array::Date);
+impl_column!(Duration, Array::Duration);
+
+// Str and Categorical both read as String, and Datetime is wrapped,
+// so these are written out.
+impl Column for String {
+    fn into_array(values: Vec<Self>) -> Array {
+        Array::Str(ArrayData::from_vec(values))
+    }
+
+    fn from_array(array: &Array) -> Result<Vec<Self>, Error> {
+        match *array {
+            Array::Str(ref array_data) => no_nulls(array_data),
+            Array::Categorical(ref cat_data) => no_nulls(&cat_data.decode()),
+            _ => Err(format_err!("type mismatch, array is {}", array.dtype())),
+        }
+    }
+}
+
+impl Column for Option<String> {
+    fn into_array(values: Vec<Self>) -> Array {
+        Array::Str(ArrayData::from_options(values))
+    }
+
+    fn from_array(array: &Array) -> Result<Vec<Self>, Error> {
+        match *array {
+            Array::Str(ref array_data) => Ok(with_nulls(array_data)),
+            Array::Categorical(ref cat_data) => Ok(with_nulls(&cat_data.decode())),
+            _ => Err(format_err!("type mismatch, array is {}", array.dtype())),
+        }
+    }
+}
+
+impl Column for NaiveDateTime {
+    fn into_array(values: Vec<Self>) -> Array {
+        Array::Datetime(DatetimeData::new(ArrayData::from_vec(values), None))
+    }
+
+    fn from_array(array: &Array) -> Result<Vec<Self>, Error> {
+        match *array {
+            Array::Datetime(ref dt_data) => no_nulls(dt_data.values()),
+            _ => Err(format_err!("type mismatch, array is {}", array.dtype())),
+        }
+    }
+}
+
+impl Column for Option<NaiveDateTime> {
+    fn into_array(values: Vec<Self>) -> Array {
+        Array::Datetime(DatetimeData::new(ArrayData::from_options(values), None))
+    }
+
+    fn from_array(array: &Array) -> Result<Vec<Self>, Error> {
+        match *array {
+            Array::Datetime(ref dt_data) => Ok(with_nulls(dt_data.values())),
+            _ => Err(format_err!("type mismatch, array is {}", array.dtype())),
+        }
+    }
+}
+
+/// Reads column `name` as `T`, for derived `FromDataFrame` impls.
+/// Errors, naming the column, if it's missing, isn't `len` rows, or
+/// doesn't fit `T`.
+pub fn column<T: Column>(df: &DataFrame, name: &str, len: usize) -> Result<Vec<T>, Error> {
+    let array = df.get_col(name).ok_or_else(|| format_err!("column {} not found", name))?;
+    if array.len() != len {
+        return Err(format_err!("column {} has len {}, expected {}", name, array.len(), len));
+    }
+    T::from_array(array).map_err(|err| format_err!("column {}: {}", name, err))
+}
+
+impl DataFrame {
+    /// Column-wise conversion from typed rows, see `IntoDataFrame`.
+    pub fn from_rows<T: IntoDataFrame>(rows: Vec<T>) -> Self {
+        T::into_dataframe(rows)
+    }
+
+    /// Column-wise conversion to typed rows, see `FromDataFrame`.
+    pub fn to_rows<T: FromDataFrame>(&self) -> Result<Vec<T>, Error> {
+        T::from_dataframe(self)
+    }
+}
@@ -0,0 +1,389 @@
+//! Generating SQL for DataFrames.
+
+use failure::Error;
+use std::io::Write;
+
+use dataframe::{Array, DataFrame};
+use io::array_to_strings;
+
+/// Database flavour of the SQL to generate, which decides identifier
+/// quoting, string escapes, literals for Bools and floats, and upsert
+/// syntax.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    Postgres,
+    MySql,
+    Sqlite,
+}
+
+impl Dialect {
+    /// Quotes an identifier, with backticks for MySQL and double quotes
+    /// otherwise, doubling any quote inside it.
+    pub fn quote_ident(self, name: &str) -> String {
+        match self {
+            Dialect::MySql => format!("`{}`", name.replace('`', "``")),
+            Dialect::Postgres | Dialect::Sqlite => quote_ident(name),
+        }
+    }
+
+    /// Quotes a string literal, doubling single quotes. MySQL also
+    /// treats backslash as an escape by default, so it's doubled too.
+    pub fn quote_str(self, s: &str) -> String {
+        let s = s.replace('\'', "''");
+        match self {
+            Dialect::MySql => format!("'{}'", s.replace('\\', "\\\\")),
+            Dialect::Postgres | Dialect::Sqlite => format!("'{}'", s),
+        }
+    }
+}
+
+/// Quotes an identifier with double quotes, doubling any inside it.
+pub fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Column type for an array, using standard SQL (PostgreSQL) names.
+/// Unsigned ints get the next larger signed type, since there are no
+/// unsigned types in standard SQL.
+pub fn sql_type(array: &Array) -> String {
+    match *array {
+        Array::Int8(_) | Array::Int16(_) | Array::UInt8(_) => "SMALLINT".to_owned(),
+        Array::Int32(_) | Array::UInt16(_) => "INTEGER".to_owned(),
+        Array::Int64(_) | Array::UInt32(_) => "BIGINT".to_owned(),
+        Array::UInt64(_) => "NUMERIC(20,0)".to_owned(),
+        Array::Float32(_) => "REAL".to_owned(),
+        Array::Float64(_) => "DOUBLE PRECISION".to_owned(),
+        Array::Bool(_) => "BOOLEAN".to_owned(),
+        Array::Str(_) | Array::Categorical(_) => "TEXT".to_owned(),
+        Array::Date(_) => "DATE".to_owned(),
+        Array::Datetime(ref dt_data) => {
+            if dt_data.offset().is_some() {
+                "TIMESTAMP WITH TIME ZONE".to_owned()
+            } else {
+                "TIMESTAMP".to_owned()
+            }
+        },
+        Array::Duration(_) => "INTERVAL".to_owned(),
+        Array::Decimal(ref dec_data) => format!("NUMERIC({},{})", dec_data.precision(), dec_data.scale()),
+    }
+}
+
+impl DataFrame {
+    /// CREATE TABLE statement with one column per DataFrame column,
+    /// typed with `sql_type`.
+    pub fn create_table_sql(&self, table: &str) -> String {
+        let cols: Vec<String> = self.columns.iter()
+            .map(|(name, array)| format!("    {} {}", quote_ident(name), sql_type(array)))
+            .collect();
+
+        format!("CREATE TABLE {} (\n{}\n);\n", quote_ident(table), cols.join(",\n"))
+    }
+
+    /// INSERT statements of up to `batch_size` rows each, one per line.
+    /// Nulls are NULL, numbers and Decimals are bare, and everything
+    /// else is a quoted string in the form written to csv (ISO 8601 for
+    /// dates, datetimes and durations). Bools are TRUE and FALSE, or 1
+    /// and 0 for SQLite.
+    ///
+    /// Errors on a `batch_size` of 0, columns of different lengths, or
+    /// NaN or infinite floats in MySQL or SQLite, which have no literal
+    /// for them. An empty DataFrame gives no statements.
+    pub fn to_sql_inserts(&self, table: &str, dialect: Dialect, batch_size: usize) -> Result<String, Error> {
+        self.inserts(table, dialect, batch_size, None)
+    }
+
+    /// Like `to_sql_inserts`, but rows whose `keys` already exist update
+    /// the other columns instead: ON CONFLICT for PostgreSQL and SQLite,
+    /// which needs a unique index on the keys, and ON DUPLICATE KEY for
+    /// MySQL, which goes by any unique index. Errors if a key column
+    /// isn't found or no keys are given.
+    pub fn to_sql_upserts(&self, table: &str, dialect: Dialect, batch_size: usize, keys: &[&str])
+        -> Result<String, Error>
+    {
+        if keys.is_empty() {
+            return Err(format_err!("upsert needs at least one key column"));
+        }
+        if let Some(key) = keys.iter().find(|key| !self.columns.contains_key(**key)) {
+            return Err(format_err!("key column {} not found", key));
+        }
+        self.inserts(table, dialect, batch_size, Some(keys))
+    }
+
+    fn inserts(&self, table: &str, dialect: Dialect, batch_size: usize, keys: Option<&[&str]>)
+        -> Result<String, Error>
+    {
+        if batch_size == 0 {
+            return Err(format_err!("batch_size must be at least 1"));
+        }
+        self.columns_of(&[])?;
+        let names: Vec<String> = self.columns.keys().map(|name| dialect.quote_ident(name)).collect();
+        let prefix = format!("INSERT INTO {} ({}) VALUES ", dialect.quote_ident(table), names.join(", "));
+        let suffix = match keys {
+            Some(keys) => upsert_clause(self, dialect, keys),
+            None => String::new(),
+        };
+
+        let cols = self.columns.iter()
+            .map(|(name, array)| {
+                let kind = LiteralKind::of(array);
+                array_to_strings(array).into_iter()
+                    .map(|s| sql_literal(dialect, kind, s)
+                        .map_err(|err| format_err!("column {}: {}", name, err)))
+                    .collect::<Result<Vec<_>, Error>>()
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let n_rows = self.n_rows();
+        let mut sql = String::new();
+        for batch_start in (0..n_rows).step_by(batch_size) {
+            let rows: Vec<String> = (batch_start..n_rows.min(batch_start + batch_size))
+                .map(|i| {
+                    let row: Vec<&str> = cols.iter().map(|col| col[i].as_str()).collect();
+                    format!("({})", row.join(", "))
+                })
+                .collect();
+            sql.push_str(&prefix);
+            sql.push_str(&rows.join(", "));
+            sql.push_str(&suffix);
+            sql.push_str(";\n");
+        }
+        Ok(sql)
+    }
+
+    /// Writes a psql script loading the DataFrame into `table` with
+    /// `COPY ... FROM STDIN`: the statement, the rows in text format
+    /// (see `write_pg_copy_data`), then the `\.` end marker.
+    pub fn write_pg_copy<W: Write>(&self, table: &str, mut wtr: W) -> Result<(), Error> {
+        self.columns_of(&[])?;
+        let names: Vec<String> = self.columns.keys().map(|name| quote_ident(name)).collect();
+        writeln!(wtr, "COPY {} ({}) FROM STDIN;", quote_ident(table), names.join(", "))?;
+        self.write_pg_copy_data(&mut wtr)?;
+        writeln!(wtr, "\\.")?;
+        Ok(())
+    }
+
+    /// Writes the rows in PostgreSQL's COPY text format, to send to a
+    /// `COPY ... FROM STDIN` through a client: tab separated, nulls as
+    /// `\N`, and backslash, tab, newline and carriage return escaped.
+    /// Errors if the columns have different lengths.
+    pub fn write_pg_copy_data<W: Write>(&self, mut wtr: W) -> Result<(), Error> {
+        self.columns_of(&[])?;
+        let cols: Vec<Vec<Option<String>>> = self.columns.values().map(array_to_strings).collect();
+        let mut line = String::new();
+        for i in 0..self.n_rows() {
+            line.clear();
+            for (j, col) in cols.iter().enumerate() {
+                if j > 0 {
+                    line.push('\t');
+                }
+                match col[i] {
+                    Some(ref s) => escape_copy_text(s, &mut line),
+                    None => line.push_str("\\N"),
+                }
+            }
+            line.push('\n');
+            wtr.write_all(line.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+// How a column's csv text becomes a SQL literal
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LiteralKind {
+    Number,
+    Float,
+    Bool,
+    Text,
+}
+
+impl LiteralKind {
+    fn of(array: &Array) -> Self {
+        match *array {
+            Array::Int8(_) | Array::Int16(_) | Array::Int32(_) | Array::Int64(_) |
+            Array::UInt8(_) | Array::UInt16(_) | Array::UInt32(_) | Array::UInt64(_) |
+            Array::Decimal(_) => LiteralKind::Number,
+            Array::Float32(_) | Array::Float64(_) => LiteralKind::Float,
+            Array::Bool(_) => LiteralKind::Bool,
+            _ => LiteralKind::Text,
+        }
+    }
+}
+
+fn sql_literal(dialect: Dialect, kind: LiteralKind, value: Option<String>) -> Result<String, Error> {
+    let value = match value {
+        Some(value) => value,
+        None => return Ok("NULL".to_owned()),
+    };
+    match kind {
+        LiteralKind::Number => Ok(value),
+        LiteralKind::Float => match value.as_str() {
+            // PostgreSQL reads these from strings; the others have no
+            // literal for them
+            "NaN" | "inf" | "-inf" => match dialect {
+                Dialect::Postgres => Ok(match value.as_str() {
+                    "NaN" => "'NaN'".to_owned(),
+                    "inf" => "'Infinity'".to_owned(),
+                    _ => "'-Infinity'".to_owned(),
+                }),
+                Dialect::MySql | Dialect::Sqlite => Err(format_err!("can't write {} in {:?}", value, dialect)),
+            },
+            _ => Ok(value),
+        },
+        LiteralKind::Bool => Ok(match (dialect, value == "true") {
+            (Dialect::Sqlite, true) => "1".to_owned(),
+            (Dialect::Sqlite, false) => "0".to_owned(),
+            (_, true) => "TRUE".to_owned(),
+            (_, false) => "FALSE".to_owned(),
+        }),
+        LiteralKind::Text => Ok(dialect.quote_str(&value)),
+    }
+}
+
+// ON CONFLICT or ON DUPLICATE KEY clause, setting the non-key columns
+// to the inserted values
+fn upsert_clause(df: &DataFrame, dialect: Dialect, keys: &[&str]) -> String {
+    let others: Vec<String> = df.columns.keys()
+        .filter(|name| !keys.contains(&name.as_str()))
+        .map(|name| dialect.quote_ident(name))
+        .collect();
+    match dialect {
+        Dialect::Postgres | Dialect::Sqlite => {
+            let keys: Vec<String> = keys.iter().map(|key| dialect.quote_ident(key)).collect();
+            if others.is_empty() {
+                format!(" ON CONFLICT ({}) DO NOTHING", keys.join(", "))
+            } else {
+                let sets: Vec<String> = others.iter().map(|col| format!("{} = excluded.{}", col, col)).collect();
+                format!(" ON CONFLICT ({}) DO UPDATE SET {}", keys.join(", "), sets.join(", "))
+            }
+        },
+        Dialect::MySql => {
+            // with only keys, a no-op update keeps the existing row
+            let sets: Vec<String> = if others.is_empty() {
+                let key = dialect.quote_ident(keys[0]);
+                vec![format!("{} = {}", key, key)]
+            } else {
+                others.iter().map(|col| format!("{} = VALUES({})", col, col)).collect()
+            };
+            format!(" ON DUPLICATE KEY UPDATE {}", sets.join(", "))
+        },
+    }
+}
+
+fn escape_copy_text(s: &str, out: &mut String) {
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dataframe::ArrayData;
+
+    fn sample() -> DataFrame {
+        let mut df = DataFrame::new();
+        df.add_col("id".to_owned(), Array::Int64(ArrayData::from_vec(vec![1, 2, 3])));
+        df.add_col("name".to_owned(), Array::Str(ArrayData::from_options(vec![
+            Some("O'Brien".to_owned()), None, Some("a\\b\tc".to_owned()),
+        ])));
+        df.add_col("score".to_owned(), Array::Float64(ArrayData::from_options(vec![Some(1.5), Some(2.0), None])));
+        df.add_col("active".to_owned(), Array::Bool(ArrayData::from_vec(vec![true, false, true])));
+        df
+    }
+
+    fn ragged() -> DataFrame {
+        let mut df = sample();
+        df.add_col("short".to_owned(), Array::Int64(ArrayData::from_vec(vec![1])));
+        df
+    }
+
+    #[test]
+    fn test_create_table_sql() {
+        let mut df = DataFrame::new();
+        df.add_col("id".to_owned(), Array::UInt32(ArrayData::from_vec(vec![1, 2])));
+        df.add_col("state \"name\"".to_owned(), Array::Str(ArrayData::from_vec(vec!["NY".to_owned(), "CA".to_owned()])));
+        let amount = Array::Str(ArrayData::from_vec(vec!["$1,234.50".to_owned(), "2".to_owned()]));
+        df.add_col("amount".to_owned(), amount.parse_decimal(12, 2).unwrap());
+
+        assert_eq!(df.create_table_sql("sales"), "\
+CREATE TABLE \"sales\" (
+    \"id\" BIGINT,
+    \"state \"\"name\"\"\" TEXT,
+    \"amount\" NUMERIC(12,2)
+);
+");
+    }
+
+    #[test]
+    fn test_to_sql_inserts() {
+        let df = sample();
+        assert_eq!(df.to_sql_inserts("people", Dialect::Postgres, 2).unwrap(), "\
+INSERT INTO \"people\" (\"id\", \"name\", \"score\", \"active\") VALUES \
+(1, 'O''Brien', 1.5, TRUE), (2, NULL, 2.0, FALSE);
+INSERT INTO \"people\" (\"id\", \"name\", \"score\", \"active\") VALUES (3, 'a\\b\tc', NULL, TRUE);
+");
+        assert_eq!(df.slice(2, 1).unwrap().to_sql_inserts("people", Dialect::MySql, 10).unwrap(), "\
+INSERT INTO `people` (`id`, `name`, `score`, `active`) VALUES (3, 'a\\\\b\tc', NULL, TRUE);
+");
+        assert_eq!(df.slice(0, 1).unwrap().to_sql_inserts("people", Dialect::Sqlite, 10).unwrap(), "\
+INSERT INTO \"people\" (\"id\", \"name\", \"score\", \"active\") VALUES (1, 'O''Brien', 1.5, 1);
+");
+        assert_eq!(df.slice(0, 0).unwrap().to_sql_inserts("people", Dialect::Postgres, 10).unwrap(), "");
+        assert!(df.to_sql_inserts("people", Dialect::Postgres, 0).is_err());
+
+        let mut nan = DataFrame::new();
+        nan.add_col("x".to_owned(), Array::Float64(ArrayData::from_vec(vec![f64::NAN])));
+        assert_eq!(nan.to_sql_inserts("t", Dialect::Postgres, 1).unwrap(), "INSERT INTO \"t\" (\"x\") VALUES ('NaN');\n");
+        assert_eq!(nan.to_sql_inserts("t", Dialect::MySql, 1).unwrap_err().to_string(), "column x: can't write NaN in MySql");
+
+        let err = ragged().to_sql_inserts("people", Dialect::Postgres, 10).unwrap_err();
+        assert_eq!(err.to_string(), "column short has len 1, column id has len 3");
+    }
+
+    #[test]
+    fn test_to_sql_upserts() {
+        let df = sample().slice(0, 1).unwrap();
+        let values = "VALUES (1, 'O''Brien', 1.5, TRUE)";
+        assert_eq!(df.to_sql_upserts("people", Dialect::Postgres, 10, &["id"]).unwrap(), format!("\
+INSERT INTO \"people\" (\"id\", \"name\", \"score\", \"active\") {} ON CONFLICT (\"id\") DO UPDATE SET \
+\"name\" = excluded.\"name\", \"score\" = excluded.\"score\", \"active\" = excluded.\"active\";
+", values));
+        assert_eq!(df.to_sql_upserts("people", Dialect::MySql, 10, &["id"]).unwrap(), format!("\
+INSERT INTO `people` (`id`, `name`, `score`, `active`) {} ON DUPLICATE KEY UPDATE \
+`name` = VALUES(`name`), `score` = VALUES(`score`), `active` = VALUES(`active`);
+", values));
+
+        let mut ids = DataFrame::new();
+        ids.add_col("id".to_owned(), Array::Int64(ArrayData::from_vec(vec![1])));
+        assert_eq!(ids.to_sql_upserts("t", Dialect::Sqlite, 10, &["id"]).unwrap(),
+            "INSERT INTO \"t\" (\"id\") VALUES (1) ON CONFLICT (\"id\") DO NOTHING;\n");
+        assert!(df.to_sql_upserts("people", Dialect::Postgres, 10, &["missing"]).is_err());
+        assert!(df.to_sql_upserts("people", Dialect::Postgres, 10, &[]).is_err());
+        assert!(ragged().to_sql_upserts("people", Dialect::Postgres, 10, &["id"]).is_err());
+    }
+
+    #[test]
+    fn test_write_pg_copy() {
+        let mut buf = Vec::new();
+        sample().write_pg_copy("people", &mut buf).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap(), "\
+COPY \"people\" (\"id\", \"name\", \"score\", \"active\") FROM STDIN;
+1\tO'Brien\t1.5\ttrue
+2\t\\N\t2.0\tfalse
+3\ta\\\\b\\tc\t\\N\ttrue
+\\.
+");
+
+        let mut buf = Vec::new();
+        assert!(ragged().write_pg_copy("people", &mut buf).is_err());
+        assert!(buf.is_empty());
+        assert!(ragged().write_pg_copy_data(Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,377 @@
+use failure::Error;
+use indexmap::IndexSet;
+use rayon::prelude::*;
+
+use categorical::CategoricalData;
+use dataframe::{Array, ArrayData, DataFrame, DataTypeIterator};
+
+const NUMERIC_STATS: [&str; 9] = ["count", "null_count", "mean", "std", "min", "25%", "50%", "75%", "max"];
+const STR_STATS: [&str; 5] = ["count", "null_count", "unique", "top", "freq"];
+
+/// How `Array::quantile` picks a value when the quantile falls
+/// between two rows `a < b`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interpolation {
+    /// `a + (b - a) * fraction`
+    Linear,
+    Lower,
+    Higher,
+    /// Whichever of `a` or `b` is closer (`a` at exactly halfway)
+    Nearest,
+    /// `(a + b) / 2`
+    Midpoint,
+}
+
+// `sorted` must be non-empty and `q` in 0..=1.
+fn quantile_sorted(sorted: &[f64], q: f64, interpolation: Interpolation) -> f64 {
+    let position = q * (sorted.len() - 1) as f64;
+    let (lo, hi) = (position.floor() as usize, position.ceil() as usize);
+    let fraction = position - lo as f64;
+    match interpolation {
+        Interpolation::Linear => sorted[lo] + (sorted[hi] - sorted[lo]) * fraction,
+        Interpolation::Lower => sorted[lo],
+        Interpolation::Higher => sorted[hi],
+        Interpolation::Nearest => if fraction > 0.5 { sorted[hi] } else { sorted[lo] },
+        Interpolation::Midpoint => (sorted[lo] + sorted[hi]) / 2.0,
+    }
+}
+
+// Non-null values of a numeric array, sorted.
+fn sorted_values(array: &Array, op: &str) -> Result<Vec<f64>, Error> {
+    let mut xs: Vec<f64> = array.numeric_values(op)?.into_iter().flatten().collect();
+    xs.sort_by(f64::total_cmp);
+    Ok(xs)
+}
+
+// False for NaN edges too.
+fn increasing(edges: &[f64]) -> bool {
+    edges.windows(2).all(|pair| pair[0] < pair[1])
+}
+
+// Bin edges in labels, rounded to 3 decimal places.
+fn format_edge(edge: f64) -> String {
+    if edge.is_finite() {
+        format!("{}", (edge * 1000.0).round() / 1000.0)
+    } else {
+        format!("{}", edge)
+    }
+}
+
+// Each value's bin, for bins between consecutive `edges`. Values
+// outside the bins (and NaN) are None. `include_lowest` also puts a
+// value equal to the first edge of a right closed bin in bin 0.
+fn bin_codes(values: &[Option<f64>], edges: &[f64], right_closed: bool, include_lowest: bool) -> Vec<Option<u32>> {
+    let n_bins = edges.len() - 1;
+    values.par_iter()
+        .map(|value| {
+            let v = (*value)?;
+            if include_lowest && right_closed && v == edges[0] {
+                return Some(0);
+            }
+            // number of edges at or below the bin's lower edge
+            let below = if right_closed {
+                edges.partition_point(|&e| e < v)
+            } else {
+                edges.partition_point(|&e| e <= v)
+            };
+            if below >= 1 && below <= n_bins {
+                Some((below - 1) as u32)
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+fn interval_labels(edges: &[f64], right_closed: bool) -> Vec<String> {
+    edges.windows(2)
+        .map(|pair| {
+            let (a, b) = (format_edge(pair[0]), format_edge(pair[1]));
+            if right_closed {
+                format!("({}, {}]", a, b)
+            } else {
+                format!("[{}, {})", a, b)
+            }
+        })
+        .collect()
+}
+
+fn binned(codes: Vec<Option<u32>>, labels: Vec<String>) -> Result<Array, Error> {
+    let n_labels = labels.len();
+    let categories: IndexSet<String> = labels.into_iter().collect();
+    if categories.len() != n_labels {
+        return Err(format_err!("bin labels must be unique"));
+    }
+    Ok(Array::Categorical(CategoricalData::new(ArrayData::from_options(codes), categories)?))
+}
+
+impl Array {
+    /// The `q` quantile (0 to 1) of the non-null values, or None if
+    /// there are none. Errors if the array isn't numeric or `q` is out
+    /// of range.
+    pub fn quantile(&self, q: f64, interpolation: Interpolation) -> Result<Option<f64>, Error> {
+        if !(0.0..=1.0).contains(&q) {
+            return Err(format_err!("quantile must be between 0 and 1, got {}", q));
+        }
+        let xs = sorted_values(self, "quantile")?;
+        if xs.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(quantile_sorted(&xs, q, interpolation)))
+    }
+
+    /// The linear 0.5 quantile.
+    pub fn median(&self) -> Result<Option<f64>, Error> {
+        self.quantile(0.5, Interpolation::Linear)
+    }
+
+    /// Buckets numeric values into the bins between consecutive `bins`
+    /// edges, as a Categorical of bin labels (every label is a
+    /// category, in bin order, even if no value falls in it).
+    ///
+    /// Bins are `(a, b]` when `right_closed`, else `[a, b)`. `labels`
+    /// default to those interval strings. Values outside the bins
+    /// become null, as do nulls.
+    ///
+    /// Errors if the array isn't numeric, there are fewer than two
+    /// edges, the edges aren't increasing, or there isn't one unique
+    /// label per bin.
+    pub fn cut(&self, bins: &[f64], labels: Option<&[&str]>, right_closed: bool) -> Result<Array, Error> {
+        if bins.len() < 2 {
+            return Err(format_err!("cut needs at least two bin edges, got {}", bins.len()));
+        }
+        if !increasing(bins) {
+            return Err(format_err!("bin edges must be increasing"));
+        }
+        let labels = match labels {
+            Some(labels) if labels.len() != bins.len() - 1 => {
+                return Err(format_err!("{} labels given for {} bins", labels.len(), bins.len() - 1));
+            },
+            Some(labels) => labels.iter().map(|l| l.to_string()).collect(),
+            None => interval_labels(bins, right_closed),
+        };
+        let codes = bin_codes(&self.numeric_values("cut")?, bins, right_closed, false);
+        binned(codes, labels)
+    }
+
+    /// Buckets numeric values into `n_quantiles` bins holding about the
+    /// same number of values each, as a Categorical of `(a, b]` labels.
+    /// The lowest bin includes its lower edge. Nulls stay null.
+    ///
+    /// Errors if the array isn't numeric, has no values, or has so many
+    /// repeats that two edges are the same.
+    pub fn qcut(&self, n_quantiles: usize) -> Result<Array, Error> {
+        if n_quantiles == 0 {
+            return Err(format_err!("qcut needs at least one quantile"));
+        }
+        let xs = sorted_values(self, "qcut")?;
+        if xs.is_empty() {
+            return Err(format_err!("can't qcut an array with no values"));
+        }
+        let edges: Vec<f64> = (0..=n_quantiles)
+            .map(|i| quantile_sorted(&xs, i as f64 / n_quantiles as f64, Interpolation::Linear))
+            .collect();
+        if !increasing(&edges) {
+            return Err(format_err!("qcut bin edges aren't unique, try fewer quantiles"));
+        }
+        let codes = bin_codes(&self.numeric_values("qcut")?, &edges, true, true);
+        binned(codes, interval_labels(&edges, true))
+    }
+}
+
+// Stats for one column, in the order of NUMERIC_STATS or STR_STATS.
+enum Summary {
+    Numeric(Vec<Option<f64>>),
+    Str(Vec<Option<String>>),
+}
+
+fn describe_numeric(values: Vec<Option<f64>>) -> Summary {
+    let null_count = values.iter().filter(|x| x.is_none()).count();
+    let mut xs: Vec<f64> = values.into_iter().flatten().collect();
+    xs.sort_by(f64::total_cmp);
+    let n = xs.len();
+
+    let mean = if n > 0 { Some(xs.iter().sum::<f64>() / n as f64) } else { None };
+    let std = match mean {
+        Some(mean) if n > 1 => Some((xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1) as f64).sqrt()),
+        _ => None,
+    };
+    let quantile = |q| if n > 0 { Some(quantile_sorted(&xs, q, Interpolation::Linear)) } else { None };
+
+    Summary::Numeric(vec![
+        Some(n as f64),
+        Some(null_count as f64),
+        mean,
+        std,
+        quantile(0.0),
+        quantile(0.25),
+        quantile(0.5),
+        quantile(0.75),
+        quantile(1.0),
+    ])
+}
+
+fn describe_str(array: &Array) -> Result<Summary, Error> {
+    let counts = array.value_counts();
+    let top = DataTypeIterator::<String>::values(&counts.columns["value"])?.next();
+    let freq = DataTypeIterator::<u64>::values(&counts.columns["count"])?.next();
+
+    Ok(Summary::Str(vec![
+        Some((array.len() - array.null_count()).to_string()),
+        Some(array.null_count().to_string()),
+        Some(counts.columns["count"].len().to_string()),
+        top.and_then(|top| top.cloned()),
+        freq.and_then(|freq| freq.map(|freq| freq.to_string())),
+    ]))
+}
+
+impl DataFrame {
+    /// Summary statistics, one row per statistic and one column per
+    /// numeric or Str/Categorical column (other dtypes are skipped).
+    /// The first column, "statistic", names the rows.
+    ///
+    /// Numeric columns get count, null_count, mean, std (sample), min,
+    /// 25%, 50%, 75% and max, as Float64. Str columns get count,
+    /// null_count, unique, top (most common value) and freq, as Str.
+    /// Statistics that don't apply to a column are null.
+    ///
+    /// Errors if there are no columns to describe.
+    pub fn describe(&self) -> Result<Self, Error> {
+        let summaries = self.columns.iter()
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .filter_map(|(name, array)| match *array {
+                Array::Str(_) | Array::Categorical(_) => Some(describe_str(array).map(|s| (name, s))),
+                _ => array.numeric_values("describe").ok().map(|values| Ok((name, describe_numeric(values)))),
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        if summaries.is_empty() {
+            return Err(format_err!("no numeric or Str columns to describe"));
+        }
+
+        let has_numeric = summaries.iter().any(|(_, s)| matches!(*s, Summary::Numeric(_)));
+        let has_str = summaries.iter().any(|(_, s)| matches!(*s, Summary::Str(_)));
+        let mut stats: Vec<&str> = vec![];
+        if has_numeric {
+            stats.extend(NUMERIC_STATS.iter());
+        }
+        if has_str {
+            for stat in STR_STATS.iter() {
+                if !stats.contains(stat) {
+                    stats.push(stat);
+                }
+            }
+        }
+
+        let mut df = DataFrame::new();
+        df.add_col("statistic".to_owned(), Array::Str(ArrayData::from_vec(stats.iter().map(|s| s.to_string()).collect())));
+        for (name, summary) in summaries {
+            let array = match summary {
+                Summary::Numeric(values) => {
+                    let rows = stats.iter().map(|s| NUMERIC_STATS.iter().position(|n| n == s).and_then(|i| values[i])).collect();
+                    Array::Float64(ArrayData::from_options(rows))
+                },
+                Summary::Str(values) => {
+                    let rows = stats.iter().map(|s| STR_STATS.iter().position(|n| n == s).and_then(|i| values[i].clone())).collect();
+                    Array::Str(ArrayData::from_options(rows))
+                },
+            };
+            df.add_col(name.clone(), array);
+        }
+        Ok(df)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use decimal::DecimalData;
+
+    #[test]
+    fn test_describe() {
+        let mut df = DataFrame::new();
+        df.add_col("n".to_owned(), Array::Int64(ArrayData::from_options(vec![Some(1), Some(2), None, Some(3), Some(4)])));
+        let names = ["a", "b", "a", "a", "c"].iter().map(|s| s.to_string()).collect();
+        df.add_col("name".to_owned(), Array::Str(ArrayData::from_vec(names)));
+        df.add_col("when".to_owned(), Array::new("Date").unwrap());
+        let amounts = ArrayData::from_options(vec![Some(125), Some(250), None, Some(375), Some(500)]);
+        df.add_col("amount".to_owned(), Array::Decimal(DecimalData::new(amounts, 5, 2).unwrap()));
+
+        let summary = df.describe().unwrap();
+        assert_eq!(summary.columns.keys().collect::<Vec<_>>(), vec!["statistic", "n", "name", "amount"]);
+        let stats: Vec<_> = DataTypeIterator::<String>::values(&summary.columns["statistic"]).unwrap()
+            .map(|x| x.unwrap().clone())
+            .collect();
+        assert_eq!(stats, vec!["count", "null_count", "mean", "std", "min", "25%", "50%", "75%", "max", "unique", "top", "freq"]);
+
+        let n: Vec<_> = DataTypeIterator::<f64>::values(&summary.columns["n"]).unwrap().map(|x| x.cloned()).collect();
+        assert_eq!(&n[..3], &[Some(4.0), Some(1.0), Some(2.5)]);
+        assert_eq!(&n[4..], &[Some(1.0), Some(1.75), Some(2.5), Some(3.25), Some(4.0), None, None, None]);
+
+        let name: Vec<_> = DataTypeIterator::<String>::values(&summary.columns["name"]).unwrap().map(|x| x.cloned()).collect();
+        assert_eq!(name[0], Some("5".to_owned()));
+        assert_eq!(name[2], None);
+        assert_eq!(&name[9..], &[Some("3".to_owned()), Some("a".to_owned()), Some("3".to_owned())]);
+
+        let amount: Vec<_> = DataTypeIterator::<f64>::values(&summary.columns["amount"]).unwrap().map(|x| x.cloned()).collect();
+        assert_eq!(&amount[..3], &[Some(4.0), Some(1.0), Some(3.125)]);
+        assert_eq!(&amount[4..9], &[Some(1.25), Some(2.1875), Some(3.125), Some(4.0625), Some(5.0)]);
+
+        assert!(DataFrame::new().describe().is_err());
+    }
+
+    fn labels(array: &Array) -> Vec<Option<String>> {
+        DataTypeIterator::<String>::values(array).unwrap().map(|x| x.cloned()).collect()
+    }
+
+    #[test]
+    fn test_quantile() {
+        let array = Array::Int32(ArrayData::from_options(vec![Some(4), None, Some(1), Some(3), Some(2)]));
+        assert_eq!(array.median().unwrap(), Some(2.5));
+        assert_eq!(array.quantile(0.5, Interpolation::Lower).unwrap(), Some(2.0));
+        assert_eq!(array.quantile(0.5, Interpolation::Higher).unwrap(), Some(3.0));
+        assert_eq!(array.quantile(0.5, Interpolation::Midpoint).unwrap(), Some(2.5));
+        assert_eq!(array.quantile(0.4, Interpolation::Nearest).unwrap(), Some(2.0));
+        assert_eq!(array.quantile(1.0, Interpolation::Linear).unwrap(), Some(4.0));
+        assert!(array.quantile(1.5, Interpolation::Linear).is_err());
+        assert_eq!(Array::new("Float64").unwrap().median().unwrap(), None);
+
+        let prices = ArrayData::from_options(vec![Some(150), None, Some(-25), Some(300)]);
+        let prices = Array::Decimal(DecimalData::new(prices, 5, 2).unwrap());
+        assert_eq!(prices.median().unwrap(), Some(1.5));
+        assert_eq!(prices.quantile(0.5, Interpolation::Lower).unwrap(), Some(1.5));
+        assert_eq!(prices.quantile(0.0, Interpolation::Linear).unwrap(), Some(-0.25));
+    }
+
+    #[test]
+    fn test_cut() {
+        let incomes = Array::Float64(ArrayData::from_options(vec![Some(5.0), Some(10.0), None, Some(25.0), Some(100.0)]));
+        let binned = incomes.cut(&[0.0, 10.0, 50.0], None, true).unwrap();
+        assert_eq!(labels(&binned), vec![Some("(0, 10]".to_owned()), Some("(0, 10]".to_owned()), None, Some("(10, 50]".to_owned()), None]);
+
+        let binned = incomes.cut(&[0.0, 10.0, 50.0], Some(&["low", "mid"]), false).unwrap();
+        assert_eq!(labels(&binned), vec![Some("low".to_owned()), Some("mid".to_owned()), None, Some("mid".to_owned()), None]);
+
+        assert!(incomes.cut(&[0.0, 10.0], Some(&["a", "b"]), true).is_err());
+        assert!(incomes.cut(&[10.0, 0.0], None, true).is_err());
+
+        let amounts = ArrayData::from_options(vec![Some(1000), Some(1001), None, Some(5000)]);
+        let amounts = Array::Decimal(DecimalData::new(amounts, 6, 2).unwrap());
+        let binned = amounts.cut(&[0.0, 10.0, 50.0], None, true).unwrap();
+        assert_eq!(labels(&binned), vec![Some("(0, 10]".to_owned()), Some("(10, 50]".to_owned()), None, Some("(10, 50]".to_owned())]);
+    }
+
+    #[test]
+    fn test_qcut() {
+        let array = Array::Int64(ArrayData::from_options(vec![Some(1), Some(2), Some(3), None, Some(4)]));
+        let binned = array.qcut(2).unwrap();
+        assert_eq!(labels(&binned), vec![Some("(1, 2.5]".to_owned()), Some("(1, 2.5]".to_owned()), Some("(2.5, 4]".to_owned()), None, Some("(2.5, 4]".to_owned())]);
+
+        let amounts = Array::Decimal(DecimalData::new(ArrayData::from_vec(vec![10, 20, 30, 40]), 3, 1).unwrap());
+        let binned = amounts.qcut(2).unwrap();
+        assert_eq!(labels(&binned), vec![Some("(1, 2.5]".to_owned()), Some("(1, 2.5]".to_owned()), Some("(2.5, 4]".to_owned()), Some("(2.5, 4]".to_owned())]);
+
+        let same = Array::Int64(ArrayData::from_vec(vec![1, 1, 1, 2]));
+        assert!(same.qcut(4).is_err());
+    }
+}
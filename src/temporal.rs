@@ -0,0 +1,467 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Timelike};
+use failure::Error;
+use rayon::prelude::*;
+use std::fmt::{Display, Write};
+
+use dataframe::{Array, ArrayData};
+
+/// Timestamps with an optional fixed timezone offset.
+///
+/// With an offset, `values` are UTC instants and the offset is only
+/// used when formatting or extracting components. Without one, they
+/// are wall clock times.
+#[derive(Debug, Clone)]
+pub struct DatetimeData {
+    values: ArrayData<NaiveDateTime>,
+    offset: Option<FixedOffset>,
+}
+
+impl DatetimeData {
+    pub fn new(values: ArrayData<NaiveDateTime>, offset: Option<FixedOffset>) -> Self {
+        DatetimeData {
+            values,
+            offset,
+        }
+    }
+
+    pub fn values(&self) -> &ArrayData<NaiveDateTime> {
+        &self.values
+    }
+
+    pub fn offset(&self) -> Option<FixedOffset> {
+        self.offset
+    }
+
+    // Wall clock time of a value, for formatting and components
+    fn local(&self, value: &NaiveDateTime) -> NaiveDateTime {
+        match self.offset {
+            Some(offset) => offset.from_utc_datetime(value).naive_local(),
+            None => *value,
+        }
+    }
+
+    pub fn apply_inplace<F>(&mut self, f: F)
+        where F: Fn(&mut NaiveDateTime) + Sync + Send
+    {
+        self.values.apply_inplace(f);
+    }
+
+    pub fn apply<F>(&self, f: F) -> Self
+        where F: Fn(&NaiveDateTime) -> NaiveDateTime + Sync + Send
+    {
+        DatetimeData::new(self.values.apply(f), self.offset)
+    }
+
+    // Inner option signifies null
+    pub fn get(&self, index: usize) -> Option<Option<&NaiveDateTime>> {
+        self.values.get(index)
+    }
+
+    pub fn push(&mut self, item: NaiveDateTime) {
+        self.values.push(item);
+    }
+
+    pub fn push_null(&mut self) {
+        self.values.push_null();
+    }
+
+    /// `value` is taken as is, so with an offset it's a UTC instant.
+    pub fn fill_null(&self, value: NaiveDateTime) -> Self {
+        DatetimeData::new(self.values.fill_null(value), self.offset)
+    }
+
+    /// Errors unless all parts have the same offset.
+    pub fn concat(parts: &[&DatetimeData]) -> Result<Self, Error> {
+        let offset = parts.first().and_then(|part| part.offset);
+        if parts.iter().any(|part| part.offset != offset) {
+            return Err(format_err!("can't concat Datetime arrays with different offsets"));
+        }
+        let values: Vec<_> = parts.iter().map(|part| &part.values).collect();
+        Ok(DatetimeData::new(ArrayData::concat(&values), offset))
+    }
+
+    pub fn multiply_row(&self, multiple: usize) -> Self {
+        DatetimeData::new(self.values.multiply_row(multiple), self.offset)
+    }
+
+    pub fn slice(&self, offset: usize, len: usize) -> Result<Self, Error> {
+        Ok(DatetimeData::new(self.values.slice(offset, len)?, self.offset))
+    }
+
+    pub(crate) fn take(&self, indices: &[usize]) -> Self {
+        DatetimeData::new(self.values.take(indices), self.offset)
+    }
+
+    pub(crate) fn take_opt(&self, indices: &[Option<usize>]) -> Self {
+        DatetimeData::new(self.values.take_opt(indices), self.offset)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+/// Component to extract from a Date or Datetime array.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DatePart {
+    Year,
+    /// 1 to 12
+    Month,
+    /// 1 to 31
+    Day,
+    /// Monday is 0, Sunday is 6
+    Weekday,
+    /// 1 to 4
+    Quarter,
+    Hour,
+    Minute,
+    Second,
+}
+
+impl DatePart {
+    fn of_date(self, date: &NaiveDate) -> Option<i32> {
+        match self {
+            DatePart::Year => Some(date.year()),
+            DatePart::Month => Some(date.month() as i32),
+            DatePart::Day => Some(date.day() as i32),
+            DatePart::Weekday => Some(date.weekday().num_days_from_monday() as i32),
+            DatePart::Quarter => Some((date.month0() / 3 + 1) as i32),
+            _ => None,
+        }
+    }
+
+    fn of_datetime(self, datetime: &NaiveDateTime) -> i32 {
+        match self {
+            DatePart::Hour => datetime.hour() as i32,
+            DatePart::Minute => datetime.minute() as i32,
+            DatePart::Second => datetime.second() as i32,
+            _ => self.of_date(&datetime.date()).expect("date parts always exist"),
+        }
+    }
+}
+
+// chrono's specifiers for a timezone offset
+fn has_offset_specifier(fmt: &str) -> bool {
+    ["%z", "%:z", "%::z", "%:::z", "%#z"].iter().any(|spec| fmt.contains(spec))
+}
+
+// Parses every non-null string, keeping nulls
+fn parse_strings<T, F>(strings: &[Option<&String>], parse: F) -> Result<Vec<Option<T>>, Error>
+    where T: Send,
+          F: Fn(usize, &str) -> Result<T, Error> + Sync + Send
+{
+    strings.par_iter()
+        .enumerate()
+        .map(|(i, s)| match *s {
+            Some(s) => parse(i, s).map(Some),
+            None => Ok(None),
+        })
+        .collect()
+}
+
+// Formats each value (null placeholders too, like `map_values`) with
+// `format`, which errors if `fmt` asks for something the value lacks,
+// like an hour of a date.
+fn format_values<T, D, F>(values: &ArrayData<T>, dtype: &str, fmt: &str, format: F) -> Result<ArrayData<String>, Error>
+    where T: Send + Sync + Clone,
+          D: Display,
+          F: Fn(&T) -> D + Sync + Send
+{
+    let strings = values.as_slice().par_iter()
+        .map(|x| {
+            let mut s = String::new();
+            write!(s, "{}", format(x)).map(|_| s)
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| format_err!("can't format {} with {:?}", dtype, fmt))?;
+    Ok(ArrayData::with_validity(strings, values.validity()))
+}
+
+// Combines two arrays row by row, null if either side is null
+fn zip_rows<A, B, T, F>(a: &ArrayData<A>, b: &ArrayData<B>, f: F) -> Result<ArrayData<T>, Error>
+    where A: Send + Sync + Clone,
+          B: Send + Sync + Clone,
+          T: Send + Sync + Clone + Default,
+          F: Fn(usize, &A, &B) -> Result<T, Error>
+{
+    if a.len() != b.len() {
+        return Err(format_err!("length mismatch: {} and {}", a.len(), b.len()));
+    }
+    let values = a.iter().zip(b.iter()).enumerate()
+        .map(|(i, pair)| match pair {
+            (Some(a), Some(b)) => f(i, a, b).map(Some),
+            _ => Ok(None),
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    Ok(ArrayData::from_options(values))
+}
+
+impl Array {
+    /// Parses a `Str` or Categorical array into a Date array, using
+    /// strftime-style `fmt` (e.g. "%Y-%m-%d"). Nulls stay null.
+    pub fn parse_date(&self, fmt: &str) -> Result<Array, Error> {
+        let strings = self.string_values("Date")?;
+        let dates = parse_strings(&strings, |i, s| {
+            NaiveDate::parse_from_str(s, fmt)
+                .map_err(|err| format_err!("row {}: can't parse {:?} as date with {:?}: {}", i, s, fmt, err))
+        })?;
+
+        Ok(Array::Date(ArrayData::from_options(dates)))
+    }
+
+    /// Parses a `Str` or Categorical array into a Datetime array, using
+    /// strftime-style `fmt`. Nulls stay null.
+    ///
+    /// If `fmt` has an offset specifier (`%z`, `%:z`, ...), values are
+    /// stored as UTC and the array takes the offset of the first row.
+    pub fn parse_datetime(&self, fmt: &str) -> Result<Array, Error> {
+        let strings = self.string_values("Datetime")?;
+        let parse_err = |i, s: &str, err| {
+            format_err!("row {}: can't parse {:?} as datetime with {:?}: {}", i, s, fmt, err)
+        };
+
+        if has_offset_specifier(fmt) {
+            let parsed = parse_strings(&strings, |i, s| {
+                DateTime::parse_from_str(s, fmt).map_err(|err| parse_err(i, s, err))
+            })?;
+            let offset = parsed.iter().filter_map(|dt| dt.map(|dt| *dt.offset())).next();
+            let values = parsed.iter().map(|dt| dt.map(|dt| dt.naive_utc())).collect();
+
+            Ok(Array::Datetime(DatetimeData::new(ArrayData::from_options(values), offset)))
+        } else {
+            let values = parse_strings(&strings, |i, s| {
+                NaiveDateTime::parse_from_str(s, fmt).map_err(|err| parse_err(i, s, err))
+            })?;
+
+            Ok(Array::Datetime(DatetimeData::new(ArrayData::from_options(values), None)))
+        }
+    }
+
+    /// Formats a Date or Datetime array into a `Str` array, using
+    /// strftime-style `fmt`. Datetimes are formatted in their own
+    /// offset, if they have one. Nulls stay null. Errors on an invalid
+    /// `fmt`, or one asking for a time of day from a Date.
+    pub fn format_temporal(&self, fmt: &str) -> Result<Array, Error> {
+        let items: Vec<Item> = StrftimeItems::new(fmt).collect();
+        if items.contains(&Item::Error) {
+            return Err(format_err!("invalid format {:?}", fmt));
+        }
+        let dtype = self.dtype();
+        let strings = match *self {
+            Array::Date(ref array_data) => {
+                format_values(array_data, &dtype, fmt, |d| d.format_with_items(items.iter()))?
+            },
+            Array::Datetime(ref dt_data) => {
+                match dt_data.offset() {
+                    Some(offset) => format_values(dt_data.values(), &dtype, fmt, |dt| {
+                        offset.from_utc_datetime(dt).format_with_items(items.iter())
+                    })?,
+                    None => format_values(dt_data.values(), &dtype, fmt, |dt| dt.format_with_items(items.iter()))?,
+                }
+            },
+            _ => return Err(format_err!("can't format {}, array must be Date or Datetime", dtype)),
+        };
+
+        Ok(Array::Str(strings))
+    }
+
+    /// Extracts a component of each Date or Datetime as an Int32 array.
+    /// Hour, Minute and Second are only available for Datetime.
+    pub fn date_part(&self, part: DatePart) -> Result<Array, Error> {
+        let parts = match *self {
+            Array::Date(ref array_data) => {
+                if part.of_date(&NaiveDate::default()).is_none() {
+                    return Err(format_err!("Date has no {:?}", part));
+                }
+                array_data.map_values(|d| part.of_date(d).expect("checked above"))
+            },
+            Array::Datetime(ref dt_data) => {
+                dt_data.values().map_values(|dt| part.of_datetime(&dt_data.local(dt)))
+            },
+            _ => return Err(format_err!("can't get {:?} of {}", part, self.dtype())),
+        };
+
+        Ok(Array::Int32(parts))
+    }
+
+    /// Adds a Duration array, row by row, to a Date, Datetime or
+    /// Duration array. Dates only move by whole days.
+    pub fn add_duration(&self, durations: &Array) -> Result<Array, Error> {
+        self.shift_by_duration(durations, false)
+    }
+
+    /// Subtracts a Duration array, row by row, from a Date, Datetime
+    /// or Duration array. Dates only move by whole days.
+    pub fn sub_duration(&self, durations: &Array) -> Result<Array, Error> {
+        self.shift_by_duration(durations, true)
+    }
+
+    fn shift_by_duration(&self, durations: &Array, negate: bool) -> Result<Array, Error> {
+        let durations = match *durations {
+            Array::Duration(ref array_data) => array_data,
+            _ => return Err(format_err!("expected Duration array, found {}", durations.dtype())),
+        };
+        let signed = |d: &Duration| if negate { -*d } else { *d };
+        let overflow = |i| format_err!("row {}: date arithmetic overflowed", i);
+
+        match *self {
+            Array::Date(ref array_data) => {
+                let values = zip_rows(array_data, durations, |i, x, d| {
+                    x.checked_add_signed(signed(d)).ok_or_else(|| overflow(i))
+                })?;
+                Ok(Array::Date(values))
+            },
+            Array::Datetime(ref dt_data) => {
+                let values = zip_rows(dt_data.values(), durations, |i, x, d| {
+                    x.checked_add_signed(signed(d)).ok_or_else(|| overflow(i))
+                })?;
+                Ok(Array::Datetime(DatetimeData::new(values, dt_data.offset())))
+            },
+            Array::Duration(ref array_data) => {
+                let values = zip_rows(array_data, durations, |i, x, d| {
+                    x.checked_add(&signed(d)).ok_or_else(|| overflow(i))
+                })?;
+                Ok(Array::Duration(values))
+            },
+            _ => Err(format_err!("can't add Duration to {}", self.dtype())),
+        }
+    }
+
+    /// Row by row difference `self - earlier` of two Date or two
+    /// Datetime arrays, as a Duration array.
+    pub fn duration_since(&self, earlier: &Array) -> Result<Array, Error> {
+        let values = match (self, earlier) {
+            (Array::Date(a), Array::Date(b)) => {
+                zip_rows(a, b, |_, a, b| Ok(a.signed_duration_since(*b)))?
+            },
+            (Array::Datetime(a), Array::Datetime(b)) => {
+                // both are UTC if they have an offset; a naive datetime
+                // is only comparable to another naive datetime
+                if a.offset().is_some() != b.offset().is_some() {
+                    return Err(format_err!("can't compare Datetime with and without offset"));
+                }
+                zip_rows(a.values(), b.values(), |_, a, b| Ok(a.signed_duration_since(*b)))?
+            },
+            _ => return Err(format_err!("can't subtract {} from {}", earlier.dtype(), self.dtype())),
+        };
+
+        Ok(Array::Duration(values))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dataframe::DataType;
+
+    fn str_array(xs: &[&str]) -> Array {
+        Array::Str(ArrayData::from_vec(xs.iter().map(|s| s.to_string()).collect()))
+    }
+
+    fn ints(array: &Array) -> Vec<i32> {
+        match *array {
+            Array::Int32(ref data) => data.as_slice().to_vec(),
+            ref other => panic!("expected Int32, found {:?}", other),
+        }
+    }
+
+    fn strings(array: &Array) -> Vec<String> {
+        match *array {
+            Array::Str(ref data) => data.as_slice().to_vec(),
+            ref other => panic!("expected Str, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_date_and_parts() {
+        let dates = str_array(&["03/15/2018", "12/31/2017"]).parse_date("%m/%d/%Y").unwrap();
+        assert_eq!(dates.dtype(), "Date");
+        let x: Option<Option<&NaiveDate>> = dates.get(0).unwrap();
+        assert_eq!(x, Some(Some(&NaiveDate::from_ymd_opt(2018, 3, 15).unwrap())));
+
+        assert_eq!(ints(&dates.date_part(DatePart::Year).unwrap()), vec![2018, 2017]);
+        assert_eq!(ints(&dates.date_part(DatePart::Month).unwrap()), vec![3, 12]);
+        assert_eq!(ints(&dates.date_part(DatePart::Day).unwrap()), vec![15, 31]);
+        // 2018-03-15 was a Thursday, 2017-12-31 a Sunday
+        assert_eq!(ints(&dates.date_part(DatePart::Weekday).unwrap()), vec![3, 6]);
+        assert_eq!(ints(&dates.date_part(DatePart::Quarter).unwrap()), vec![1, 4]);
+        assert!(dates.date_part(DatePart::Hour).is_err());
+
+        assert_eq!(strings(&dates.format_temporal("%Y-%m-%d").unwrap()), vec!["2018-03-15", "2017-12-31"]);
+
+        let err = str_array(&["2018-03-15", "nope"]).parse_date("%Y-%m-%d").unwrap_err();
+        assert!(err.to_string().contains("row 1"));
+        assert!(Array::Int8(ArrayData::from_vec(vec![1])).parse_date("%Y").is_err());
+    }
+
+    #[test]
+    fn test_parse_datetime_with_offset() {
+        let dts = str_array(&["2018-03-15 23:30:00 +0500", "2018-03-16 01:00:00 +0000"])
+            .parse_datetime("%Y-%m-%d %H:%M:%S %z")
+            .unwrap();
+        match dts {
+            Array::Datetime(ref data) => assert_eq!(data.offset(), FixedOffset::east_opt(5 * 3600)),
+            ref other => panic!("expected Datetime, found {:?}", other),
+        }
+        // stored as UTC
+        let x: Option<Option<&NaiveDateTime>> = dts.get(0).unwrap();
+        assert_eq!(x.unwrap().unwrap().hour(), 18);
+        // components and formatting are in the array's offset
+        assert_eq!(ints(&dts.date_part(DatePart::Hour).unwrap()), vec![23, 6]);
+        assert_eq!(ints(&dts.date_part(DatePart::Day).unwrap()), vec![15, 16]);
+        assert_eq!(
+            strings(&dts.format_temporal("%Y-%m-%dT%H:%M%:z").unwrap()),
+            vec!["2018-03-15T23:30+05:00", "2018-03-16T06:00+05:00"]
+        );
+    }
+
+    #[test]
+    fn test_nulls_pass_through() {
+        let mut strings = ArrayData::from_vec(vec!["2018-03-15".to_owned()]);
+        strings.push_null();
+        let dates = Array::Str(strings).parse_date("%Y-%m-%d").unwrap();
+        assert_eq!(dates.null_count(), 1);
+        assert!(dates.date_part(DatePart::Year).unwrap().is_null(1));
+        assert!(dates.format_temporal("%F").unwrap().is_null(1));
+        assert_eq!(dates.format_temporal("%Q").unwrap_err().to_string(), "invalid format \"%Q\"");
+        assert_eq!(dates.format_temporal("%F %H").unwrap_err().to_string(), "can't format Date with \"%F %H\"");
+
+        let week = Array::Duration(ArrayData::from_vec(vec![Duration::days(7), Duration::days(7)]));
+        let later = dates.add_duration(&week).unwrap();
+        assert_eq!(later.null_count(), 1);
+        let x: Option<Option<&NaiveDate>> = later.get(0).unwrap();
+        assert_eq!(x, Some(Some(&NaiveDate::from_ymd_opt(2018, 3, 22).unwrap())));
+    }
+
+    #[test]
+    fn test_duration_arithmetic() {
+        let start = str_array(&["2018-01-31", "2018-03-01"]).parse_date("%Y-%m-%d").unwrap();
+        let end = str_array(&["2018-02-01", "2018-02-01"]).parse_date("%Y-%m-%d").unwrap();
+
+        let elapsed = end.duration_since(&start).unwrap();
+        assert_eq!(elapsed.dtype(), "Duration");
+        let x: Option<Option<&Duration>> = elapsed.get(1).unwrap();
+        assert_eq!(x, Some(Some(&Duration::days(-28))));
+
+        let back = start.add_duration(&elapsed).unwrap();
+        assert_eq!(strings(&back.format_temporal("%F").unwrap()), vec!["2018-02-01", "2018-02-01"]);
+        let again = back.sub_duration(&elapsed).unwrap();
+        assert_eq!(strings(&again.format_temporal("%F").unwrap()), vec!["2018-01-31", "2018-03-01"]);
+
+        let doubled = elapsed.add_duration(&elapsed).unwrap();
+        let x: Option<Option<&Duration>> = doubled.get(0).unwrap();
+        assert_eq!(x, Some(Some(&Duration::days(2))));
+
+        let naive = str_array(&["2018-01-01 00:00:00"]).parse_datetime("%Y-%m-%d %H:%M:%S").unwrap();
+        let aware = str_array(&["2018-01-01 00:00:00 +0000"]).parse_datetime("%Y-%m-%d %H:%M:%S %z").unwrap();
+        assert!(naive.duration_since(&aware).is_err());
+        assert!(start.duration_since(&start.slice(0, 1).unwrap()).is_err());
+        assert!(start.duration_since(&naive).is_err());
+        assert!(start.add_duration(&start).is_err());
+    }
+}
@@ -0,0 +1,190 @@
+//! Typed handles on a column. The dtype is checked once, when the
+//! handle is made, so element access after that can't fail with a
+//! type mismatch the way `DataType::apply` can.
+
+use failure::Error;
+
+use dataframe::{Array, ArrayData, ArrayIterator, DataFrame, DataTypeIterator, IterValues};
+use window::Numeric;
+
+/// A column known to hold `T`, from `DataFrame::column` or
+/// `Array::typed`. Cheap to copy, and slicing is zero-copy.
+pub struct TypedColumn<'a, T: 'a> {
+    values: IterValues<'a, T>,
+    offset: usize,
+    len: usize,
+}
+
+impl<'a, T> Clone for TypedColumn<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for TypedColumn<'a, T> {}
+
+impl<'a, T: Send + Sync + Clone> TypedColumn<'a, T> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Outer Option is whether `index` is in bounds, inner is null.
+    pub fn get(&self, index: usize) -> Option<Option<&'a T>> {
+        if index >= self.len {
+            return None;
+        }
+        self.values.get(self.offset + index)
+    }
+
+    pub fn is_null(&self, index: usize) -> bool {
+        self.get(index).is_some_and(|x| x.is_none())
+    }
+
+    pub fn null_count(&self) -> usize {
+        self.iter().filter(|x| x.is_none()).count()
+    }
+
+    pub fn iter(&self) -> ArrayIterator<'a, T> {
+        ArrayIterator::window(self.values, self.offset, self.offset + self.len)
+    }
+
+    /// Zero-copy view of `len` rows starting at `offset`.
+    pub fn slice(&self, offset: usize, len: usize) -> Result<Self, Error> {
+        match offset.checked_add(len) {
+            Some(end) if end <= self.len => {
+                Ok(TypedColumn {
+                    values: self.values,
+                    offset: self.offset + offset,
+                    len,
+                })
+            },
+            Some(end) => Err(format_err!("slice {}..{} out of bounds for array of len {}", offset, end, self.len)),
+            None => Err(format_err!("slice of len {} at offset {} overflows", len, offset)),
+        }
+    }
+
+    /// `f` applied to each value, nulls stay null. For Categorical
+    /// columns `f` runs once per category rather than once per row.
+    pub fn map<U, F>(&self, f: F) -> ArrayData<U>
+        where U: Send + Sync + Clone + Default,
+              F: Fn(&T) -> U + Sync + Send
+    {
+        match self.values {
+            IterValues::Plain(values) => {
+                values.slice(self.offset, self.len)
+                    .expect("typed column in bounds")
+                    .map_values(f)
+            },
+            IterValues::Dictionary(codes, dictionary) => {
+                let mapped: Vec<U> = dictionary.iter().map(f).collect();
+                let codes = codes.slice(self.offset, self.len).expect("typed column in bounds");
+                let values = codes.iter()
+                    .map(|code| code.map(|&code| mapped[code as usize].clone()))
+                    .collect();
+                ArrayData::from_options(values)
+            },
+        }
+    }
+
+    /// Sum of the non-null values, 0 if there are none. Errors on
+    /// integer overflow, like `Array::cumsum`.
+    pub fn sum(&self) -> Result<T, Error>
+        where T: Numeric + Default
+    {
+        self.iter().flatten()
+            .try_fold(T::default(), |acc, &x| acc.checked_add(x))
+            .ok_or_else(|| format_err!("overflow in sum"))
+    }
+
+    pub fn to_vec(&self) -> Vec<Option<T>> {
+        self.iter().map(|x| x.cloned()).collect()
+    }
+}
+
+impl<'a, T: Send + Sync + Clone> IntoIterator for TypedColumn<'a, T> {
+    type Item = Option<&'a T>;
+    type IntoIter = ArrayIterator<'a, T>;
+
+    fn into_iter(self) -> ArrayIterator<'a, T> {
+        self.iter()
+    }
+}
+
+impl Array {
+    /// This array as a `TypedColumn<T>`, or an error if it doesn't
+    /// hold `T`.
+    pub fn typed<'a, T>(&'a self) -> Result<TypedColumn<'a, T>, Error>
+        where T: Send + Sync + Clone,
+              &'a Array: DataTypeIterator<'a, T>
+    {
+        let values = self.values()?.into_values();
+        Ok(TypedColumn {
+            values,
+            offset: 0,
+            len: values.len(),
+        })
+    }
+}
+
+impl DataFrame {
+    /// Column `name` as a `TypedColumn<T>`. Errors if there's no such
+    /// column or it doesn't hold `T`.
+    pub fn column<'a, T>(&'a self, name: &str) -> Result<TypedColumn<'a, T>, Error>
+        where T: Send + Sync + Clone,
+              &'a Array: DataTypeIterator<'a, T>
+    {
+        self.get_col(name)
+            .ok_or_else(|| format_err!("column {} not found", name))?
+            .typed()
+            .map_err(|err| format_err!("column {}: {}", name, err))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use categorical::CategoricalData;
+
+    #[test]
+    fn test_typed_column() {
+        let mut df = DataFrame::new();
+        df.add_col("id".to_owned(), Array::Int8(ArrayData::from_options(vec![Some(1), None, Some(3), Some(4)])));
+        assert!(df.column::<i32>("id").is_err());
+        assert!(df.column::<i8>("missing").is_err());
+
+        let id = df.column::<i8>("id").unwrap();
+        assert_eq!(id.len(), 4);
+        assert_eq!(id.get(1), Some(None));
+        assert_eq!(id.get(2), Some(Some(&3)));
+        assert_eq!(id.get(4), None);
+        assert_eq!(id.null_count(), 1);
+        assert_eq!(id.sum().unwrap(), 8);
+
+        let tail = id.slice(1, 3).unwrap();
+        assert_eq!(tail.to_vec(), vec![None, Some(3), Some(4)]);
+        assert_eq!(tail.slice(1, 1).unwrap().to_vec(), vec![Some(3)]);
+        assert_eq!(tail.sum().unwrap(), 7);
+        assert_eq!(tail.slice(0, 1).unwrap().sum().unwrap(), 0);
+        assert!(tail.slice(2, 2).is_err());
+
+        let big = Array::Int8(ArrayData::from_vec(vec![100, 100]));
+        assert_eq!(big.typed::<i8>().unwrap().sum().unwrap_err().to_string(), "overflow in sum");
+
+        let doubled = tail.map(|&x| x as i64 * 2);
+        assert_eq!(doubled.iter().map(|x| x.cloned()).collect::<Vec<_>>(), vec![None, Some(6), Some(8)]);
+    }
+
+    #[test]
+    fn test_typed_categorical() {
+        let words = vec![Some("a".to_owned()), None, Some("bb".to_owned()), Some("a".to_owned())];
+        let array = Array::Categorical(CategoricalData::encode_array(&ArrayData::from_options(words)));
+        let words = array.typed::<String>().unwrap().slice(1, 3).unwrap();
+        assert_eq!(words.get(1), Some(Some(&"bb".to_owned())));
+        let lens = words.map(|s| s.len());
+        assert_eq!(lens.iter().map(|x| x.cloned()).collect::<Vec<_>>(), vec![None, Some(2), Some(1)]);
+    }
+}
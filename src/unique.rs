@@ -0,0 +1,134 @@
+use failure::Error;
+
+use dataframe::{Array, ArrayData, DataFrame};
+use groupby::row_groups;
+
+/// Which row of a set of duplicates `DataFrame::drop_duplicates` keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Keep {
+    First,
+    Last,
+    /// Drop every row that has a duplicate
+    None,
+}
+
+impl DataFrame {
+    /// Bool mask, true for each row that repeats an earlier row's
+    /// values in the `subset` columns (every column, if `subset` is
+    /// empty). Nulls are equal to each other.
+    pub fn duplicated(&self, subset: &[&str]) -> Result<Array, Error> {
+        let groups = row_groups(&self.columns_of(subset)?);
+        let len = groups.iter().map(|rows| rows.len()).sum();
+        let mut mask = vec![true; len];
+        for rows in &groups {
+            mask[rows[0]] = false;
+        }
+        Ok(Array::Bool(ArrayData::from_vec(mask)))
+    }
+
+    /// Rows with distinct values in the `subset` columns (every column,
+    /// if `subset` is empty), keeping the rows' original order. Errors
+    /// if any columns have different lengths.
+    pub fn drop_duplicates(&self, subset: &[&str], keep: Keep) -> Result<Self, Error> {
+        self.columns_of(&[])?;
+        let groups = row_groups(&self.columns_of(subset)?);
+        let mut indices: Vec<usize> = groups.iter()
+            .filter_map(|rows| match keep {
+                Keep::First => rows.first().cloned(),
+                Keep::Last => rows.last().cloned(),
+                Keep::None if rows.len() == 1 => Some(rows[0]),
+                Keep::None => None,
+            })
+            .collect();
+        indices.sort_unstable();
+        Ok(self.take(&indices))
+    }
+}
+
+impl Array {
+    /// Distinct values in order of first appearance, including one
+    /// null if there are any.
+    pub fn unique(&self) -> Array {
+        let first_rows: Vec<usize> = row_groups(&[self]).iter().map(|rows| rows[0]).collect();
+        self.take(&first_rows)
+    }
+
+    /// Number of distinct non-null values.
+    pub fn n_unique(&self) -> usize {
+        row_groups(&[self]).iter().filter(|rows| !self.is_null(rows[0])).count()
+    }
+
+    /// Distinct non-null values and how often each occurs, as a
+    /// DataFrame with columns "value" (same dtype as the array) and
+    /// "count" (UInt64). Most common first; ties in order of first
+    /// appearance.
+    pub fn value_counts(&self) -> DataFrame {
+        let mut groups: Vec<Vec<usize>> = row_groups(&[self]).into_iter()
+            .filter(|rows| !self.is_null(rows[0]))
+            .collect();
+        groups.sort_by_key(|rows| ::std::cmp::Reverse(rows.len()));
+
+        let first_rows: Vec<usize> = groups.iter().map(|rows| rows[0]).collect();
+        let counts = groups.iter().map(|rows| rows.len() as u64).collect();
+        let mut df = DataFrame::new();
+        df.add_col("value".to_owned(), self.take(&first_rows));
+        df.add_col("count".to_owned(), Array::UInt64(ArrayData::from_vec(counts)));
+        df
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dataframe::DataTypeIterator;
+
+    fn sample() -> DataFrame {
+        let mut df = DataFrame::new();
+        let names = ["a", "b", "a", "c", "a"].iter().map(|s| s.to_string()).collect();
+        df.add_col("name".to_owned(), Array::Str(ArrayData::from_vec(names)));
+        df.add_col("n".to_owned(), Array::Int64(ArrayData::from_options(vec![Some(1), None, Some(1), None, Some(2)])));
+        df
+    }
+
+    fn ints(array: &Array) -> Vec<Option<i64>> {
+        DataTypeIterator::<i64>::values(array).unwrap().map(|x| x.cloned()).collect()
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let df = sample();
+        let mask = df.duplicated(&[]).unwrap();
+        assert_eq!(DataTypeIterator::<bool>::values(&mask).unwrap().map(|x| x.cloned()).collect::<Vec<_>>(),
+            vec![Some(false), Some(false), Some(true), Some(false), Some(false)]);
+        assert_eq!(df.filter(&mask).unwrap().get_col("n").unwrap().len(), 1);
+
+        let first = df.drop_duplicates(&["name"], Keep::First).unwrap();
+        assert_eq!(ints(first.get_col("n").unwrap()), vec![Some(1), None, None]);
+        let last = df.drop_duplicates(&["name"], Keep::Last).unwrap();
+        assert_eq!(ints(last.get_col("n").unwrap()), vec![None, None, Some(2)]);
+        let none = df.drop_duplicates(&["name"], Keep::None).unwrap();
+        assert_eq!(ints(none.get_col("n").unwrap()), vec![None, None]);
+
+        assert!(df.duplicated(&["missing"]).is_err());
+
+        let mut ragged = df.clone();
+        ragged.add_col("short".to_owned(), Array::Int64(ArrayData::from_vec(vec![1])));
+        assert!(ragged.drop_duplicates(&["name"], Keep::First).is_err());
+    }
+
+    #[test]
+    fn test_unique_and_value_counts() {
+        let df = sample();
+        let n = df.get_col("n").unwrap();
+        assert_eq!(ints(&n.unique()), vec![Some(1), None, Some(2)]);
+        assert_eq!(n.n_unique(), 2);
+
+        let counts = df.get_col("name").unwrap().value_counts();
+        assert_eq!(DataTypeIterator::<String>::values(counts.get_col("value").unwrap()).unwrap()
+            .map(|x| x.cloned()).collect::<Vec<_>>(),
+            vec![Some("a".to_owned()), Some("b".to_owned()), Some("c".to_owned())]);
+        assert_eq!(DataTypeIterator::<u64>::values(counts.get_col("count").unwrap()).unwrap()
+            .map(|x| x.cloned()).collect::<Vec<_>>(),
+            vec![Some(3), Some(1), Some(1)]);
+    }
+}
@@ -0,0 +1,304 @@
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use failure::Error;
+use std::fmt;
+
+use dataframe::{Array, DataFrame};
+use decimal::{check_fits, format_decimal, MAX_PRECISION};
+
+/// A single cell of any dtype, for when the type is only known at
+/// runtime. Categorical cells are `Str`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Int8(i8),
+    Int16(i16),
+    Int32(i32),
+    Int64(i64),
+    UInt8(u8),
+    UInt16(u16),
+    UInt32(u32),
+    UInt64(u64),
+    Float32(f32),
+    Float64(f64),
+    Bool(bool),
+    Str(String),
+    Date(NaiveDate),
+    /// As stored: a UTC instant if the array has an offset
+    Datetime(NaiveDateTime),
+    Duration(Duration),
+    /// Unscaled value and scale, so `Decimal(12345, 2)` is 123.45
+    Decimal(i128, u8),
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        *self == Value::Null
+    }
+
+    /// The dtype of an `Array` that holds this value, as accepted by
+    /// `Array::new`. Decimals get the maximum precision. None for
+    /// `Null`.
+    pub fn dtype(&self) -> Option<String> {
+        use self::Value::*;
+        let dtype = match *self {
+            Null => return None,
+            Int8(_) => "Int8",
+            Int16(_) => "Int16",
+            Int32(_) => "Int32",
+            Int64(_) => "Int64",
+            UInt8(_) => "UInt8",
+            UInt16(_) => "UInt16",
+            UInt32(_) => "UInt32",
+            UInt64(_) => "UInt64",
+            Float32(_) => "Float32",
+            Float64(_) => "Float64",
+            Bool(_) => "Bool",
+            Str(_) => "Str",
+            Date(_) => "Date",
+            Datetime(_) => "Datetime",
+            Duration(_) => "Duration",
+            Decimal(_, scale) => return Some(format!("Decimal({},{})", MAX_PRECISION, scale)),
+        };
+        Some(dtype.to_owned())
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::Value::*;
+        match *self {
+            Null => write!(f, "null"),
+            Int8(x) => write!(f, "{}", x),
+            Int16(x) => write!(f, "{}", x),
+            Int32(x) => write!(f, "{}", x),
+            Int64(x) => write!(f, "{}", x),
+            UInt8(x) => write!(f, "{}", x),
+            UInt16(x) => write!(f, "{}", x),
+            UInt32(x) => write!(f, "{}", x),
+            UInt64(x) => write!(f, "{}", x),
+            Float32(x) => write!(f, "{}", x),
+            Float64(x) => write!(f, "{}", x),
+            Bool(x) => write!(f, "{}", x),
+            Str(ref x) => write!(f, "{}", x),
+            Date(x) => write!(f, "{}", x),
+            Datetime(x) => write!(f, "{}", x),
+            Duration(x) => write!(f, "{}", x),
+            Decimal(x, scale) => write!(f, "{}", format_decimal(x, scale)),
+        }
+    }
+}
+
+impl Array {
+    /// The cell at `index` as a `Value` (`Value::Null` for a null), or
+    /// None if `index` is out of bounds.
+    pub fn get_value(&self, index: usize) -> Option<Value> {
+        macro_rules! value_at {
+            ($data:expr, $variant:path) => {
+                $data.get(index).map(|v| v.map_or(Value::Null, |v| $variant(v.clone())))
+            };
+        }
+        match *self {
+            Array::Int8(ref array_data) => value_at!(array_data, Value::Int8),
+            Array::Int16(ref array_data) => value_at!(array_data, Value::Int16),
+            Array::Int32(ref array_data) => value_at!(array_data, Value::Int32),
+            Array::Int64(ref array_data) => value_at!(array_data, Value::Int64),
+            Array::UInt8(ref array_data) => value_at!(array_data, Value::UInt8),
+            Array::UInt16(ref array_data) => value_at!(array_data, Value::UInt16),
+            Array::UInt32(ref array_data) => value_at!(array_data, Value::UInt32),
+            Array::UInt64(ref array_data) => value_at!(array_data, Value::UInt64),
+            Array::Float32(ref array_data) => value_at!(array_data, Value::Float32),
+            Array::Float64(ref array_data) => value_at!(array_data, Value::Float64),
+            Array::Bool(ref array_data) => value_at!(array_data, Value::Bool),
+            Array::Str(ref array_data) => value_at!(array_data, Value::Str),
+            Array::Categorical(ref cat_data) => value_at!(cat_data, Value::Str),
+            Array::Date(ref array_data) => value_at!(array_data, Value::Date),
+            Array::Datetime(ref dt_data) => value_at!(dt_data, Value::Datetime),
+            Array::Duration(ref array_data) => value_at!(array_data, Value::Duration),
+            Array::Decimal(ref dec_data) => {
+                let scale = dec_data.scale();
+                dec_data.get(index).map(|v| v.map_or(Value::Null, |&v| Value::Decimal(v, scale)))
+            },
+        }
+    }
+
+    /// Errors unless `value` is null or has this array's dtype. A
+    /// Decimal must have the same scale and fit in the precision.
+    pub(crate) fn check_value(&self, value: &Value) -> Result<(), Error> {
+        let matches = match (self, value) {
+            (_, Value::Null) => true,
+            (Array::Int8(_), Value::Int8(_)) => true,
+            (Array::Int16(_), Value::Int16(_)) => true,
+            (Array::Int32(_), Value::Int32(_)) => true,
+            (Array::Int64(_), Value::Int64(_)) => true,
+            (Array::UInt8(_), Value::UInt8(_)) => true,
+            (Array::UInt16(_), Value::UInt16(_)) => true,
+            (Array::UInt32(_), Value::UInt32(_)) => true,
+            (Array::UInt64(_), Value::UInt64(_)) => true,
+            (Array::Float32(_), Value::Float32(_)) => true,
+            (Array::Float64(_), Value::Float64(_)) => true,
+            (Array::Bool(_), Value::Bool(_)) => true,
+            (Array::Str(_), Value::Str(_)) => true,
+            (Array::Categorical(_), Value::Str(_)) => true,
+            (Array::Date(_), Value::Date(_)) => true,
+            (Array::Datetime(_), Value::Datetime(_)) => true,
+            (Array::Duration(_), Value::Duration(_)) => true,
+            (Array::Decimal(dec_data), &Value::Decimal(x, scale)) => {
+                if scale != dec_data.scale() {
+                    return Err(format_err!("decimal scale {} doesn't match array scale {}", scale, dec_data.scale()));
+                }
+                check_fits(x, dec_data.precision())?;
+                true
+            },
+            _ => false,
+        };
+        if matches {
+            Ok(())
+        } else {
+            Err(format_err!("type mismatch, can't push {:?} to {} array", value, self.dtype()))
+        }
+    }
+
+    /// Pushes a `Value`, type-checked against the array's dtype.
+    pub fn push_value(&mut self, value: Value) -> Result<(), Error> {
+        self.check_value(&value)?;
+        match (self, value) {
+            (array, Value::Null) => array.push_null(),
+            (&mut Array::Int8(ref mut array_data), Value::Int8(x)) => array_data.push(x),
+            (&mut Array::Int16(ref mut array_data), Value::Int16(x)) => array_data.push(x),
+            (&mut Array::Int32(ref mut array_data), Value::Int32(x)) => array_data.push(x),
+            (&mut Array::Int64(ref mut array_data), Value::Int64(x)) => array_data.push(x),
+            (&mut Array::UInt8(ref mut array_data), Value::UInt8(x)) => array_data.push(x),
+            (&mut Array::UInt16(ref mut array_data), Value::UInt16(x)) => array_data.push(x),
+            (&mut Array::UInt32(ref mut array_data), Value::UInt32(x)) => array_data.push(x),
+            (&mut Array::UInt64(ref mut array_data), Value::UInt64(x)) => array_data.push(x),
+            (&mut Array::Float32(ref mut array_data), Value::Float32(x)) => array_data.push(x),
+            (&mut Array::Float64(ref mut array_data), Value::Float64(x)) => array_data.push(x),
+            (&mut Array::Bool(ref mut array_data), Value::Bool(x)) => array_data.push(x),
+            (&mut Array::Str(ref mut array_data), Value::Str(x)) => array_data.push(x),
+            (&mut Array::Categorical(ref mut cat_data), Value::Str(x)) => cat_data.push(x),
+            (&mut Array::Date(ref mut array_data), Value::Date(x)) => array_data.push(x),
+            (&mut Array::Datetime(ref mut dt_data), Value::Datetime(x)) => dt_data.push(x),
+            (&mut Array::Duration(ref mut array_data), Value::Duration(x)) => array_data.push(x),
+            (&mut Array::Decimal(ref mut dec_data), Value::Decimal(x, _)) => dec_data.push(x)?,
+            _ => unreachable!("checked by check_value"),
+        }
+        Ok(())
+    }
+}
+
+impl DataFrame {
+    /// The cell at `row` in column `col_name`. Errors if either is
+    /// missing.
+    pub fn get(&self, row: usize, col_name: &str) -> Result<Value, Error> {
+        self.get_col(col_name)
+            .ok_or_else(|| format_err!("column {} not found", col_name))?
+            .get_value(row)
+            .ok_or_else(|| format_err!("row {} out of bounds for column {}", row, col_name))
+    }
+
+    /// Every cell of `row`, in column order. Errors if any column is
+    /// too short.
+    pub fn row(&self, row: usize) -> Result<Vec<Value>, Error> {
+        self.columns.iter()
+            .map(|(name, array)| {
+                array.get_value(row).ok_or_else(|| format_err!("row {} out of bounds for column {}", row, name))
+            })
+            .collect()
+    }
+
+    /// Iterates over rows as `Vec<Value>`. Errors if the columns have
+    /// different lengths.
+    pub fn rows(&self) -> Result<Rows<'_>, Error> {
+        self.columns_of(&[])?;
+        Ok(Rows {
+            df: self,
+            index: 0,
+        })
+    }
+
+    /// Appends a row, one value per column in column order. Nothing is
+    /// pushed unless every value matches its column's dtype.
+    pub fn push_row(&mut self, row: Vec<Value>) -> Result<(), Error> {
+        if row.len() != self.columns.len() {
+            return Err(format_err!("row has {} values, DataFrame has {} columns", row.len(), self.columns.len()));
+        }
+        for ((name, array), value) in self.columns.iter().zip(row.iter()) {
+            array.check_value(value).map_err(|err| format_err!("column {}: {}", name, err))?;
+        }
+        for (array, value) in self.columns.values_mut().zip(row) {
+            array.push_value(value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Iterator over the rows of a DataFrame, made by `DataFrame::rows`.
+pub struct Rows<'a> {
+    df: &'a DataFrame,
+    index: usize,
+}
+
+impl<'a> Iterator for Rows<'a> {
+    type Item = Vec<Value>;
+
+    fn next(&mut self) -> Option<Vec<Value>> {
+        if self.index >= self.df.n_rows() {
+            return None;
+        }
+        let row = self.df.row(self.index).expect("column lengths checked by rows");
+        self.index += 1;
+        Some(row)
+    }
+}
+
+// Every cell of column `name`, for tests comparing whole columns.
+#[cfg(test)]
+pub(crate) fn column_values(df: &DataFrame, name: &str) -> Vec<Value> {
+    (0..df.n_rows()).map(|i| df.get(i, name).unwrap()).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dataframe::ArrayData;
+    use decimal::DecimalData;
+
+    fn sample() -> DataFrame {
+        let mut df = DataFrame::new();
+        df.add_col("id".to_owned(), Array::Int64(ArrayData::from_vec(vec![1, 2])));
+        df.add_col("name".to_owned(), Array::Str(ArrayData::from_options(vec![Some("a".to_owned()), None])).to_categorical().unwrap());
+        df.add_col("price".to_owned(), Array::Decimal(DecimalData::new(ArrayData::from_vec(vec![150, 275]), 5, 2).unwrap()));
+        df
+    }
+
+    #[test]
+    fn test_get_and_rows() {
+        let df = sample();
+        assert_eq!(df.get(0, "name").unwrap(), Value::Str("a".to_owned()));
+        assert_eq!(df.get(1, "name").unwrap(), Value::Null);
+        assert!(df.get(2, "id").is_err());
+        assert!(df.get(0, "missing").is_err());
+
+        assert_eq!(df.row(1).unwrap(), vec![Value::Int64(2), Value::Null, Value::Decimal(275, 2)]);
+        assert_eq!(df.rows().unwrap().count(), 2);
+        assert_eq!(df.get(0, "price").unwrap().to_string(), "1.50");
+
+        let mut ragged = sample();
+        ragged.add_col("short".to_owned(), Array::Int64(ArrayData::from_vec(vec![1])));
+        assert!(ragged.rows().is_err());
+    }
+
+    #[test]
+    fn test_push_row() {
+        let mut df = sample();
+        df.push_row(vec![Value::Int64(3), Value::Str("c".to_owned()), Value::Null]).unwrap();
+        assert_eq!(df.n_rows(), 3);
+        assert_eq!(df.row(2).unwrap(), vec![Value::Int64(3), Value::Str("c".to_owned()), Value::Null]);
+
+        // nothing is pushed when a later value is the wrong type
+        assert!(df.push_row(vec![Value::Int64(4), Value::Null, Value::Float64(1.0)]).is_err());
+        assert!(df.push_row(vec![Value::Int64(4), Value::Null, Value::Decimal(1, 3)]).is_err());
+        assert!(df.push_row(vec![Value::Int64(4)]).is_err());
+        assert_eq!(df.get_col("id").unwrap().len(), 3);
+    }
+}
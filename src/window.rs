@@ -0,0 +1,563 @@
+use failure::Error;
+use rayon::prelude::*;
+
+use dataframe::{Array, ArrayData};
+use decimal::DecimalData;
+
+/// Primitive numbers the window functions work on.
+pub trait Numeric: Copy + PartialOrd + Send + Sync {
+    /// None on integer overflow. Floats never overflow.
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_sub(self, other: Self) -> Option<Self>;
+    fn checked_mul(self, other: Self) -> Option<Self>;
+    fn to_f64(self) -> f64;
+}
+
+macro_rules! impl_numeric_int {
+    ($t:ty) => {
+        impl Numeric for $t {
+            fn checked_add(self, other: Self) -> Option<Self> {
+                <$t>::checked_add(self, other)
+            }
+            fn checked_sub(self, other: Self) -> Option<Self> {
+                <$t>::checked_sub(self, other)
+            }
+            fn checked_mul(self, other: Self) -> Option<Self> {
+                <$t>::checked_mul(self, other)
+            }
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+        }
+    };
+}
+
+macro_rules! impl_numeric_float {
+    ($t:ty) => {
+        impl Numeric for $t {
+            fn checked_add(self, other: Self) -> Option<Self> {
+                Some(self + other)
+            }
+            fn checked_sub(self, other: Self) -> Option<Self> {
+                Some(self - other)
+            }
+            fn checked_mul(self, other: Self) -> Option<Self> {
+                Some(self * other)
+            }
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+        }
+    };
+}
+
+impl_numeric_int!(i8);
+impl_numeric_int!(i16);
+impl_numeric_int!(i32);
+impl_numeric_int!(i64);
+impl_numeric_int!(u8);
+impl_numeric_int!(u16);
+impl_numeric_int!(u32);
+impl_numeric_int!(u64);
+// the unscaled values of a Decimal
+impl_numeric_int!(i128);
+impl_numeric_float!(f32);
+impl_numeric_float!(f64);
+
+// Runs `$body` on the ArrayData of any numeric variant, wrapping the
+// result back up in the same variant.
+macro_rules! map_numeric {
+    ($array:expr, $op:expr, |$data:ident| $body:expr) => {
+        match *$array {
+            Array::Int8(ref $data) => Array::Int8($body),
+            Array::Int16(ref $data) => Array::Int16($body),
+            Array::Int32(ref $data) => Array::Int32($body),
+            Array::Int64(ref $data) => Array::Int64($body),
+            Array::UInt8(ref $data) => Array::UInt8($body),
+            Array::UInt16(ref $data) => Array::UInt16($body),
+            Array::UInt32(ref $data) => Array::UInt32($body),
+            Array::UInt64(ref $data) => Array::UInt64($body),
+            Array::Float32(ref $data) => Array::Float32($body),
+            Array::Float64(ref $data) => Array::Float64($body),
+            _ => return Err(format_err!("can't {} {}, array must be numeric", $op, $array.dtype())),
+        }
+    };
+}
+
+fn to_f64s<T: Numeric>(array_data: &ArrayData<T>) -> Vec<Option<f64>> {
+    array_data.iter().map(|x| x.map(|x| x.to_f64())).collect()
+}
+
+// Running fold over the non-null values. Null rows stay null and
+// don't reset the running value.
+fn cumulative<T, F>(array_data: &ArrayData<T>, op: &str, f: F) -> Result<ArrayData<T>, Error>
+    where T: Numeric + Clone + Default,
+          F: Fn(T, T) -> Option<T>,
+{
+    let mut acc = None;
+    let values = array_data.iter()
+        .map(|x| match x {
+            Some(&x) => {
+                let next = match acc {
+                    Some(acc) => f(acc, x).ok_or_else(|| format_err!("overflow in {}", op))?,
+                    None => x,
+                };
+                acc = Some(next);
+                Ok(Some(next))
+            },
+            None => Ok(None),
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    Ok(ArrayData::from_options(values))
+}
+
+// New unscaled `values` for a Decimal, keeping its precision and
+// scale. Errors if a value doesn't fit.
+fn with_decimal_values(dec_data: &DecimalData, op: &str, values: ArrayData<i128>) -> Result<Array, Error> {
+    let dec_data = DecimalData::new(values, dec_data.precision(), dec_data.scale())
+        .map_err(|err| format_err!("overflow in {}: {}", op, err))?;
+    Ok(Array::Decimal(dec_data))
+}
+
+// Like `cumulative`, on the unscaled values of a Decimal, keeping its
+// precision and scale. Errors if `f` does, or a result doesn't fit.
+fn cumulative_decimal<F>(dec_data: &DecimalData, op: &str, f: F) -> Result<Array, Error>
+    where F: Fn(i128, i128) -> Result<i128, Error>,
+{
+    let mut acc = None;
+    let values = dec_data.values().iter()
+        .map(|x| match x {
+            Some(&x) => {
+                let next = match acc {
+                    Some(acc) => f(acc, x)?,
+                    None => x,
+                };
+                acc = Some(next);
+                Ok(Some(next))
+            },
+            None => Ok(None),
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    with_decimal_values(dec_data, op, ArrayData::from_options(values))
+}
+
+// Difference of each row with the row `periods` before, null where
+// either is null or out of range.
+fn diff_values<T>(array_data: &ArrayData<T>, periods: i64) -> Result<ArrayData<T>, Error>
+    where T: Numeric + Clone + Default,
+{
+    let len = array_data.len();
+    let diffs = (0..len)
+        .map(|i| {
+            let prev = lagged(i, periods, len).and_then(|j| array_data.get(j)).and_then(|x| x);
+            match (array_data.get(i).and_then(|x| x), prev) {
+                (Some(&x), Some(&prev)) => x.checked_sub(prev).map(Some).ok_or_else(|| format_err!("overflow in diff")),
+                _ => Ok(None),
+            }
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    Ok(ArrayData::from_options(diffs))
+}
+
+// Folds the non-null values of each window with `f`. Rows whose
+// window has fewer than `min_periods` values are null.
+fn rolling_fold<T, F>(array_data: &ArrayData<T>, window: usize, min_periods: usize, op: &str, f: F)
+    -> Result<ArrayData<T>, Error>
+    where T: Numeric + Clone + Default,
+          F: Fn(T, T) -> Option<T> + Sync + Send,
+{
+    let values: Vec<Option<T>> = array_data.iter().map(|x| x.cloned()).collect();
+    let results = (0..values.len()).into_par_iter()
+        .map(|i| {
+            let start = (i + 1).saturating_sub(window);
+            let xs: Vec<T> = values[start..=i].iter().filter_map(|x| *x).collect();
+            if xs.len() < min_periods {
+                return Ok(None);
+            }
+            xs[1..].iter()
+                .try_fold(xs[0], |acc, &x| f(acc, x))
+                .map(Some)
+                .ok_or_else(|| format_err!("overflow in rolling {}", op))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    Ok(ArrayData::from_options(results))
+}
+
+// The aggregates of `Rolling` that keep the dtype.
+#[derive(Debug, Clone, Copy)]
+enum Fold {
+    Sum,
+    Min,
+    Max,
+}
+
+impl Fold {
+    fn name(self) -> &'static str {
+        match self {
+            Fold::Sum => "sum",
+            Fold::Min => "min",
+            Fold::Max => "max",
+        }
+    }
+
+    fn apply<T: Numeric>(self, a: T, b: T) -> Option<T> {
+        match self {
+            Fold::Sum => a.checked_add(b),
+            Fold::Min => min(a, b),
+            Fold::Max => max(a, b),
+        }
+    }
+}
+
+fn min<T: Numeric>(a: T, b: T) -> Option<T> {
+    Some(if b < a { b } else { a })
+}
+
+fn max<T: Numeric>(a: T, b: T) -> Option<T> {
+    Some(if b > a { b } else { a })
+}
+
+// Index of the row `periods` before `i`, if there is one.
+fn lagged(i: usize, periods: i64, len: usize) -> Option<usize> {
+    match (i as i64).checked_sub(periods) {
+        Some(j) if j >= 0 && (j as usize) < len => Some(j as usize),
+        _ => None,
+    }
+}
+
+impl Array {
+    // The values of a numeric array as f64, nulls as None. Decimals are
+    // their unscaled value over 10^scale.
+    pub(crate) fn numeric_values(&self, op: &str) -> Result<Vec<Option<f64>>, Error> {
+        use self::Array::*;
+        Ok(match *self {
+            Int8(ref array_data) => to_f64s(array_data),
+            Int16(ref array_data) => to_f64s(array_data),
+            Int32(ref array_data) => to_f64s(array_data),
+            Int64(ref array_data) => to_f64s(array_data),
+            UInt8(ref array_data) => to_f64s(array_data),
+            UInt16(ref array_data) => to_f64s(array_data),
+            UInt32(ref array_data) => to_f64s(array_data),
+            UInt64(ref array_data) => to_f64s(array_data),
+            Float32(ref array_data) => to_f64s(array_data),
+            Float64(ref array_data) => to_f64s(array_data),
+            Decimal(ref dec_data) => {
+                let divisor = 10f64.powi(i32::from(dec_data.scale()));
+                dec_data.values().iter().map(|x| x.map(|&x| x as f64 / divisor)).collect()
+            },
+            _ => return Err(format_err!("can't {} {}, array must be numeric", op, self.dtype())),
+        })
+    }
+
+    /// Moves rows down by `periods` (up, if negative), keeping the
+    /// length. Rows shifted in from outside the array are null. Works
+    /// on any dtype.
+    pub fn shift(&self, periods: i64) -> Array {
+        let len = self.len();
+        let indices: Vec<_> = (0..len).map(|i| lagged(i, periods, len)).collect();
+        self.take_opt(&indices)
+    }
+
+    /// Difference with the row `periods` before, keeping the dtype.
+    /// Null where either row is null or out of range. Errors on integer
+    /// overflow (including a negative difference of unsigned ints), or
+    /// a Decimal difference outgrowing its precision.
+    pub fn diff(&self, periods: i64) -> Result<Array, Error> {
+        if let Array::Decimal(ref dec_data) = *self {
+            return with_decimal_values(dec_data, "diff", diff_values(dec_data.values(), periods)?);
+        }
+        Ok(map_numeric!(self, "diff", |array_data| diff_values(array_data, periods)?))
+    }
+
+    /// Fractional change from the row `periods` before, as Float64.
+    /// Null where either row is null or out of range; a change from 0
+    /// is infinite (or NaN for 0 to 0).
+    pub fn pct_change(&self, periods: i64) -> Result<Array, Error> {
+        let values = self.numeric_values("pct_change")?;
+        let len = values.len();
+        let changes = (0..len)
+            .map(|i| match (values[i], lagged(i, periods, len).and_then(|j| values[j])) {
+                (Some(x), Some(prev)) => Some(x / prev - 1.0),
+                _ => None,
+            })
+            .collect();
+        Ok(Array::Float64(ArrayData::from_options(changes)))
+    }
+
+    /// Running sum, keeping the dtype. Nulls are skipped (and stay
+    /// null). Errors on integer overflow, or a Decimal sum outgrowing
+    /// its precision.
+    pub fn cumsum(&self) -> Result<Array, Error> {
+        if let Array::Decimal(ref dec_data) = *self {
+            return cumulative_decimal(dec_data, "cumsum", |a, b| {
+                a.checked_add(b).ok_or_else(|| format_err!("overflow in cumsum"))
+            });
+        }
+        Ok(map_numeric!(self, "cumsum", |array_data| cumulative(array_data, "cumsum", Numeric::checked_add)?))
+    }
+
+    /// Running product, keeping the dtype. Nulls are skipped (and stay
+    /// null). Errors on integer overflow. A Decimal product must be
+    /// exact at the array's scale; round to a larger scale first if
+    /// it isn't.
+    pub fn cumprod(&self) -> Result<Array, Error> {
+        if let Array::Decimal(ref dec_data) = *self {
+            let divisor = 10i128.pow(u32::from(dec_data.scale()));
+            return cumulative_decimal(dec_data, "cumprod", |a, b| {
+                let product = a.checked_mul(b).ok_or_else(|| format_err!("overflow in cumprod"))?;
+                if product % divisor != 0 {
+                    return Err(format_err!("cumprod has more than {} decimal places", dec_data.scale()));
+                }
+                Ok(product / divisor)
+            });
+        }
+        Ok(map_numeric!(self, "cumprod", |array_data| cumulative(array_data, "cumprod", Numeric::checked_mul)?))
+    }
+
+    /// Running minimum, keeping the dtype. Nulls are skipped (and stay
+    /// null).
+    pub fn cummin(&self) -> Result<Array, Error> {
+        if let Array::Decimal(ref dec_data) = *self {
+            return cumulative_decimal(dec_data, "cummin", |a, b| Ok(a.min(b)));
+        }
+        Ok(map_numeric!(self, "cummin", |array_data| cumulative(array_data, "cummin", min)?))
+    }
+
+    /// Running maximum, keeping the dtype. Nulls are skipped (and stay
+    /// null).
+    pub fn cummax(&self) -> Result<Array, Error> {
+        if let Array::Decimal(ref dec_data) = *self {
+            return cumulative_decimal(dec_data, "cummax", |a, b| Ok(a.max(b)));
+        }
+        Ok(map_numeric!(self, "cummax", |array_data| cumulative(array_data, "cummax", max)?))
+    }
+
+    /// Aggregates over a window of the current row and the
+    /// `window - 1` rows before it. Errors if the array isn't numeric
+    /// or `window` is 0.
+    ///
+    /// ```
+    /// # extern crate llamas2;
+    /// # use llamas2::dataframe::{Array, ArrayData};
+    /// # use llamas2::value::Value;
+    /// # fn main() {
+    /// let array = Array::Float64(ArrayData::from_vec(vec![1.0, 2.0, 6.0]));
+    /// let avg = array.rolling(3).unwrap().min_periods(1).mean().unwrap();
+    /// assert_eq!(avg.get_value(1), Some(Value::Float64(1.5)));
+    /// assert_eq!(avg.get_value(2), Some(Value::Float64(3.0)));
+    /// # }
+    /// ```
+    pub fn rolling(&self, window: usize) -> Result<Rolling, Error> {
+        if window == 0 {
+            return Err(format_err!("rolling window must be at least 1"));
+        }
+        Ok(Rolling {
+            array: self.clone(),
+            values: self.numeric_values("rolling")?,
+            window,
+            min_periods: window,
+        })
+    }
+}
+
+/// A numeric array windowed by `Array::rolling`. Each aggregate
+/// returns an array of the same length: `sum`, `min` and `max` keep
+/// the dtype, `mean` and `std` are Float64.
+#[derive(Debug, Clone)]
+pub struct Rolling {
+    array: Array,
+    values: Vec<Option<f64>>,
+    window: usize,
+    min_periods: usize,
+}
+
+impl Rolling {
+    /// Non-null values a window needs for a result; otherwise the row
+    /// is null. Defaults to the window size.
+    pub fn min_periods(mut self, min_periods: usize) -> Self {
+        self.min_periods = min_periods;
+        self
+    }
+
+    /// Errors on integer overflow, or a Decimal sum outgrowing its
+    /// precision.
+    pub fn sum(&self) -> Result<Array, Error> {
+        self.fold(Fold::Sum)
+    }
+
+    pub fn mean(&self) -> Result<Array, Error> {
+        self.aggregate(|xs| Some(xs.iter().sum::<f64>() / xs.len() as f64))
+    }
+
+    pub fn min(&self) -> Result<Array, Error> {
+        self.fold(Fold::Min)
+    }
+
+    pub fn max(&self) -> Result<Array, Error> {
+        self.fold(Fold::Max)
+    }
+
+    /// Sample standard deviation. Null for windows with fewer than two
+    /// values.
+    pub fn std(&self) -> Result<Array, Error> {
+        self.aggregate(|xs| {
+            if xs.len() < 2 {
+                return None;
+            }
+            let mean = xs.iter().sum::<f64>() / xs.len() as f64;
+            let var = xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (xs.len() - 1) as f64;
+            Some(var.sqrt())
+        })
+    }
+
+    fn check_min_periods(&self) -> Result<(), Error> {
+        if self.min_periods == 0 || self.min_periods > self.window {
+            return Err(format_err!(
+                "min_periods must be between 1 and the window size {}, got {}",
+                self.window, self.min_periods
+            ));
+        }
+        Ok(())
+    }
+
+    // `rolling_fold` on the array's own values, keeping the dtype.
+    // Decimals fold their unscaled values.
+    fn fold(&self, fold: Fold) -> Result<Array, Error> {
+        self.check_min_periods()?;
+        let (window, min_periods, op) = (self.window, self.min_periods, fold.name());
+        if let Array::Decimal(ref dec_data) = self.array {
+            let values = rolling_fold(dec_data.values(), window, min_periods, op, |a, b| fold.apply(a, b))?;
+            return with_decimal_values(dec_data, &format!("rolling {}", op), values);
+        }
+        Ok(map_numeric!(&self.array, "rolling", |array_data| {
+            rolling_fold(array_data, window, min_periods, op, |a, b| fold.apply(a, b))?
+        }))
+    }
+
+    // `f` gets the non-null values of each window, and is only called
+    // when there are at least `min_periods` of them.
+    fn aggregate<F>(&self, f: F) -> Result<Array, Error>
+        where F: Fn(&[f64]) -> Option<f64> + Sync + Send
+    {
+        self.check_min_periods()?;
+        let results = (0..self.values.len()).into_par_iter()
+            .map(|i| {
+                let start = (i + 1).saturating_sub(self.window);
+                let xs: Vec<f64> = self.values[start..=i].iter().filter_map(|x| *x).collect();
+                if xs.len() < self.min_periods {
+                    None
+                } else {
+                    f(&xs)
+                }
+            })
+            .collect();
+        Ok(Array::Float64(ArrayData::from_options(results)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use dataframe::DataTypeIterator;
+    use decimal::RoundingMode;
+
+    fn floats(array: &Array) -> Vec<Option<f64>> {
+        DataTypeIterator::<f64>::values(array).unwrap().map(|x| x.cloned()).collect()
+    }
+
+    fn ints(array: &Array) -> Vec<Option<i64>> {
+        DataTypeIterator::<i64>::values(array).unwrap().map(|x| x.cloned()).collect()
+    }
+
+    #[test]
+    fn test_shift_and_diff() {
+        let array = Array::Int64(ArrayData::from_options(vec![Some(10), Some(15), None, Some(16)]));
+        assert_eq!(ints(&array.shift(1)), vec![None, Some(10), Some(15), None]);
+        assert_eq!(array.shift(-3).null_count(), 3);
+
+        assert_eq!(ints(&array.diff(1).unwrap()), vec![None, Some(5), None, None]);
+        assert_eq!(floats(&array.pct_change(1).unwrap())[1], Some(0.5));
+        let unsigned = Array::UInt8(ArrayData::from_vec(vec![5, 3]));
+        assert_eq!(unsigned.diff(1).unwrap_err().to_string(), "overflow in diff");
+
+        let strs = Array::Str(ArrayData::from_vec(vec!["a".to_owned(), "b".to_owned()]));
+        assert_eq!(strs.shift(1).null_count(), 1);
+        assert!(strs.diff(1).is_err());
+    }
+
+    #[test]
+    fn test_cumulative() {
+        let array = Array::Int32(ArrayData::from_options(vec![Some(3), None, Some(1), Some(4)]));
+        let values = |array: Array| DataTypeIterator::<i32>::values(&array).unwrap().map(|x| x.cloned()).collect::<Vec<_>>();
+        assert_eq!(values(array.cumsum().unwrap()), vec![Some(3), None, Some(4), Some(8)]);
+        assert_eq!(values(array.cumprod().unwrap()), vec![Some(3), None, Some(3), Some(12)]);
+        assert_eq!(values(array.cummin().unwrap()), vec![Some(3), None, Some(1), Some(1)]);
+        assert_eq!(values(array.cummax().unwrap()), vec![Some(3), None, Some(3), Some(4)]);
+
+        let big = Array::UInt8(ArrayData::from_vec(vec![200, 100]));
+        assert!(big.cumsum().is_err());
+    }
+
+    #[test]
+    fn test_rolling() {
+        let array = Array::Float64(ArrayData::from_options(vec![Some(1.0), Some(2.0), None, Some(4.0), Some(6.0)]));
+        assert_eq!(floats(&array.rolling(2).unwrap().sum().unwrap()), vec![None, Some(3.0), None, None, Some(10.0)]);
+        assert_eq!(floats(&array.rolling(2).unwrap().min_periods(1).mean().unwrap()),
+            vec![Some(1.0), Some(1.5), Some(2.0), Some(4.0), Some(5.0)]);
+        assert_eq!(floats(&array.rolling(3).unwrap().min_periods(1).max().unwrap())[2], Some(2.0));
+        assert_eq!(floats(&array.rolling(3).unwrap().min_periods(1).std().unwrap()),
+            vec![None, Some(0.5f64.sqrt()), Some(0.5f64.sqrt()), Some(2.0f64.sqrt()), Some(2.0f64.sqrt())]);
+
+        let counts = Array::Int64(ArrayData::from_options(vec![Some(3), None, Some(1), Some(4)]));
+        assert_eq!(ints(&counts.rolling(2).unwrap().min_periods(1).sum().unwrap()), vec![Some(3), Some(3), Some(1), Some(5)]);
+        assert_eq!(ints(&counts.rolling(3).unwrap().min_periods(1).min().unwrap()), vec![Some(3), Some(3), Some(1), Some(1)]);
+        assert_eq!(counts.rolling(3).unwrap().max().unwrap().dtype(), "Int64");
+        assert_eq!(floats(&counts.rolling(1).unwrap().mean().unwrap())[0], Some(3.0));
+        let huge = Array::Int64(ArrayData::from_vec(vec![1, i64::MAX]));
+        assert_eq!(huge.rolling(2).unwrap().sum().unwrap_err().to_string(), "overflow in rolling sum");
+
+        assert!(array.rolling(0).is_err());
+        assert!(array.rolling(2).unwrap().min_periods(3).sum().is_err());
+    }
+
+    #[test]
+    fn test_decimal() {
+        let strs = vec![Some("1.50"), None, Some("2.25"), Some("-0.75")].into_iter().map(|s| s.map(|s| s.to_owned())).collect();
+        let array = Array::Str(ArrayData::from_options(strs)).parse_decimal(6, 2).unwrap();
+        let decimals = |array: Array| match array {
+            Array::Decimal(ref dec_data) => {
+                assert_eq!((dec_data.precision(), dec_data.scale()), (6, 2));
+                dec_data.values().iter().map(|x| x.cloned()).collect::<Vec<_>>()
+            },
+            other => panic!("expected Decimal, found {:?}", other),
+        };
+
+        assert_eq!(decimals(array.cumsum().unwrap()), vec![Some(150), None, Some(375), Some(300)]);
+        assert_eq!(decimals(array.cummin().unwrap()), vec![Some(150), None, Some(150), Some(-75)]);
+        assert_eq!(decimals(array.cummax().unwrap()), vec![Some(150), None, Some(225), Some(225)]);
+        // 1.50 * 2.25 = 3.375 needs a third decimal place
+        assert!(array.cumprod().is_err());
+        let array4 = array.round_decimal(4, RoundingMode::HalfEven).unwrap();
+        assert_eq!(array4.slice(0, 3).unwrap().cumprod().unwrap().dtype(), "Decimal(8,4)");
+
+        assert_eq!(decimals(array.diff(2).unwrap()), vec![None, None, Some(75), None]);
+        assert_eq!(decimals(array.rolling(2).unwrap().min_periods(1).sum().unwrap()),
+            vec![Some(150), Some(150), Some(225), Some(150)]);
+        assert_eq!(decimals(array.rolling(3).unwrap().min_periods(1).max().unwrap()),
+            vec![Some(150), Some(150), Some(225), Some(225)]);
+
+        let big = Array::Str(ArrayData::from_vec(vec!["999.99".to_owned(), "0.01".to_owned()])).parse_decimal(5, 2).unwrap();
+        assert!(big.cumsum().is_err());
+        assert!(big.rolling(2).unwrap().sum().is_err());
+        let wide = Array::Str(ArrayData::from_vec(vec!["-999.99".to_owned(), "999.99".to_owned()])).parse_decimal(5, 2).unwrap();
+        assert!(wide.diff(1).is_err());
+    }
+
+    #[test]
+    fn test_shift_extreme_periods() {
+        let array = Array::Int64(ArrayData::from_vec(vec![1, 2, 3]));
+        assert_eq!(array.shift(i64::MIN).null_count(), 3);
+        assert_eq!(array.shift(i64::MAX).null_count(), 3);
+        assert_eq!(ints(&array.diff(i64::MIN).unwrap()), vec![None, None, None]);
+    }
+}